@@ -1,19 +1,34 @@
-use std::{net::SocketAddr, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::Instant,
+};
 
 use axum::middleware::from_fn_with_state;
 use axum::{
     Extension, Router,
     body::Body,
     error_handling::HandleErrorLayer,
-    extract::State,
-    http::{Request, Uri},
+    extract::{Path, Query, State},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, Request, StatusCode, Uri,
+        header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE},
+    },
     middleware::Next,
-    response::{Html, IntoResponse, Response},
-    routing::{get, get_service},
+    response::{
+        Html, IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, get_service, post},
 };
 use axum_oidc::{EmptyAdditionalClaims, OidcAuthLayer, OidcLoginLayer, error::MiddlewareError};
-use minijinja::{Environment, context};
-use tokio::sync::Mutex;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, stream};
+use minijinja::{Environment, Value, context};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, broadcast::error::RecvError, oneshot};
 use tower::ServiceBuilder;
 use tower::{Layer, Service};
 use tower_http::services::ServeDir;
@@ -22,27 +37,697 @@ use tower_sessions::{
     cookie::{SameSite, time::Duration},
 };
 
-use crate::collector::IngressCollectionWrapper;
+use crate::{
+    collector::{
+        CollectionUpdates, Collector, IngressCollection, IngressCollectionWrapper, IngressInfo,
+        LastSuccess, RefreshRequests,
+    },
+    config::Config,
+    locale::Locale,
+    metrics, openapi,
+    websocket::{self, Incoming},
+};
+
+/// Pages always reflect the latest collection unless configured otherwise
+const DEFAULT_HTML_CACHE_CONTROL: &str = "no-store";
+
+/// Shown instead of the page until the first collection finished
+const LOADING_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <meta http-equiv="refresh" content="5">
+    <title>Landingpage</title>
+</head>
+<body>
+    <p>Collecting&hellip;</p>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Default, Deserialize)]
+struct IndexParams {
+    view: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct JsonParams {
+    /// Indent the JSON for reading it in a terminal or browser
+    #[serde(default)]
+    pretty: bool,
+}
+
+/// Filters of `/api/v1/ingresses`, all given filters must match
+#[derive(Debug, Default, Deserialize)]
+struct IngressParams {
+    group: Option<String>,
+    cluster: Option<String>,
+    /// Searched case-insensitively in name, description and URL
+    q: Option<String>,
+    /// Comma-separated list of `key=value` or `key`, like a Kubernetes label selector
+    label: Option<String>,
+    #[serde(default)]
+    pretty: bool,
+}
+
+impl IngressParams {
+    fn matches(&self, entry: &FlatIngressInfo) -> bool {
+        let ingress = &entry.ingress;
+        if self
+            .group
+            .as_ref()
+            .is_some_and(|group| *group != entry.group)
+            || self
+                .cluster
+                .as_ref()
+                .is_some_and(|cluster| *cluster != entry.cluster)
+        {
+            return false;
+        }
+        if let Some(q) = self.q.as_ref().map(|q| q.trim().to_lowercase())
+            && !q.is_empty()
+            && ![&ingress.name, &ingress.description, &ingress.url]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&q))
+        {
+            return false;
+        }
+        self.label
+            .iter()
+            .flat_map(|label| label.split(','))
+            .map(str::trim)
+            .filter(|selector| !selector.is_empty())
+            .all(|selector| match selector.split_once('=') {
+                Some((key, value)) => {
+                    ingress.labels.get(key.trim()) == Some(&value.trim().to_owned())
+                }
+                None => ingress.labels.contains_key(selector),
+            })
+    }
+}
+
+/// An ingress together with the group and cluster it was collected from, used for the flat view
+#[derive(Clone, Debug, Serialize)]
+struct FlatIngressInfo {
+    group: String,
+    cluster: String,
+    #[serde(flatten)]
+    ingress: IngressInfo,
+}
+
+fn flatten_collection(collection: &IngressCollection) -> Vec<FlatIngressInfo> {
+    let mut result: Vec<FlatIngressInfo> = collection
+        .iter()
+        .flat_map(|group| {
+            group.clusters.iter().flat_map(move |cluster| {
+                cluster
+                    .ingresses
+                    .iter()
+                    .map(move |ingress| FlatIngressInfo {
+                        group: group.name.clone(),
+                        cluster: cluster.name.clone(),
+                        ingress: ingress.clone(),
+                    })
+            })
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        a.ingress
+            .name
+            .to_lowercase()
+            .cmp(&b.ingress.name.to_lowercase())
+            .then_with(|| a.ingress.url.cmp(&b.ingress.url))
+    });
+    result
+}
+
+/// The different pages rendered from the template, available as `view` in the template context
+#[derive(Clone, Copy, Debug)]
+enum View {
+    Grouped,
+    Flat,
+    /// Overview of the groups with their counts, used instead of `Grouped` for large collections
+    Summary,
+    /// A single group on `/group/{name}`
+    Group,
+}
+
+impl View {
+    fn name(&self) -> &'static str {
+        match self {
+            View::Grouped => "grouped",
+            View::Flat => "flat",
+            View::Summary => "summary",
+            View::Group => "group",
+        }
+    }
+}
+
+/// Number of ingresses in the collection including the ones hidden by `maxIngressesPerCluster`
+fn entry_count(collection: &IngressCollection) -> usize {
+    collection
+        .iter()
+        .flat_map(|group| group.clusters.iter())
+        .map(|cluster| cluster.ingresses.len() + cluster.hidden_count)
+        .sum()
+}
+
+/// Returns a copy of the collection without the ingresses that are expired at the given time
+fn without_expired(collection: &IngressCollection, now: DateTime<Utc>) -> IngressCollection {
+    let mut collection = collection.clone();
+    for group in collection.iter_mut() {
+        for cluster in group.clusters.iter_mut() {
+            cluster
+                .ingresses
+                .retain(|ingress| ingress.expires.is_none_or(|expires| expires > now));
+        }
+    }
+    collection
+}
+
+/// The compiled template and the settings of the pages
+struct PageTemplate {
+    env: Environment<'static>,
+    /// Additional key the collection is available under besides `groups`
+    context_root_key: Option<String>,
+    /// The index shows the summary instead of all groups above this number of ingresses
+    summarize_above_count: Option<usize>,
+    cache_rendered_page: bool,
+    cache_control: HeaderValue,
+    swagger_ui: bool,
+}
+
+/// The current template, replaced whenever the collector is restarted with a changed config
+#[derive(Clone)]
+struct TemplateEnvironment(Arc<std::sync::RwLock<Arc<PageTemplate>>>);
+
+impl TemplateEnvironment {
+    fn new(template_env: PageTemplate) -> Self {
+        Self(Arc::new(std::sync::RwLock::new(Arc::new(template_env))))
+    }
+
+    fn current(&self) -> Arc<PageTemplate> {
+        self.0.read().unwrap().clone()
+    }
+
+    fn replace(&self, template_env: PageTemplate) {
+        *self.0.write().unwrap() = Arc::new(template_env);
+    }
+}
+
+/// Compiles the main template once so it does not have to be parsed on every request
+fn template_environment(template: &'static str, config: &Config) -> PageTemplate {
+    let global = config.global.as_ref();
+    let locale = global.and_then(|g| g.locale.clone()).unwrap_or_default();
+    let mut template_env = Environment::new();
+    add_locale_filters(&mut template_env, &locale);
+    template_env.add_global(
+        "show_resource_name",
+        global.is_some_and(|g| g.show_resource_name),
+    );
+    template_env.add_global("live_updates", global.is_some_and(|g| g.live_updates));
+    template_env.add_template("main", template).unwrap();
+    let cache_control = global
+        .and_then(|g| g.html_cache_control.as_deref())
+        .unwrap_or(DEFAULT_HTML_CACHE_CONTROL);
+    PageTemplate {
+        env: template_env,
+        context_root_key: global.and_then(|g| g.context_root_key.clone()),
+        summarize_above_count: global.and_then(|g| g.summarize_above_count),
+        cache_rendered_page: global.is_none_or(|g| g.cache_rendered_page),
+        cache_control: HeaderValue::from_str(cache_control)
+            .expect("htmlCacheControl is checked by Config::validate"),
+        swagger_ui: global.is_some_and(|g| g.swagger_ui),
+    }
+}
+
+/// Compiles the template again whenever the collector is restarted with a changed config
+async fn reload_template(
+    template: &'static str,
+    template_env: TemplateEnvironment,
+    page_cache: PageCache,
+    mut configs: tokio::sync::watch::Receiver<Arc<Config>>,
+) {
+    while configs.changed().await.is_ok() {
+        let config = configs.borrow_and_update().clone();
+        template_env.replace(template_environment(template, &config));
+        page_cache.clear();
+    }
+}
+
+fn render(
+    template_env: &PageTemplate,
+    collection: &IngressCollection,
+    view: View,
+    now: DateTime<Utc>,
+) -> Html<String> {
+    let collection = &without_expired(collection, now);
+    metrics::TEMPLATE_RENDERS.inc();
+    let template = template_env.env.get_template("main").unwrap();
+    let view = match view {
+        View::Grouped
+            if template_env
+                .summarize_above_count
+                .is_some_and(|limit| entry_count(collection) > limit) =>
+        {
+            View::Summary
+        }
+        view => view,
+    };
+    let ctx = match view {
+        View::Flat => context! {
+            view => view.name(),
+            groups => collection,
+            ingresses => flatten_collection(collection),
+        },
+        _ => context! { view => view.name(), groups => collection },
+    };
+    let ctx = match template_env.context_root_key.as_ref() {
+        Some(key) => context! {
+            ..ctx,
+            ..Value::from_iter([(key.clone(), Value::from_serialize(collection))])
+        },
+        None => ctx,
+    };
+    Html(template.render(ctx).unwrap())
+}
+
+/// Rendered HTML of the default view, cleared whenever the collector publishes new data
+#[derive(Clone, Default)]
+struct PageCache(Arc<std::sync::Mutex<Option<CachedPage>>>);
+
+struct CachedPage {
+    html: String,
+    /// The next time an ingress expires, after which the page must be rendered again
+    valid_until: Option<DateTime<Utc>>,
+}
+
+impl PageCache {
+    fn get(&self, now: DateTime<Utc>) -> Option<String> {
+        let cache = self.0.lock().unwrap();
+        cache
+            .as_ref()
+            .filter(|page| page.valid_until.is_none_or(|valid_until| now < valid_until))
+            .map(|page| page.html.clone())
+    }
+
+    fn set(&self, html: String, valid_until: Option<DateTime<Utc>>) {
+        *self.0.lock().unwrap() = Some(CachedPage { html, valid_until });
+    }
+
+    fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+async fn invalidate_page_cache(page_cache: PageCache, updates: CollectionUpdates) {
+    let mut receiver = updates.subscribe();
+    loop {
+        match receiver.recv().await {
+            Ok(_) | Err(RecvError::Lagged(_)) => page_cache.clear(),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Returns the earliest expiry of an ingress after `now`
+fn next_expiry(collection: &IngressCollection, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    collection
+        .iter()
+        .flat_map(|group| group.clusters.iter())
+        .flat_map(|cluster| cluster.ingresses.iter())
+        .filter_map(|ingress| ingress.expires)
+        .filter(|expires| *expires > now)
+        .min()
+}
 
+/// Adds the filters `format_number` and `format_datetime` that format values according to the configured locale
+fn add_locale_filters(template_env: &mut Environment, locale: &Locale) {
+    let number_locale = locale.clone();
+    template_env.add_filter("format_number", move |value: f64| {
+        number_locale.format_number(value)
+    });
+    let datetime_locale = locale.clone();
+    template_env.add_filter(
+        "format_datetime",
+        move |value: String| -> Result<String, minijinja::Error> {
+            let datetime = DateTime::parse_from_rfc3339(&value).map_err(|err| {
+                minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, err.to_string())
+            })?;
+            Ok(datetime_locale.format_datetime(&datetime.to_utc()))
+        },
+    );
+}
+
+#[tracing::instrument(skip_all)]
 async fn index(
+    Query(params): Query<IndexParams>,
     Extension(collection): Extension<IngressCollectionWrapper>,
-    Extension(template): Extension<String>,
-) -> Html<String> {
-    let mut template_env = Environment::new();
-    template_env.add_template("main", &template).unwrap();
-    let template = template_env.get_template("main").unwrap();
+    Extension(template_env): Extension<TemplateEnvironment>,
+    Extension(page_cache): Extension<PageCache>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Response {
+    if last_success.read().await.is_none() {
+        return loading_page();
+    }
+    let template_env = template_env.current();
+    let now = Utc::now();
+    // Hold the lock while rendering so the cache is not filled with outdated data
+    let collection = collection.read().await;
+    if params.view.as_deref() == Some("flat") {
+        return render(&template_env, &collection, View::Flat, now).into_response();
+    }
+    if !template_env.cache_rendered_page {
+        return render(&template_env, &collection, View::Grouped, now).into_response();
+    }
+    if let Some(html) = page_cache.get(now) {
+        metrics::PAGE_CACHE_HITS.inc();
+        return Html(html).into_response();
+    }
+    metrics::PAGE_CACHE_MISSES.inc();
+    let html = render(&template_env, &collection, View::Grouped, now);
+    page_cache.set(html.0.clone(), next_expiry(&collection, now));
+    html.into_response()
+}
+
+#[tracing::instrument(skip_all)]
+async fn all(
+    Extension(collection): Extension<IngressCollectionWrapper>,
+    Extension(template_env): Extension<TemplateEnvironment>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Response {
+    if last_success.read().await.is_none() {
+        return loading_page();
+    }
     let collection = collection.read().await;
-    Html(
-        template
-            .render(context! { groups => collection.clone()})
-            .unwrap(),
+    render(&template_env.current(), &collection, View::Flat, Utc::now()).into_response()
+}
+
+#[tracing::instrument(skip_all)]
+async fn group(
+    Path(name): Path<String>,
+    Extension(collection): Extension<IngressCollectionWrapper>,
+    Extension(template_env): Extension<TemplateEnvironment>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Response {
+    if last_success.read().await.is_none() {
+        return loading_page();
+    }
+    let collection = collection.read().await;
+    let Some(group) = collection.iter().find(|group| group.name == name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    render(
+        &template_env.current(),
+        &vec![group.clone()],
+        View::Group,
+        Utc::now(),
+    )
+    .into_response()
+}
+
+/// The page reloads itself until data is available
+fn loading_page() -> Response {
+    (StatusCode::SERVICE_UNAVAILABLE, Html(LOADING_PAGE)).into_response()
+}
+
+fn json_response(value: &impl Serialize, pretty: bool) -> Response {
+    let body = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    (
+        [(CONTENT_TYPE, "application/json")],
+        body.expect("collected data can be serialized"),
     )
+        .into_response()
+}
+
+/// Answer of the JSON API until the first collection finished
+fn not_collected() -> Response {
+    let mut response = json_response(
+        &serde_json::json!({ "error": "The clusters have not been collected yet" }),
+        false,
+    );
+    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    response
+}
+
+/// The complete collection as JSON, the same data the template gets as `groups`
+#[tracing::instrument(skip_all)]
+async fn clusters(
+    Query(params): Query<JsonParams>,
+    Extension(collection): Extension<IngressCollectionWrapper>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Response {
+    if last_success.read().await.is_none() {
+        return not_collected();
+    }
+    let collection = without_expired(&*collection.read().await, Utc::now());
+    json_response(&collection, params.pretty)
+}
+
+/// All ingresses as a flat list, filtered by the query parameters
+#[tracing::instrument(skip_all)]
+async fn ingresses(
+    Query(params): Query<IngressParams>,
+    Extension(collection): Extension<IngressCollectionWrapper>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Response {
+    if last_success.read().await.is_none() {
+        return not_collected();
+    }
+    let collection = without_expired(&*collection.read().await, Utc::now());
+    let ingresses: Vec<FlatIngressInfo> = flatten_collection(&collection)
+        .into_iter()
+        .filter(|entry| params.matches(entry))
+        .collect();
+    json_response(&ingresses, params.pretty)
+}
+
+async fn openapi(Query(params): Query<JsonParams>) -> Response {
+    json_response(&openapi::openapi_spec(), params.pretty)
+}
+
+/// Only available with `swaggerUi`
+async fn swagger_ui(Extension(template_env): Extension<TemplateEnvironment>) -> Response {
+    if !template_env.current().swagger_ui {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Html(openapi::SWAGGER_UI_PAGE).into_response()
+}
+
+/// Server-Sent Events stream that emits the full collection whenever it was updated
+async fn events(
+    Extension(updates): Extension<CollectionUpdates>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = stream::unfold(updates.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(collection) => {
+                    let collection = without_expired(&collection, Utc::now());
+                    let data = serde_json::to_string(&collection).unwrap();
+                    let event = Event::default().event("collection").data(data);
+                    return Some((Ok::<_, Infallible>(event), receiver));
+                }
+                // Missed some updates, the next one contains the full state anyway
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Ingresses that differ between two collections
+#[derive(Debug, Default, Serialize)]
+struct CollectionDiff {
+    added: Vec<FlatIngressInfo>,
+    removed: Vec<FlatIngressInfo>,
+    /// The new state of ingresses whose data changed, e.g. their health
+    changed: Vec<FlatIngressInfo>,
+}
+
+/// Identifies an ingress across collections by group, cluster, object name and URL
+fn ingress_key(entry: &FlatIngressInfo) -> (&str, &str, &str, &str) {
+    (
+        &entry.group,
+        &entry.cluster,
+        &entry.ingress.ingress_name,
+        &entry.ingress.url,
+    )
+}
+
+/// Compares the published data of two entries, internal fields like `last_seen` are not serialized
+fn same_content(a: &FlatIngressInfo, b: &FlatIngressInfo) -> bool {
+    serde_json::to_value(&a.ingress).ok() == serde_json::to_value(&b.ingress).ok()
+}
+
+fn diff_collections(old: &[FlatIngressInfo], new: &[FlatIngressInfo]) -> CollectionDiff {
+    let old_entries: HashMap<_, _> = old.iter().map(|e| (ingress_key(e), e)).collect();
+    let new_keys: HashSet<_> = new.iter().map(ingress_key).collect();
+    let mut diff = CollectionDiff::default();
+    for entry in new {
+        match old_entries.get(&ingress_key(entry)) {
+            None => diff.added.push(entry.clone()),
+            Some(old_entry) if !same_content(old_entry, entry) => diff.changed.push(entry.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|entry| !new_keys.contains(&ingress_key(entry)))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Server-Sent Events stream that starts with all ingresses and then emits the changes of every collection
+async fn events_v1(
+    Extension(collection): Extension<IngressCollectionWrapper>,
+    Extension(updates): Extension<CollectionUpdates>,
+    Extension(last_success): Extension<LastSuccess>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Subscribe before reading the collection to not miss an update in between
+    let receiver = updates.subscribe();
+    let current = if last_success.read().await.is_some() {
+        flatten_collection(&without_expired(&*collection.read().await, Utc::now()))
+    } else {
+        Vec::new()
+    };
+    let snapshot = Event::default()
+        .event("snapshot")
+        .data(serde_json::to_string(&current).unwrap());
+    let diffs = stream::unfold((receiver, current), |(mut receiver, previous)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(collection) => {
+                    let current = flatten_collection(&without_expired(&collection, Utc::now()));
+                    let diff = diff_collections(&previous, &current);
+                    let data = serde_json::to_string(&diff).unwrap();
+                    let event = Event::default().event("diff").data(data);
+                    return Some((Ok(event), (receiver, current)));
+                }
+                // The diff is computed against the last sent state, so missed updates are included in the next one
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream::once(async { Ok(snapshot) }).chain(diffs)).keep_alive(KeepAlive::default())
+}
+
+/// Selects the view sent over `/ws`, the grouped view if not set
+#[derive(Debug, Default, Deserialize)]
+struct LiveParams {
+    view: Option<String>,
+    group: Option<String>,
+}
+
+impl LiveParams {
+    /// Renders the selected view, `None` if the group does not exist (anymore)
+    fn render(
+        &self,
+        template_env: &PageTemplate,
+        collection: &IngressCollection,
+    ) -> Option<Html<String>> {
+        let now = Utc::now();
+        if let Some(name) = self.group.as_ref() {
+            let group = collection.iter().find(|group| group.name == *name)?;
+            return Some(render(template_env, &vec![group.clone()], View::Group, now));
+        }
+        let view = match self.view.as_deref() {
+            Some("flat") => View::Flat,
+            _ => View::Grouped,
+        };
+        Some(render(template_env, collection, view, now))
+    }
+}
+
+/// Interval of pings that keep idle connections through proxies open
+const WEBSOCKET_PING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// WebSocket that sends the rendered page whenever the collection was updated
+async fn live(
+    Query(params): Query<LiveParams>,
+    Extension(updates): Extension<CollectionUpdates>,
+    Extension(template_env): Extension<TemplateEnvironment>,
+    req: Request<Body>,
+) -> Response {
+    websocket::upgrade(req, move |mut socket| async move {
+        let mut receiver = updates.subscribe();
+        let mut ping = tokio::time::interval_at(
+            tokio::time::Instant::now() + WEBSOCKET_PING_INTERVAL,
+            WEBSOCKET_PING_INTERVAL,
+        );
+        loop {
+            let result = tokio::select! {
+                update = receiver.recv() => match update {
+                    Ok(collection) => match params.render(&template_env.current(), &collection) {
+                        Some(html) => socket.send_text(&html.0).await,
+                        None => Ok(()),
+                    },
+                    // Missed some updates, the next one contains the full state anyway
+                    Err(RecvError::Lagged(_)) => Ok(()),
+                    Err(RecvError::Closed) => break,
+                },
+                incoming = socket.receive() => match incoming {
+                    Incoming::Ping(payload) => socket.pong(&payload).await,
+                    Incoming::Close => break,
+                },
+                _ = ping.tick() => socket.ping().await,
+            };
+            if let Err(err) = result {
+                tracing::debug!("Sending to WebSocket failed: {err}");
+                break;
+            }
+        }
+        socket.close().await;
+    })
+}
+
+/// Bearer token required by `/api/refresh`, read from `REFRESH_TOKEN`
+#[derive(Clone)]
+struct RefreshToken(Option<String>);
+
+/// Collects all clusters immediately and answers once the collection was updated
+async fn refresh(
+    Extension(refresh): Extension<RefreshRequests>,
+    Extension(RefreshToken(token)): Extension<RefreshToken>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Some(token) = token {
+        let authorized = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|value| value == token);
+        if !authorized {
+            return StatusCode::UNAUTHORIZED;
+        }
+    }
+    let (sender, receiver) = oneshot::channel();
+    if refresh.send(sender).await.is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    match receiver.await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
 }
 
 async fn health() -> &'static str {
     "OK"
 }
 
+async fn metrics() -> ([(HeaderName, &'static str); 1], String) {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
 pub struct InnerOidcState {
     pub issuer: String,
     pub base_url: String,
@@ -111,27 +796,98 @@ async fn oidc_layer(
     service.call(req).await
 }
 
-pub async fn api(collection: IngressCollectionWrapper) {
+/// The custom template from `TEMPLATE_PATH` or the default one
+fn read_template() -> &'static str {
     let template = if let Ok(template_path) = std::env::var("TEMPLATE_PATH") {
         tracing::info!("Using custom template at {template_path}");
         std::fs::read_to_string(template_path).unwrap()
     } else {
         std::fs::read_to_string("template.html").unwrap()
     };
+    // The template lives for the whole runtime of the process
+    Box::leak(template.into_boxed_str())
+}
 
-    let app = Router::new()
+/// Renders the index page like it is served, for the `render` command
+pub fn render_index(config: &Config, collection: &IngressCollection) -> String {
+    let template_env = template_environment(read_template(), config);
+    render(&template_env, collection, View::Grouped, Utc::now()).0
+}
+
+/// Sets the configured `Cache-Control` header on the HTML pages
+async fn set_cache_control(
+    State(template_env): State<TemplateEnvironment>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let cache_control = template_env.current().cache_control.clone();
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(CACHE_CONTROL, cache_control);
+    response
+}
+
+pub async fn api(config: Config, collector: Collector) {
+    let template = read_template();
+    let template_env = TemplateEnvironment::new(template_environment(template, &config));
+
+    let page_cache = PageCache::default();
+    tokio::spawn(invalidate_page_cache(
+        page_cache.clone(),
+        collector.updates.clone(),
+    ));
+    tokio::spawn(reload_template(
+        template,
+        template_env.clone(),
+        page_cache.clone(),
+        collector.config.subscribe(),
+    ));
+
+    let html_routes = Router::new()
         .route("/", get(index))
-        .layer(Extension(collection))
-        .layer(Extension(template));
+        .route("/all", get(all))
+        .route("/group/{name}", get(group))
+        .layer(from_fn_with_state(template_env.clone(), set_cache_control));
+
+    let refresh_token = std::env::var("REFRESH_TOKEN").ok();
+    let oidc_issuer = std::env::var("OIDC_ISSUER").ok();
+    let refresh_routes = Router::new()
+        .route("/api/refresh", post(refresh))
+        .layer(Extension(collector.refresh))
+        .layer(Extension(RefreshToken(refresh_token.clone())));
+    // With a token the endpoint can be called by scripts and is therefore not behind the login.
+    // Without a token or a login anybody could trigger collections, so it is not available then.
+    let (protected_routes, public_routes) = if refresh_token.is_some() {
+        (Router::new(), refresh_routes)
+    } else if oidc_issuer.is_some() {
+        (refresh_routes, Router::new())
+    } else {
+        tracing::info!("Neither REFRESH_TOKEN nor OIDC is configured, /api/refresh is disabled");
+        (Router::new(), Router::new())
+    };
+
+    let app = Router::new()
+        .merge(html_routes)
+        .route("/docs", get(swagger_ui))
+        .merge(protected_routes)
+        .route("/api/events", get(events))
+        .route("/api/v1/clusters", get(clusters))
+        .route("/api/v1/ingresses", get(ingresses))
+        .route("/api/v1/events", get(events_v1))
+        .route("/ws", get(live))
+        .route("/openapi.json", get(openapi))
+        .layer(Extension(collector.collection))
+        .layer(Extension(collector.updates))
+        .layer(Extension(template_env))
+        .layer(Extension(page_cache))
+        .layer(Extension(collector.last_success));
 
-    let app = if let Ok(issuer) = std::env::var("OIDC_ISSUER") {
+    let app = if let Some(issuer) = oidc_issuer {
         tracing::info!("Configuring OIDC with issuer {issuer}");
 
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store)
-            .with_secure(false)
-            .with_same_site(SameSite::Lax)
-            .with_expiry(Expiry::OnInactivity(Duration::hours(24)));
+        let session_layer = session_layer(
+            std::env::var("SESSION_COOKIE_NAME").ok(),
+            std::env::var("SESSION_COOKIE_DOMAIN").ok(),
+        );
 
         let oidc_login_service = ServiceBuilder::new()
             .layer(HandleErrorLayer::new(|e: MiddlewareError| async {
@@ -149,7 +905,10 @@ pub async fn api(collection: IngressCollectionWrapper) {
         app
     };
 
-    let app = app.route("/health", get(health));
+    let app = app
+        .merge(public_routes)
+        .route("/health", get(health))
+        .route("/metrics", get(metrics));
 
     let app = if let Ok(static_dir) = std::env::var("STATIC_FOLDER") {
         tracing::info!("Adding static folder at {static_dir}");
@@ -163,3 +922,385 @@ pub async fn api(collection: IngressCollectionWrapper) {
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
+
+/// Sessions of the OIDC login, the cookie name and domain can be configured
+fn session_layer(
+    cookie_name: Option<String>,
+    cookie_domain: Option<String>,
+) -> SessionManagerLayer<MemoryStore> {
+    let session_layer = SessionManagerLayer::new(MemoryStore::default())
+        .with_secure(false)
+        .with_same_site(SameSite::Lax)
+        .with_expiry(Expiry::OnInactivity(Duration::hours(24)));
+    let session_layer = if let Some(cookie_name) = cookie_name {
+        session_layer.with_name(cookie_name)
+    } else {
+        session_layer
+    };
+    if let Some(cookie_domain) = cookie_domain {
+        session_layer.with_domain(cookie_domain)
+    } else {
+        session_layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        collector::{ClusterInfo, GroupInfo},
+        config::{ConfigFormat, parse_config},
+    };
+
+    fn config(yaml: &str) -> Config {
+        parse_config(yaml, ConfigFormat::Yaml).unwrap()
+    }
+
+    fn ingress(name: &str, url: &str) -> IngressInfo {
+        IngressInfo {
+            name: name.to_owned(),
+            ingress_name: name.to_owned(),
+            url: url.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn group(name: &str, clusters: Vec<(&str, Vec<IngressInfo>)>) -> GroupInfo {
+        GroupInfo {
+            name: name.to_owned(),
+            description: String::new(),
+            clusters: clusters
+                .into_iter()
+                .map(|(name, ingresses)| ClusterInfo {
+                    name: name.to_owned(),
+                    description: String::new(),
+                    ingresses,
+                    truncated: false,
+                    hidden_count: 0,
+                    error: None,
+                    gateways: Vec::new(),
+                    links: Vec::new(),
+                    last_updated: Utc::now(),
+                    stale: false,
+                })
+                .collect(),
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn flat_view_lists_all_ingresses_sorted() {
+        let collection = vec![
+            group(
+                "dev",
+                vec![(
+                    "dev",
+                    vec![
+                        ingress("grafana", "https://grafana.dev"),
+                        ingress("Argo", "https://argo.dev"),
+                    ],
+                )],
+            ),
+            group(
+                "prod",
+                vec![("prod", vec![ingress("Grafana", "https://grafana.prod")])],
+            ),
+        ];
+        let entries: Vec<_> = flatten_collection(&collection)
+            .into_iter()
+            .map(|entry| (entry.ingress.name, entry.cluster, entry.group))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Argo".to_owned(), "dev".to_owned(), "dev".to_owned()),
+                ("grafana".to_owned(), "dev".to_owned(), "dev".to_owned()),
+                ("Grafana".to_owned(), "prod".to_owned(), "prod".to_owned()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sets_configured_cache_control() {
+        let config = config("global:\n  htmlCacheControl: max-age=60\n");
+        let template_env = TemplateEnvironment::new(template_environment("", &config));
+        let app = Router::new()
+            .route("/", get(|| async { "page" }))
+            .layer(from_fn_with_state(template_env, set_cache_control));
+        let response = app
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CACHE_CONTROL], "max-age=60");
+    }
+
+    #[tokio::test]
+    async fn counts_cache_hits_and_misses() {
+        let template_env =
+            TemplateEnvironment::new(template_environment("{{ view }}", &config("{}")));
+
+        let collection: IngressCollectionWrapper = Arc::default();
+        let last_success: LastSuccess = Arc::new(tokio::sync::RwLock::new(Some(Utc::now())));
+        let page_cache = PageCache::default();
+        let request = || {
+            index(
+                Query(IndexParams::default()),
+                Extension(collection.clone()),
+                Extension(template_env.clone()),
+                Extension(page_cache.clone()),
+                Extension(last_success.clone()),
+            )
+        };
+
+        let (page_misses, renders) = (
+            metrics::PAGE_CACHE_MISSES.get(),
+            metrics::TEMPLATE_RENDERS.get(),
+        );
+        request().await;
+        assert!(metrics::PAGE_CACHE_MISSES.get() > page_misses);
+        assert!(metrics::TEMPLATE_RENDERS.get() > renders);
+
+        let page_hits = metrics::PAGE_CACHE_HITS.get();
+        request().await;
+        assert!(metrics::PAGE_CACHE_HITS.get() > page_hits);
+    }
+
+    #[tokio::test]
+    async fn sets_configured_session_cookie() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: tower_sessions::Session| async move {
+                    session.insert("user", "jane").await.unwrap();
+                }),
+            )
+            .layer(session_layer(
+                Some("landingpage_session".to_owned()),
+                Some("example.com".to_owned()),
+            ));
+        let response = app
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let cookie = response.headers()[axum::http::header::SET_COOKIE]
+            .to_str()
+            .unwrap();
+        assert!(cookie.starts_with("landingpage_session="), "{cookie}");
+        assert!(cookie.contains("Domain=example.com"), "{cookie}");
+    }
+
+    #[test]
+    fn hides_expired_ingresses() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let mut expired = ingress("expired", "https://expired.example.com");
+        expired.expires = Some(now - chrono::Duration::minutes(1));
+        let mut expiring = ingress("expiring", "https://expiring.example.com");
+        expiring.expires = Some(now + chrono::Duration::minutes(1));
+        let collection = vec![group(
+            "local",
+            vec![(
+                "local",
+                vec![expired, expiring, ingress("app", "https://app.example.com")],
+            )],
+        )];
+        let names: Vec<_> = flatten_collection(&without_expired(&collection, now))
+            .into_iter()
+            .map(|entry| entry.ingress.name)
+            .collect();
+        assert_eq!(names, ["app", "expiring"]);
+        assert_eq!(
+            next_expiry(&collection, now),
+            Some(now + chrono::Duration::minutes(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_collection_after_update() {
+        let (updates, _) = tokio::sync::broadcast::channel(4);
+        let app = Router::new()
+            .route("/api/events", get(events))
+            .layer(Extension::<CollectionUpdates>(updates.clone()));
+        let response = app
+            .oneshot(Request::get("/api/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let mut body = response.into_body().into_data_stream();
+
+        let update = vec![group(
+            "local",
+            vec![("local", vec![ingress("app", "https://app.example.com")])],
+        )];
+        updates.send(Arc::new(update)).unwrap();
+        let event = body.next().await.unwrap().unwrap();
+        let data = std::str::from_utf8(&event)
+            .unwrap()
+            .trim_end()
+            .strip_prefix("event: collection\ndata: ")
+            .unwrap();
+        let collection: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(collection[0]["name"], "local");
+        assert_eq!(
+            collection[0]["clusters"][0]["ingresses"][0]["url"],
+            "https://app.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_diff_after_collection_update() {
+        let collection: IngressCollectionWrapper = Arc::default();
+        let (updates, _) = tokio::sync::broadcast::channel(4);
+        let last_success: LastSuccess = Arc::new(tokio::sync::RwLock::new(Some(Utc::now())));
+        let app = Router::new()
+            .route("/api/v1/events", get(events_v1))
+            .layer(Extension(collection))
+            .layer(Extension::<CollectionUpdates>(updates.clone()))
+            .layer(Extension(last_success));
+        let response = app
+            .oneshot(Request::get("/api/v1/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let snapshot = body.next().await.unwrap().unwrap();
+        assert_eq!(snapshot, "event: snapshot\ndata: []\n\n");
+
+        let update = vec![group(
+            "local",
+            vec![("local", vec![ingress("app", "https://app.example.com")])],
+        )];
+        updates.send(Arc::new(update)).unwrap();
+        let event = body.next().await.unwrap().unwrap();
+        let event = std::str::from_utf8(&event).unwrap();
+        assert!(event.starts_with("event: diff\ndata: "), "{event}");
+        let data = event
+            .trim_end()
+            .strip_prefix("event: diff\ndata: ")
+            .unwrap();
+        let diff: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(diff["added"][0]["url"], "https://app.example.com");
+        assert_eq!(diff["removed"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn caches_page_until_cleared_or_expired() {
+        let now = Utc::now();
+        let page_cache = PageCache::default();
+        assert_eq!(page_cache.get(now), None);
+
+        page_cache.set("page".to_owned(), None);
+        assert_eq!(page_cache.get(now).as_deref(), Some("page"));
+        page_cache.clear();
+        assert_eq!(page_cache.get(now), None);
+
+        let valid_until = now + chrono::Duration::minutes(1);
+        page_cache.set("page".to_owned(), Some(valid_until));
+        assert_eq!(page_cache.get(now).as_deref(), Some("page"));
+        assert_eq!(page_cache.get(valid_until), None);
+    }
+
+    #[tokio::test]
+    async fn serves_cached_page_without_rendering() {
+        let page_cache = PageCache::default();
+        page_cache.set("cached".to_owned(), None);
+        let response = index(
+            Query(IndexParams::default()),
+            Extension(Arc::default()),
+            Extension(TemplateEnvironment::new(template_environment(
+                "rendered",
+                &config("{}"),
+            ))),
+            Extension(page_cache),
+            Extension(Arc::new(tokio::sync::RwLock::new(Some(Utc::now())))),
+        )
+        .await;
+        let body = response.into_body().into_data_stream().next().await;
+        assert_eq!(body.unwrap().unwrap(), "cached");
+    }
+
+    #[tokio::test]
+    async fn shows_loading_page_before_first_collection() {
+        let response = index(
+            Query(IndexParams::default()),
+            Extension(Arc::default()),
+            Extension(TemplateEnvironment::new(template_environment(
+                "rendered",
+                &config("{}"),
+            ))),
+            Extension(PageCache::default()),
+            Extension(Arc::default()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().into_data_stream().next().await;
+        assert_eq!(body.unwrap().unwrap(), LOADING_PAGE);
+    }
+
+    #[test]
+    fn adds_context_root_key() {
+        let template = "{% for g in groups %}{{ g.name }}{% endfor %}|{% for g in clusters %}{{ g.name }}{% endfor %}";
+        let collection = vec![group("prod", Vec::new()), group("dev", Vec::new())];
+        let render_with = |yaml: &str| {
+            let template_env = template_environment(template, &config(yaml));
+            render(&template_env, &collection, View::Grouped, Utc::now()).0
+        };
+        assert_eq!(render_with("{}"), "proddev|");
+        assert_eq!(
+            render_with("global:\n  contextRootKey: clusters\n"),
+            "proddev|proddev"
+        );
+    }
+
+    #[test]
+    fn summarizes_above_threshold() {
+        let template = "{{ view }}";
+        let collection = vec![group(
+            "prod",
+            vec![(
+                "prod",
+                vec![
+                    ingress("a", "https://a.example.com"),
+                    ingress("b", "https://b.example.com"),
+                ],
+            )],
+        )];
+        let view = |yaml: &str| {
+            let template_env = template_environment(template, &config(yaml));
+            render(&template_env, &collection, View::Grouped, Utc::now()).0
+        };
+        assert_eq!(view("{}"), "grouped");
+        assert_eq!(view("global:\n  summarizeAboveCount: 2\n"), "grouped");
+        assert_eq!(view("global:\n  summarizeAboveCount: 1\n"), "summary");
+    }
+
+    #[test]
+    fn diffs_collections() {
+        let flat = |ingresses: Vec<IngressInfo>| {
+            flatten_collection(&vec![group("local", vec![("local", ingresses)])])
+        };
+        let old = flat(vec![
+            ingress("kept", "https://kept.example.com"),
+            ingress("changed", "https://changed.example.com"),
+            ingress("removed", "https://removed.example.com"),
+        ]);
+        let mut changed = ingress("changed", "https://changed.example.com");
+        changed.description = "New description".to_owned();
+        let new = flat(vec![
+            ingress("kept", "https://kept.example.com"),
+            changed,
+            ingress("added", "https://added.example.com"),
+        ]);
+        let diff = diff_collections(&old, &new);
+        let names = |entries: &[FlatIngressInfo]| {
+            entries
+                .iter()
+                .map(|entry| entry.ingress.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&diff.added), ["added"]);
+        assert_eq!(names(&diff.changed), ["changed"]);
+        assert_eq!(names(&diff.removed), ["removed"]);
+    }
+}
@@ -0,0 +1,316 @@
+//! Command line interface. Without a command the server is started, the other commands
+//! run once and exit, e.g. to check a config in CI or to debug the collection.
+
+use std::time::Duration;
+
+use crate::{
+    api,
+    collector::{self, IngressCollection},
+    config::{self, Config, ConfigFiles},
+    reload, resource, schema,
+};
+
+/// One-shot commands give up if not every cluster could be collected within this time
+const COLLECT_TIMEOUT: Duration = Duration::from_secs(300);
+
+const USAGE: &str = "Usage: landingpage [COMMAND]
+
+Commands:
+  serve                          Collect continuously and serve the page (default)
+  validate-config                Check the config file and exit
+  collect [--output json|yaml]   Collect all clusters once and print the result
+  render [--out FILE]            Collect all clusters once and write the page to FILE (index.html)
+  schema                         Print the JSON Schema of the config file
+
+The config file is read from CONFIG_FILE (config.yaml if not set, can be a comma-separated list
+of files and directories that are merged) in the format given by CONFIG_FORMAT or its extension
+(yaml, json or toml), or from the LandingPage resource given as CONFIG_RESOURCE=<namespace>/<name>.
+The commands collect and render do not update the status resource.";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    ValidateConfig,
+    Collect { output: OutputFormat },
+    Render { out: String },
+    Schema,
+    Help,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+}
+
+/// Parses the arguments without the name of the program
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut command = match args.next().as_deref() {
+        None | Some("serve") => Command::Serve,
+        Some("validate-config") => Command::ValidateConfig,
+        Some("collect") => Command::Collect {
+            output: OutputFormat::Json,
+        },
+        Some("render") => Command::Render {
+            out: "index.html".to_owned(),
+        },
+        Some("schema") => Command::Schema,
+        Some("help" | "-h" | "--help") => return Ok(Command::Help),
+        Some(other) => return Err(format!("Unknown command {other}")),
+    };
+    while let Some(arg) = args.next() {
+        // Options can be given as `--name value` or `--name=value`
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+            None => (arg, None),
+        };
+        if name == "-h" || name == "--help" {
+            return Ok(Command::Help);
+        }
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {name}"))
+        };
+        match (&mut command, name.as_str()) {
+            (Command::Collect { output }, "--output" | "-o") => {
+                *output = match value()?.as_str() {
+                    "json" => OutputFormat::Json,
+                    "yaml" => OutputFormat::Yaml,
+                    other => return Err(format!("Unknown output format {other}")),
+                };
+            }
+            (Command::Render { out }, "--out" | "-o") => *out = value()?,
+            _ => return Err(format!("Unknown option {name}")),
+        }
+    }
+    Ok(command)
+}
+
+impl Command {
+    /// Output of one-shot commands goes to stdout, so their logs are written to stderr
+    pub fn logs_to_stderr(&self) -> bool {
+        !matches!(self, Command::Serve)
+    }
+}
+
+/// Runs the command, returns the exit code of the process
+pub async fn run(command: Command) -> i32 {
+    match command {
+        Command::Serve => {
+            let Some((config, generation)) = load_config().await else {
+                return 1;
+            };
+            if let Err(err) = collector::check_remotes(&config).await {
+                tracing::error!("Not starting with strictRemotes: {err}");
+                return 1;
+            }
+            let collector = collector::start_collector(config.clone());
+            match &config.source_resource {
+                Some(source) => resource::watch_config(source, generation, collector.clone()),
+                None => reload::watch_config(&config, collector.clone()),
+            }
+            api::api(config, collector).await;
+            0
+        }
+        Command::ValidateConfig => validate_config().await,
+        Command::Collect { output } => {
+            let Some((config, _)) = load_config().await else {
+                return 1;
+            };
+            let Some(collection) = collect_once(config).await else {
+                return 1;
+            };
+            let result = match output {
+                OutputFormat::Json => {
+                    serde_json::to_string_pretty(&collection).map_err(|err| err.to_string())
+                }
+                OutputFormat::Yaml => {
+                    serde_yaml::to_string(&collection).map_err(|err| err.to_string())
+                }
+            };
+            match result {
+                Ok(data) => {
+                    println!("{data}");
+                    0
+                }
+                Err(err) => {
+                    eprintln!("Could not serialize the collection: {err}");
+                    1
+                }
+            }
+        }
+        Command::Render { out } => {
+            let Some((config, _)) = load_config().await else {
+                return 1;
+            };
+            let Some(collection) = collect_once(config.clone()).await else {
+                return 1;
+            };
+            let html = api::render_index(&config, &collection);
+            match std::fs::write(&out, html) {
+                Ok(()) => {
+                    println!("Wrote the page to {out}");
+                    0
+                }
+                Err(err) => {
+                    eprintln!("Could not write {out}: {err}");
+                    1
+                }
+            }
+        }
+        Command::Schema => {
+            let schema = schema::config_schema();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).expect("JSON values can be serialized")
+            );
+            0
+        }
+        Command::Help => {
+            println!("{USAGE}");
+            0
+        }
+    }
+}
+
+/// Reads the config from the `LandingPage` resource if `CONFIG_RESOURCE` is set, from the file otherwise.
+/// The generation of the resource is returned as well, `None` is returned if the config is invalid.
+async fn load_config() -> Option<(Config, Option<i64>)> {
+    match config::config_resource() {
+        Ok(Some(source)) => match resource::read_config(&source).await {
+            Ok(result) => Some(result),
+            Err(err) => {
+                tracing::error!(
+                    "Could not read config from resource {}/{}: {err}",
+                    source.namespace,
+                    source.name
+                );
+                None
+            }
+        },
+        Ok(None) => Some((config::read_config(), None)),
+        Err(err) => {
+            tracing::error!("{err}");
+            None
+        }
+    }
+}
+
+async fn collect_once(config: Config) -> Option<IngressCollection> {
+    match tokio::time::timeout(COLLECT_TIMEOUT, collector::collect_once(config)).await {
+        Ok(collection) => Some(collection),
+        Err(_) => {
+            eprintln!(
+                "Not all clusters could be collected within {}s, see the logs for errors",
+                COLLECT_TIMEOUT.as_secs()
+            );
+            None
+        }
+    }
+}
+
+/// Unlike the server a missing config file is reported, though it is not an error
+async fn validate_config() -> i32 {
+    match config::config_resource() {
+        Ok(Some(source)) => {
+            return match resource::read_config(&source).await {
+                Ok(_) => {
+                    println!(
+                        "Config resource {}/{} is valid",
+                        source.namespace, source.name
+                    );
+                    0
+                }
+                Err(err) => {
+                    eprintln!(
+                        "Invalid config resource {}/{}: {err}",
+                        source.namespace, source.name
+                    );
+                    1
+                }
+            };
+        }
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{err}");
+            return 1;
+        }
+    }
+    let path = config::config_path();
+    match ConfigFiles::read().and_then(|files| files.map(|files| files.parse()).transpose()) {
+        Ok(Some(_)) => {
+            println!("Config file {path} is valid");
+            0
+        }
+        Ok(None) => {
+            println!(
+                "Config file {path} does not exist, the default in-cluster mode would be used"
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("Invalid config file {path}: {err}");
+            1
+        }
+    }
+}
+
+/// Prints the error together with the usage, for invalid arguments
+pub fn usage_error(err: &str) -> i32 {
+    eprintln!("{err}\n\n{USAGE}");
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        assert_eq!(parse_args(&[]), Ok(Command::Serve));
+        assert_eq!(
+            parse_args(&["validate-config"]),
+            Ok(Command::ValidateConfig)
+        );
+        assert_eq!(
+            parse_args(&["collect", "--output", "yaml"]),
+            Ok(Command::Collect {
+                output: OutputFormat::Yaml
+            })
+        );
+        assert_eq!(
+            parse_args(&["render", "--out=page.html"]),
+            Ok(Command::Render {
+                out: "page.html".to_owned()
+            })
+        );
+        assert_eq!(parse_args(&["collect", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(
+            parse_args(&["deploy"]),
+            Err("Unknown command deploy".to_owned())
+        );
+        assert_eq!(
+            parse_args(&["collect", "--out", "page.html"]),
+            Err("Unknown option --out".to_owned())
+        );
+        assert_eq!(
+            parse_args(&["collect", "--output", "xml"]),
+            Err("Unknown output format xml".to_owned())
+        );
+        assert_eq!(
+            parse_args(&["render", "--out"]),
+            Err("Missing value for --out".to_owned())
+        );
+    }
+}
@@ -1,32 +1,199 @@
-use k8s_openapi::api::{core::v1::Secret, networking::v1::Ingress};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use futures::future::{join_all, try_join_all};
+use k8s_openapi::ByteString;
+use k8s_openapi::api::{
+    authorization::v1::{ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec},
+    core::v1::{ConfigMap, Namespace, Secret},
+    discovery::v1::EndpointSlice,
+    networking::v1::{HTTPIngressPath, Ingress, IngressBackend},
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::{
     Api, Client, ResourceExt,
-    api::ListParams,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams, PostParams},
     config::{KubeConfigOptions, Kubeconfig},
 };
+use rand::Rng;
+use regex::Regex;
 use serde::Serialize;
-use std::{collections::BTreeMap, sync::Arc, time::Duration};
-use tokio::sync::RwLock;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{Notify, RwLock, Semaphore, broadcast, mpsc, oneshot},
+    task::JoinSet,
+};
+use url::Url;
 
 use crate::{
-    config::{Config, RemoteCluster},
+    config::{
+        ClusterApi, ClusterConfig, Config, DedupHosts, Discovery, Global, GroupDescriptions,
+        Impersonate, KubeconfigSecret, LocalCluster, RegexPathHandling, RemoteCluster,
+        ResourceKind, Retry, SecretReference,
+    },
+    discovery,
     errors::{Error, Result},
+    health::{self, HealthMethod, HealthStatus, StatusRanges},
+    retry, routes,
+    sanitize::sanitize_html,
+    status,
+    token::{EksTokenProvider, TokenProvider},
+    urls::{UrlOptions, normalize_url},
+    watch::{self, IngressStore},
 };
 
 const NAME_ANNOTATION: &str = "landingpage.info/name";
 const DESCRIPTION_ANNOTATION: &str = "landingpage.info/description";
+const DOCS_URL_ANNOTATION: &str = "landingpage.info/docs-url";
+const EXPIRES_ANNOTATION: &str = "landingpage.info/expires";
+const SECONDARY_URL_ANNOTATION: &str = "landingpage.info/secondary-url";
+const GROUPS_ANNOTATION: &str = "landingpage.info/groups";
+const HEALTH_METHOD_ANNOTATION: &str = "landingpage.info/health-method";
+const HEALTH_EXPECT_ANNOTATION: &str = "landingpage.info/health-expect";
+const CATEGORY_ANNOTATION: &str = "landingpage.info/category";
+const COLOR_ANNOTATION: &str = "landingpage.info/color";
+const HIDE_ANNOTATION: &str = "landingpage.info/hide";
+const SCHEME_ANNOTATION: &str = "landingpage.info/scheme";
+/// Annotations that mark an ingress as protected by authentication or rate limiting if not configured
+const DEFAULT_PROTECTION_ANNOTATIONS: &[&str] = &[
+    "nginx.ingress.kubernetes.io/auth-url",
+    "nginx.ingress.kubernetes.io/auth-signin",
+    "nginx.ingress.kubernetes.io/auth-type",
+    "nginx.ingress.kubernetes.io/limit-rps",
+    "nginx.ingress.kubernetes.io/limit-rpm",
+    "nginx.ingress.kubernetes.io/limit-connections",
+];
+/// Colors that can be used by name in the color annotation
+const NAMED_COLORS: &[&str] = &[
+    "black", "blue", "cyan", "gray", "green", "grey", "orange", "pink", "purple", "red", "teal",
+    "white", "yellow",
+];
+const URL_ANNOTATION: &str = "landingpage.info/url";
+const USE_REGEX_ANNOTATION: &str = "nginx.ingress.kubernetes.io/use-regex";
+/// Number of clusters and namespaces that are collected at the same time if not configured
+const DEFAULT_MAX_CONCURRENT_COLLECTIONS: usize = 8;
+const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 2000;
+/// Time to wait for other clusters after one was collected before publishing the collection
+const PUBLISH_DEBOUNCE: Duration = Duration::from_secs(1);
+/// A requested refresh is answered after this time even if not all clusters reported back, e.g. because a task failed
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(300);
+/// Label of EndpointSlices that references their service
+const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+/// Characters that mark the start of the regex part of a path
+const REGEX_CHARACTERS: &[char] = &[
+    '.', '^', '$', '*', '+', '?', '(', ')', '[', ']', '{', '}', '|', '\\',
+];
+
+/// Annotations that are never exposed as they only add noise
+const IGNORED_ANNOTATIONS: &[&str] = &["kubectl.kubernetes.io/last-applied-configuration"];
+/// Annotations whose key contains one of these are never exposed as they might contain sensitive data
+const SENSITIVE_ANNOTATION_PARTS: &[&str] =
+    &["secret", "password", "token", "credential", "private-key"];
+/// Annotation values longer than this are not exposed to keep the page small
+const MAX_ANNOTATION_VALUE_LENGTH: usize = 256;
 
+/// A single entry (host and path) of an ingress or another routing resource
 #[derive(Clone, Debug, Serialize)]
-struct IngressSpec {
+pub(crate) struct IngressSpec {
     pub name: String,
     pub namespace: String,
     pub host: String,
     pub tls_used: bool,
     pub path: Option<String>,
+    /// The path is a regular expression, handled according to `regexPathHandling`
+    pub path_regex: bool,
+    /// Name and description read from the resource, the annotations take precedence
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub backend: Option<BackendInfo>,
     pub annotations: BTreeMap<String, String>,
     pub labels: BTreeMap<String, String>,
 }
 
+impl IngressSpec {
+    /// Entry for the root path of the host, taking name, namespace, annotations and labels from the object
+    pub(crate) fn for_object(metadata: &ObjectMeta, host: String, tls_used: bool) -> IngressSpec {
+        IngressSpec {
+            name: metadata.name.clone().unwrap_or_default(),
+            namespace: metadata
+                .namespace
+                .clone()
+                .unwrap_or_else(|| "default".to_owned()),
+            host,
+            tls_used,
+            path: None,
+            path_regex: false,
+            display_name: None,
+            description: None,
+            backend: None,
+            annotations: metadata.annotations.clone().unwrap_or_default(),
+            labels: metadata.labels.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// A listed object together with the entries derived from it
+pub(crate) type ObjectSpecs = (ObjectMeta, Vec<IngressSpec>);
+
+/// Accent color of an ingress, either a named color or a hex code like `#f00` or `#ff0000`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Color(String);
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let color = value.trim().to_lowercase();
+        let valid = match color.strip_prefix('#') {
+            Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => NAMED_COLORS.contains(&color.as_str()),
+        };
+        if valid {
+            Ok(Color(color))
+        } else {
+            Err(format!("Invalid color {value}"))
+        }
+    }
+}
+
+/// The backend an ingress path routes to
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackendInfo {
+    Service { name: String, port: Option<String> },
+    Resource { kind: String, name: String },
+}
+
+impl BackendInfo {
+    fn from_ingress_backend(backend: &IngressBackend) -> Option<BackendInfo> {
+        if let Some(service) = backend.service.as_ref() {
+            let port = service.port.as_ref().and_then(|port| {
+                port.name
+                    .clone()
+                    .or_else(|| port.number.map(|number| number.to_string()))
+            });
+            Some(BackendInfo::Service {
+                name: service.name.clone(),
+                port,
+            })
+        } else {
+            backend
+                .resource
+                .as_ref()
+                .map(|resource| BackendInfo::Resource {
+                    kind: resource.kind.clone(),
+                    name: resource.name.clone(),
+                })
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ContextInfo {
     pub clusters: Vec<ClusterInfo>,
@@ -37,270 +204,3749 @@ pub struct ClusterInfo {
     pub name: String,
     pub description: String,
     pub ingresses: Vec<IngressInfo>,
+    /// Set if ingresses were left out because of `maxIngressesPerCluster`
+    pub truncated: bool,
+    pub hidden_count: usize,
+    /// Set if the cluster could not or only partially be collected
+    pub error: Option<String>,
+    /// Entry points of the cluster, only collected with the kind `gateway`
+    pub gateways: Vec<GatewayInfo>,
+    /// Static links from the config that are shown in this cluster
+    pub links: Vec<LinkInfo>,
+    /// Time of the last successful collection of the cluster
+    pub last_updated: DateTime<Utc>,
+    /// Set if the latest collection failed and the cluster shows the data of an earlier collection
+    pub stale: bool,
+}
+
+/// A Gateway API gateway with its listeners
+#[derive(Clone, Debug, Serialize)]
+pub struct GatewayInfo {
+    pub name: String,
+    pub namespace: String,
+    pub class_name: Option<String>,
+    pub listeners: Vec<ListenerInfo>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ListenerInfo {
+    pub name: String,
+    /// Listeners without hostname accept all hosts
+    pub hostname: Option<String>,
+    pub port: u64,
+    pub protocol: String,
+    pub tls: bool,
+    /// Number of routes attached to the listener as reported in the status of the gateway
+    pub attached_routes: Option<u64>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 pub struct GroupInfo {
     pub name: String,
+    pub description: String,
     pub clusters: Vec<ClusterInfo>,
+    /// Static links from the config that are not backed by an ingress
+    pub links: Vec<LinkInfo>,
 }
 
 #[derive(Clone, Debug, Serialize)]
+pub struct LinkInfo {
+    pub name: String,
+    pub description: String,
+    pub url: String,
+    /// URL of an image shown beside the name
+    pub icon: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct IngressInfo {
+    /// Display name, taken from the name annotation if set
     pub name: String,
+    /// Name of the Kubernetes object
+    pub ingress_name: String,
     pub description: String,
+    /// Sanitized HTML version of the description, only set if `htmlDescription` is enabled
+    pub description_html: Option<String>,
     pub url: String,
+    /// Fallback URL that is used if health checking finds the primary URL to be down
+    pub secondary_url: Option<String>,
+    pub docs_url: Option<String>,
+    /// Ingress is hidden from the page after this point in time
+    pub expires: Option<DateTime<Utc>>,
+    pub annotations: BTreeMap<String, String>,
+    /// Labels of the Kubernetes object
+    pub labels: BTreeMap<String, String>,
+    /// Only set if `showBackend` is enabled
+    pub backend: Option<BackendInfo>,
+    pub health: HealthStatus,
+    /// Set if `url` is the secondary URL because the primary URL is down
+    pub failed_over: bool,
+    /// Groups the ingress is shown in instead of the group of its cluster
+    #[serde(skip)]
+    pub groups: Vec<String>,
+    pub color: Option<Color>,
+    /// Section of the cluster the ingress is shown in
+    pub category: Option<String>,
+    /// Set if the ingress has one of the protection annotations
+    pub protected: bool,
+    /// The protection annotations the ingress has
+    pub protection: Option<String>,
+    /// Overrides the health check method from the config
+    #[serde(skip)]
+    pub health_method: Option<HealthMethod>,
+    /// Overrides the status codes expected by the health check from the config
+    #[serde(skip)]
+    pub health_expect: Option<StatusRanges>,
+    /// Clusters the URL was found in, only set if `dedupByUrl` merged entries of several clusters
+    pub sources: Vec<String>,
+    /// Set if the ingress is missing from the latest collection but still within the grace period
+    pub stale: bool,
+    #[serde(skip)]
+    pub last_seen: DateTime<Utc>,
 }
 
 pub type IngressCollection = Vec<GroupInfo>;
 pub type IngressCollectionWrapper = Arc<RwLock<IngressCollection>>;
 
-pub async fn start_collector(config: Config) -> Result<IngressCollectionWrapper> {
-    let result = collect_for_all_clusters(&config).await?;
-    let info = Arc::new(RwLock::new(result));
-    tokio::spawn(run_collector_task(config, info.clone()));
-    Ok(info)
+/// Publishes every newly collected collection
+pub type CollectionUpdates = broadcast::Sender<Arc<IngressCollection>>;
+
+/// Time of the last successful collection, `None` until the first collection finished
+pub type LastSuccess = Arc<RwLock<Option<DateTime<Utc>>>>;
+
+/// Requests an immediate collection of all clusters, the sender is notified once the collection was updated
+pub type RefreshRequests = mpsc::Sender<oneshot::Sender<()>>;
+
+/// The config the collector currently runs with, updated on every restart
+pub type ConfigUpdates = tokio::sync::watch::Sender<Arc<Config>>;
+
+/// Handles to the data of a running collector
+#[derive(Clone)]
+pub struct Collector {
+    pub collection: IngressCollectionWrapper,
+    pub updates: CollectionUpdates,
+    pub last_success: LastSuccess,
+    pub refresh: RefreshRequests,
+    pub config: ConfigUpdates,
+    /// Kept across restarts, only the task combining the slices of the current config receives
+    requests: Arc<tokio::sync::Mutex<mpsc::Receiver<oneshot::Sender<()>>>>,
+    /// All tasks collecting with the current config, dropping them stops the tasks
+    tasks: Arc<Mutex<JoinSet<()>>>,
+}
+
+/// Starts collecting in the background, the collection stays empty until the first collection succeeded
+pub fn start_collector(config: Config) -> Collector {
+    let (updates, _) = broadcast::channel(16);
+    let (refresh, requests) = mpsc::channel(16);
+    let collector = Collector {
+        collection: Arc::new(RwLock::new(Vec::new())),
+        updates,
+        last_success: Arc::new(RwLock::new(None)),
+        refresh,
+        config: tokio::sync::watch::Sender::new(Arc::default()),
+        requests: Arc::new(tokio::sync::Mutex::new(requests)),
+        tasks: Arc::default(),
+    };
+    collector.restart(config);
+    collector
+}
+
+/// Collects all clusters once and returns the finished collection, for one-shot commands.
+/// The status resource is not updated, the result is only printed.
+pub async fn collect_once(mut config: Config) -> IngressCollection {
+    if let Some(global) = config.global.as_mut() {
+        global.status_resource = None;
+    }
+    config.source_resource = None;
+    let collector = start_collector(config);
+    let mut updates = collector.updates.subscribe();
+    if collector.last_success.read().await.is_none() {
+        // The collector keeps running, so the channel is never closed
+        let _ = updates.recv().await;
+    }
+    collector.collection.read().await.clone()
+}
+
+impl Collector {
+    /// Stops all tasks and starts collecting with the given config. The current collection stays
+    /// available until every cluster of the new config was collected once.
+    pub fn restart(&self, config: Config) {
+        self.config.send_replace(Arc::new(config.clone()));
+        let mut tasks = JoinSet::new();
+        spawn_tasks(config, self, &mut tasks);
+        // Replacing the tasks drops (and thereby aborts) the old ones
+        *self.tasks.lock().expect("tasks lock is never poisoned") = tasks;
+    }
+}
+
+fn spawn_tasks(config: Config, collector: &Collector, tasks: &mut JoinSet<()>) {
+    let sources = cluster_sources(&config);
+    let (done, done_receiver) = mpsc::unbounded_channel();
+    let shared = SharedState {
+        slices: Arc::new(RwLock::new(vec![None; sources.len()])),
+        collected: Arc::new(Notify::new()),
+        // Bounds the number of concurrent collections across all clusters and namespaces
+        limit: Arc::new(Semaphore::new(
+            config
+                .global
+                .as_ref()
+                .and_then(|g| g.max_concurrent_collections)
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_COLLECTIONS)
+                .max(1),
+        )),
+        triggers: broadcast::channel(1).0,
+        done,
+    };
+    for (index, source) in sources.into_iter().enumerate() {
+        tasks.spawn(run_cluster_task(
+            config.clone(),
+            source,
+            index,
+            shared.clone(),
+        ));
+    }
+    tasks.spawn(run_collector_task(
+        config,
+        shared,
+        done_receiver,
+        collector.clone(),
+    ));
+}
+
+/// A cluster of the config, every cluster is collected by its own task
+#[derive(Clone)]
+enum ClusterSource {
+    Local(Box<LocalCluster>),
+    /// A remote cluster with the name of its group
+    Remote(String, Box<RemoteCluster>),
+    /// The remote clusters registered by labeled kubeconfig secrets
+    Discovered(Discovery),
+    /// The remote clusters registered by Cluster API
+    ClusterApi(ClusterApi),
 }
 
-async fn run_collector_task(config: Config, info: IngressCollectionWrapper) {
-    let refresh_interval = config
+impl ClusterSource {
+    fn name(&self) -> &str {
+        match self {
+            ClusterSource::Local(_) => "local",
+            ClusterSource::Remote(_, remote) => &remote.name,
+            ClusterSource::Discovered(_) => "discovered",
+            ClusterSource::ClusterApi(_) => "cluster-api",
+        }
+    }
+
+    /// The interval of the cluster, otherwise the global one
+    fn refresh_interval(&self, config: &Config) -> Duration {
+        let cluster_interval = match self {
+            ClusterSource::Local(local) => local.refresh_interval_seconds(),
+            ClusterSource::Remote(_, remote) => remote.refresh_interval_seconds(),
+            ClusterSource::Discovered(_) | ClusterSource::ClusterApi(_) => None,
+        };
+        let seconds = cluster_interval
+            .or_else(|| {
+                config
+                    .global
+                    .as_ref()
+                    .and_then(|g| g.refresh_interval_seconds)
+            })
+            .unwrap_or(30);
+        Duration::from_secs(seconds)
+    }
+}
+
+fn cluster_sources(config: &Config) -> Vec<ClusterSource> {
+    let local = config
+        .local
+        .iter()
+        .filter(|local| local.enabled)
+        .map(|local| ClusterSource::Local(Box::new(local.clone())));
+    let remotes = config
+        .remote
+        .iter()
+        .flatten()
+        .flat_map(|(group_name, clusters)| {
+            clusters
+                .iter()
+                .map(|remote| ClusterSource::Remote(group_name.0.clone(), Box::new(remote.clone())))
+        });
+    let discovered = config
+        .discovery
+        .iter()
+        .filter(|discovery| discovery.enabled)
+        .map(|discovery| ClusterSource::Discovered(discovery.clone()));
+    let cluster_api = config
+        .cluster_api
+        .iter()
+        .filter(|cluster_api| cluster_api.enabled)
+        .map(|cluster_api| ClusterSource::ClusterApi(cluster_api.clone()));
+    local
+        .chain(remotes)
+        .chain(discovered)
+        .chain(cluster_api)
+        .collect()
+}
+
+/// Collected clusters of a source, each together with the name of its group
+type Slice = Vec<(String, ClusterInfo)>;
+
+/// The latest slice of every source in the order of the sources, `None` until it was collected once
+type Slices = Arc<RwLock<Vec<Option<Slice>>>>;
+
+/// State shared by the tasks of the clusters and the task combining their slices
+#[derive(Clone)]
+struct SharedState {
+    slices: Slices,
+    /// Notified whenever a slice was updated
+    collected: Arc<Notify>,
+    limit: Arc<Semaphore>,
+    /// Asks all cluster tasks to collect immediately
+    triggers: broadcast::Sender<()>,
+    /// Every cluster task reports here once it finished a triggered collection
+    done: mpsc::UnboundedSender<()>,
+}
+
+/// Collects a single cluster in its own interval (or when its ingresses changed) and stores it as its slice
+async fn run_cluster_task(
+    config: Config,
+    source: ClusterSource,
+    index: usize,
+    shared: SharedState,
+) {
+    let mut triggers = shared.triggers.subscribe();
+    let refresh_interval = source.refresh_interval(&config);
+    let watch_debounce =
+        config.global.as_ref().filter(|g| g.watch).map(|g| {
+            Duration::from_millis(g.watch_debounce_ms.unwrap_or(DEFAULT_WATCH_DEBOUNCE_MS))
+        });
+    let changed = Arc::new(Notify::new());
+    let ingresses = IngressStore::default();
+    // Stopped together with this task
+    let mut watches = JoinSet::new();
+    if watch_debounce.is_some() {
+        match &source {
+            ClusterSource::Local(local) => {
+                watch::watch_local(&config, local, &ingresses, changed.clone(), &mut watches)
+            }
+            ClusterSource::Remote(_, remote) => {
+                let rotated = Arc::new(Notify::new());
+                watch::watch_remote(
+                    &config,
+                    remote,
+                    &ingresses,
+                    changed.clone(),
+                    rotated.clone(),
+                    &mut watches,
+                );
+                watch::watch_secrets(remote, changed.clone(), rotated, &mut watches);
+            }
+            ClusterSource::Discovered(discovery) => {
+                watch::watch_discovery(discovery, changed.clone(), &mut watches)
+            }
+            ClusterSource::ClusterApi(cluster_api) => {
+                watch::watch_cluster_api(cluster_api, changed.clone(), &mut watches)
+            }
+        }
+    }
+    let jitter = config
         .global
         .as_ref()
-        .and_then(|g| g.refresh_interval_seconds)
-        .unwrap_or(30);
+        .and_then(|g| g.refresh_jitter_seconds);
+    let startup_delay = config.global.as_ref().and_then(|g| g.startup_delay_seconds);
+    tokio::time::sleep(random_delay(startup_delay)).await;
+    let mut first = true;
     loop {
-        tokio::time::sleep(Duration::from_secs(refresh_interval)).await;
-        tracing::info!("Reloading ingresses");
-        let new_info = match collect_for_all_clusters(&config).await {
-            Ok(result) => result,
+        let mut triggered = false;
+        if !first {
+            tokio::select! {
+                _ = tokio::time::sleep(refresh_interval + random_delay(jitter)) => {}
+                _ = debounced_change(&changed, watch_debounce.unwrap_or_default()) => {
+                    tracing::debug!("Ingresses changed, refreshing before the next interval");
+                }
+                _ = triggers.recv() => triggered = true,
+            }
+        }
+        first = false;
+        tracing::info!("Reloading ingresses of cluster {}", source.name());
+        match collect_source(&config, &source, &shared.limit, &ingresses).await {
+            Ok(mut slice) => {
+                check_health(&config, &mut slice).await;
+                shared.slices.write().await[index] = Some(slice);
+            }
             Err(err) => {
-                tracing::error!("Encountered error when reloading ingresses: {err}");
-                continue;
+                match err {
+                    // Already logged as warning, the cluster may still be provisioned
+                    Error::MissingKubeconfig(_) => tracing::debug!(
+                        "Kubeconfig of cluster {} does not exist (yet): {err}",
+                        source.name()
+                    ),
+                    _ => tracing::error!(
+                        "Encountered error when reloading ingresses of cluster {}: {err}",
+                        source.name()
+                    ),
+                }
+                keep_stale(
+                    &config,
+                    &source,
+                    &mut shared.slices.write().await[index],
+                    &err,
+                );
             }
-        };
-        let mut lock = info.write().await;
-        *lock = new_info;
+        }
+        if triggered {
+            // The receiver only goes away with the whole collector
+            let _ = shared.done.send(());
+        } else {
+            shared.collected.notify_one();
+        }
     }
 }
 
-pub async fn collect_for_all_clusters(config: &Config) -> Result<IngressCollection> {
-    let mut result = Vec::new();
-    let client = kube::Client::try_default().await?;
-
-    // Local cluster as its own group named "local"
-    if let Some(local) = config.local.as_ref()
-        && local.enabled
+/// Probes the ingresses of a newly collected slice if health checks are enabled, so that only the
+/// collected clusters are checked and not the whole collection
+async fn check_health(config: &Config, slice: &mut Slice) {
+    if let Some(health_check) = config.global.as_ref().and_then(|g| g.health_check.as_ref())
+        && health_check.enabled
     {
-        let cluster_info = if let Some(namespaces) = local.namespaces.as_ref() {
-            let mut collected = Vec::new();
-            for namespace in namespaces.iter() {
-                collected
-                    .append(&mut collect_ingresses(config, client.clone(), Some(namespace)).await?);
-            }
-            transform_to_info("local".to_owned(), &local.description, collected)
-        } else {
-            transform_to_info(
-                "local".to_owned(),
-                &local.description,
-                collect_ingresses(config, client.clone(), None).await?,
-            )
-        };
-        result.push(GroupInfo {
-            name: "local".to_owned(),
-            clusters: vec![cluster_info],
-        });
+        let ingresses = slice
+            .iter_mut()
+            .flat_map(|(_, cluster)| cluster.ingresses.iter_mut());
+        health::check_ingresses(health_check, ingresses).await;
     }
+}
 
-    // Remote clusters by group
-    if let Some(remotes) = config.remote.as_ref() {
-        for (group_name, clusters) in remotes.iter() {
-            let mut group_clusters = Vec::new();
-            for remote in clusters.iter() {
-                if let Some(clusterinfo) = collect_from_remote(config, remote, client.clone()).await
-                {
-                    group_clusters.push(clusterinfo);
-                }
-            }
-            result.push(GroupInfo {
-                name: group_name.0.clone(),
-                clusters: group_clusters,
-            });
+/// Waits for a change and then for the debounce window, so that a burst of changes leads to only one refresh
+async fn debounced_change(changed: &Notify, window: Duration) {
+    changed.notified().await;
+    tokio::time::sleep(window).await;
+    // Changes during the window stored a permit, which would otherwise trigger a second refresh
+    let _ = changed.notified().now_or_never();
+}
+
+/// Random delay of up to the given seconds, used to spread the load of several replicas on the clusters
+fn random_delay(max_seconds: Option<u64>) -> Duration {
+    match max_seconds {
+        Some(max_seconds) if max_seconds > 0 => {
+            Duration::from_millis(rand::thread_rng().gen_range(0..=max_seconds * 1000))
         }
+        _ => Duration::ZERO,
     }
-
-    Ok(result)
 }
 
-async fn collect_from_remote(
-    config: &Config,
-    remote: &RemoteCluster,
-    client: Client,
-) -> Option<ClusterInfo> {
-    let remote_client = match kubeconfig(remote, client).await {
-        Ok(client) => client,
-        Err(err) => {
-            tracing::error!("Could not create client to remote cluster: {err}");
-            return None;
+/// Keeps the last collected clusters of a failed source, marked as stale.
+/// A source that was never collected is shown as failed instead (if `showClusterErrors` is set), so
+/// that the other clusters are published anyway. With `strictRemotes` a remote cluster with an
+/// invalid kubeconfig is left out.
+fn keep_stale(config: &Config, source: &ClusterSource, slice: &mut Option<Slice>, err: &Error) {
+    if let Some(clusters) = slice {
+        for (_, cluster) in clusters.iter_mut() {
+            cluster.stale = true;
+            cluster.error = Some(err.to_string());
+        }
+        return;
+    }
+    let strict = config
+        .global
+        .as_ref()
+        .map(|g| g.strict_remotes)
+        .unwrap_or_default();
+    let (group_name, description) = match source {
+        ClusterSource::Local(local) => (config.local_group().to_owned(), &local.description),
+        ClusterSource::Remote(_, _) if strict && matches!(err, Error::InvalidKubeconfig(_)) => {
+            *slice = Some(Vec::new());
+            return;
         }
+        ClusterSource::Remote(group_name, remote) => (group_name.clone(), &remote.description),
+        ClusterSource::Discovered(discovery) => (
+            discovery
+                .default_group
+                .clone()
+                .unwrap_or_else(|| discovery::DEFAULT_GROUP.to_owned()),
+            &None,
+        ),
+        ClusterSource::ClusterApi(cluster_api) => (
+            cluster_api
+                .default_group
+                .clone()
+                .unwrap_or_else(|| discovery::DEFAULT_CLUSTER_API_GROUP.to_owned()),
+            &None,
+        ),
     };
+    let cluster = failed_cluster(config, source.name(), description, err.to_string());
+    *slice = Some(
+        cluster
+            .into_iter()
+            .map(|cluster| (group_name.clone(), cluster))
+            .collect(),
+    );
+}
 
-    if let Some(namespaces) = remote.namespaces.as_ref() {
-        let mut collected = Vec::new();
-        for namespace in namespaces.iter() {
-            match collect_ingresses(config, remote_client.clone(), Some(namespace)).await {
-                Ok(mut specs) => collected.append(&mut specs),
-                Err(err) => tracing::error!("Could not read ingressess from cluster: {err}"),
-            }
+/// Combines the slices into the collection every time a cluster was collected.
+/// Nothing is published until every cluster was collected once.
+async fn run_collector_task(
+    config: Config,
+    shared: SharedState,
+    mut done: mpsc::UnboundedReceiver<()>,
+    collector: Collector,
+) {
+    // Released when the task is stopped by a restart
+    let mut requests = collector.requests.lock().await;
+    let mut answer: Option<oneshot::Sender<()>> = None;
+    loop {
+        let complete: Option<Vec<Slice>> = shared.slices.read().await.iter().cloned().collect();
+        if let Some(complete) = complete {
+            publish(&config, &collector, group_slices(complete)).await;
         }
-        Some(transform_to_info(
-            remote.name.clone(),
-            &remote.description,
-            collected,
-        ))
-    } else {
-        match collect_ingresses(config, remote_client.clone(), None).await {
-            Ok(specs) => Some(transform_to_info(
-                remote.name.clone(),
-                &remote.description,
-                specs,
-            )),
-            Err(err) => {
-                tracing::error!("Could not read ingressess from cluster: {err}");
-                None
+        if let Some(answer) = answer.take() {
+            // The requester may have given up waiting
+            let _ = answer.send(());
+        }
+        tokio::select! {
+            // Clusters that finish at about the same time are published together
+            _ = debounced_change(&shared.collected, PUBLISH_DEBOUNCE) => {}
+            Some(request) = requests.recv() => {
+                tracing::info!("Refresh requested, collecting all clusters");
+                // Reports of clusters that were too late for an earlier refresh
+                while done.try_recv().is_ok() {}
+                // Sending fails if there are no clusters, then there is nothing to wait for
+                let clusters = shared.triggers.send(()).unwrap_or_default();
+                let all_done = async {
+                    for _ in 0..clusters {
+                        done.recv().await;
+                    }
+                };
+                if tokio::time::timeout(REFRESH_TIMEOUT, all_done).await.is_err() {
+                    tracing::warn!(
+                        "Not all clusters were collected within {}s, publishing the refresh anyway",
+                        REFRESH_TIMEOUT.as_secs()
+                    );
+                }
+                answer = Some(request);
             }
         }
     }
 }
 
-async fn kubeconfig(remote: &RemoteCluster, client: Client) -> Result<Client> {
-    let secret_api = Api::<Secret>::namespaced(client, &remote.kubeconfig_secret.namespace);
-    let error_name = format!(
-        "{}/{}",
-        remote.kubeconfig_secret.namespace, remote.kubeconfig_secret.name
-    );
+/// Finishes the collection and makes it available to the page, the status resource and subscribers
+async fn publish(config: &Config, collector: &Collector, collection: IngressCollection) {
+    let mut new_info = finish_collection(config, collection).await;
+    if let Some(grace_period) = config
+        .global
+        .as_ref()
+        .and_then(|g| g.ingress_grace_period_seconds)
+    {
+        let previous = collector.collection.read().await;
+        retain_disappeared(&previous, &mut new_info, grace_period, Utc::now());
+    }
+    let mut lock = collector.collection.write().await;
+    *lock = new_info.clone();
+    drop(lock);
+    *collector.last_success.write().await = Some(Utc::now());
+    if let Some(resource) = config.status_resource() {
+        status::publish(resource, &new_info).await;
+    }
+    // Sending only fails if nobody is subscribed
+    let _ = collector.updates.send(Arc::new(new_info));
+}
 
-    let secret = match secret_api.get(&remote.kubeconfig_secret.name).await {
-        Ok(result) => result,
-        Err(err) => {
-            return Err(Error::MissingKubeconfig(format!(
-                "Could not get kubeconfig secret {error_name}: {err}"
-            )));
-        }
-    };
-    let Some(data) = secret.data.as_ref() else {
-        return Err(Error::MissingKubeconfig(format!(
-            "Could not get kubeconfig secret {error_name}: No data"
-        )));
-    };
-    let Some(kubeconfig_data) = data.get("value") else {
-        return Err(Error::MissingKubeconfig(format!(
-            "Could not get kubeconfig secret {error_name}: No data field kubeconfig"
-        )));
+/// Collects the clusters of a single source, the local cluster is in the group named "local" unless it is assigned to another one
+#[tracing::instrument(skip_all, fields(cluster = %source.name()))]
+async fn collect_source(
+    config: &Config,
+    source: &ClusterSource,
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Result<Slice> {
+    let client = match source {
+        ClusterSource::Local(local) => local_client(local).await?,
+        _ => kube::Client::try_default().await?,
     };
-
-    let kubeconfig: Kubeconfig = serde_yaml::from_slice(&kubeconfig_data.0)
-        .map_err(|err| Error::MissingKubeconfig(err.to_string()))?;
-    // create client from kubeconfig
-    let mut config =
-        kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
-            .await
-            .map_err(|err| Error::MissingKubeconfig(err.to_string()))?;
-    config.accept_invalid_certs = true;
-    Ok(config.try_into()?)
+    match source {
+        ClusterSource::Local(local) => {
+            let collection = collect_local(config, local, client, limit, ingresses);
+            let clusters = match cluster_timeout(config) {
+                Some(timeout) => {
+                    tokio::time::timeout(timeout, collection)
+                        .await
+                        .map_err(|_| {
+                            Error::Timeout(format!(
+                                "Collecting the local cluster timed out after {}s",
+                                timeout.as_secs()
+                            ))
+                        })??
+                }
+                None => collection.await?,
+            };
+            Ok(clusters
+                .into_iter()
+                .map(|cluster| (config.local_group().to_owned(), cluster))
+                .collect())
+        }
+        ClusterSource::Remote(group_name, remote) => {
+            let cluster = retry::retry(
+                &retry::policy(config),
+                &format!("collecting cluster {}", remote.name),
+                retry::is_transient,
+                || {
+                    collect_from_remote_with_timeout(
+                        config,
+                        remote,
+                        client.clone(),
+                        limit,
+                        ingresses,
+                    )
+                },
+            )
+            .await?;
+            Ok(vec![(group_name.clone(), cluster)])
+        }
+        ClusterSource::Discovered(discovery) => {
+            let remotes = discovery::discover_secrets(discovery, client.clone()).await?;
+            Ok(collect_discovered(config, remotes, client, limit).await)
+        }
+        ClusterSource::ClusterApi(cluster_api) => {
+            let remotes = discovery::discover_cluster_api(cluster_api, client.clone()).await?;
+            Ok(collect_discovered(config, remotes, client, limit).await)
+        }
+    }
 }
 
-async fn collect_ingresses(
+/// Collects discovered remote clusters concurrently. A cluster that can not be collected is
+/// shown as failed, it is not kept as stale since it may have been deregistered.
+async fn collect_discovered(
     config: &Config,
+    remotes: Vec<(String, RemoteCluster)>,
     client: Client,
-    namespace: Option<&str>,
-) -> Result<Vec<IngressSpec>> {
-    let api = if let Some(namespace) = namespace {
-        Api::<Ingress>::namespaced(client, namespace)
-    } else {
-        Api::<Ingress>::all(client)
-    };
-    let only_with_annotation = config
+    limit: &Semaphore,
+) -> Slice {
+    let policy = retry::policy(config);
+    let (policy, client) = (&policy, &client);
+    // Discovered clusters are not watched, their ingresses are always listed
+    let ingresses = &IngressStore::default();
+    let collections = remotes.iter().map(|(_, remote)| async move {
+        retry::retry(
+            policy,
+            &format!("collecting cluster {}", remote.name),
+            retry::is_transient,
+            || collect_from_remote_with_timeout(config, remote, client.clone(), limit, ingresses),
+        )
+        .await
+    });
+    let results = join_all(collections).await;
+    remotes
+        .iter()
+        .zip(results)
+        .filter_map(|((group_name, remote), result)| {
+            let cluster = match result {
+                Ok(cluster) => Some(cluster),
+                Err(err) => {
+                    tracing::error!(
+                        "Encountered error when reloading ingresses of discovered cluster {}: {err}",
+                        remote.name
+                    );
+                    failed_cluster(config, &remote.name, &remote.description, err.to_string())
+                }
+            };
+            cluster.map(|cluster| (group_name.clone(), cluster))
+        })
+        .collect()
+}
+
+/// Merges the slices into groups, in the order the groups first appear
+fn group_slices(slices: Vec<Slice>) -> IngressCollection {
+    let mut result: IngressCollection = Vec::new();
+    for (group_name, cluster) in slices.into_iter().flatten() {
+        match result.iter_mut().find(|group| group.name == group_name) {
+            Some(group) => group.clusters.push(cluster),
+            None => result.push(GroupInfo {
+                name: group_name,
+                description: String::new(),
+                clusters: vec![cluster],
+                links: Vec::new(),
+            }),
+        }
+    }
+    result
+}
+
+/// Applies everything that needs the clusters of all groups: regrouping, deduplication,
+/// static links, group descriptions, sorting and hiding
+async fn finish_collection(config: &Config, mut result: IngressCollection) -> IngressCollection {
+    regroup(&mut result);
+    if config.global.as_ref().is_some_and(|g| g.dedup_by_url) {
+        dedup_by_url(&mut result);
+    }
+    add_static_links(config, &mut result);
+
+    for group in result.iter_mut() {
+        if let Some(description) = config
+            .group(&group.name)
+            .and_then(|g| g.description.as_ref())
+        {
+            group.description = description.clone();
+        }
+    }
+    if let Some(group_descriptions) = config
         .global
         .as_ref()
-        .map(|g| g.only_with_annotation)
-        .unwrap_or_default();
-    let params = ListParams::default();
-    let object_list = api.list(&params).await?;
+        .and_then(|g| g.group_descriptions.as_ref())
+        && group_descriptions.enabled
+    {
+        match kube::Client::try_default().await {
+            Ok(client) => describe_groups(group_descriptions, &mut result, client).await,
+            Err(err) => tracing::error!("Could not read group descriptions: {err}"),
+        }
+    }
 
-    let mut result = Vec::new();
+    // The health was checked when the clusters were collected, see `check_health`
+    if config
+        .global
+        .as_ref()
+        .is_some_and(|g| g.sort_by_health && g.health_check.as_ref().is_some_and(|h| h.enabled))
+    {
+        health::sort_by_health(&mut result);
+    }
 
-    for ingress in object_list {
-        let name = ingress.name_any();
-        if only_with_annotation {
-            if let Some(annotations) = ingress.metadata.annotations.as_ref() {
-                if annotations.get(NAME_ANNOTATION).is_none()
-                    && annotations.get(DESCRIPTION_ANNOTATION).is_none()
-                {
-                    // none of our annotations, filter it out
-                    continue;
-                }
-            } else {
-                // no annotations at all, filter it out
-                continue;
-            }
+    // Stable, so groups without order keep their order
+    result.sort_by_key(|group| {
+        config
+            .group(&group.name)
+            .and_then(|g| g.order)
+            .map_or((1, 0), |order| (0, order))
+    });
+
+    sort_by_category(
+        &mut result,
+        config
+            .global
+            .as_ref()
+            .map(|g| g.category_order.as_slice())
+            .unwrap_or_default(),
+    );
+
+    // Only after all sorting, so the ingresses shown first are kept
+    if let Some(max_ingresses) = config
+        .global
+        .as_ref()
+        .and_then(|g| g.max_ingresses_per_cluster)
+    {
+        truncate_clusters(&mut result, max_ingresses);
+    }
+
+    if let Some(global) = config.global.as_ref() {
+        hide_empty(
+            &mut result,
+            global.hide_empty_clusters,
+            global.hide_empty_groups,
+        );
+    }
+    if !show_cluster_errors(config) {
+        for cluster in result
+            .iter_mut()
+            .flat_map(|group| group.clusters.iter_mut())
+        {
+            cluster.error = None;
         }
-        let Some(spec) = ingress.spec else {
-            continue;
-        };
-        for rule in spec.rules.unwrap_or_default() {
-            let Some(host) = rule.host else {
-                continue;
-            };
-            for path in rule.http.unwrap_or_default().paths {
-                result.push(IngressSpec {
-                    name: name.clone(),
-                    namespace: ingress
-                        .metadata
-                        .namespace
-                        .clone()
-                        .unwrap_or_else(|| "default".to_owned()),
-                    host: host.clone(),
-                    tls_used: true,
-                    path: path.path,
-                    annotations: ingress.metadata.annotations.clone().unwrap_or_default(),
-                    labels: ingress.metadata.labels.clone().unwrap_or_default(),
-                })
+    }
+
+    result
+}
+
+/// Only keeps the first `max_ingresses` ingresses of every cluster, the rest is counted in `hidden_count`
+fn truncate_clusters(collection: &mut IngressCollection, max_ingresses: usize) {
+    for cluster in collection
+        .iter_mut()
+        .flat_map(|group| group.clusters.iter_mut())
+    {
+        if cluster.ingresses.len() > max_ingresses {
+            cluster.hidden_count = cluster.ingresses.len() - max_ingresses;
+            cluster.truncated = true;
+            cluster.ingresses.truncate(max_ingresses);
+        }
+    }
+}
+
+/// Moves ingresses that are assigned to groups via annotation into these groups.
+/// Within a group they are placed in a cluster with the same name as their original cluster.
+fn regroup(collection: &mut IngressCollection) {
+    let mut assigned = Vec::new();
+    for group in collection.iter_mut() {
+        for cluster in group.clusters.iter_mut() {
+            let (with_groups, without_groups) = cluster
+                .ingresses
+                .drain(..)
+                .partition(|ingress| !ingress.groups.is_empty());
+            cluster.ingresses = without_groups;
+            for ingress in with_groups {
+                assigned.push((cluster.name.clone(), cluster.description.clone(), ingress));
             }
         }
     }
 
-    Ok(result)
+    for (cluster_name, cluster_description, ingress) in assigned {
+        for group_name in ingress.groups.iter() {
+            let cluster =
+                cluster_entry(collection, group_name, &cluster_name, &cluster_description);
+            // The same ingress can be listed in a group only once
+            if !cluster
+                .ingresses
+                .iter()
+                .any(|i| i.name == ingress.name && i.url == ingress.url)
+            {
+                cluster.ingresses.push(ingress.clone());
+                sort_ingresses(&mut cluster.ingresses);
+            }
+        }
+    }
 }
 
-fn transform_to_info(
-    cluster_name: String,
-    description: &Option<String>,
-    input: Vec<IngressSpec>,
-) -> ClusterInfo {
-    let ingresses = input
-        .into_iter()
-        .map(|i| {
-            let url = format!(
-                "https://{}{}",
-                i.host,
-                i.path.unwrap_or_else(|| "/".to_owned())
-            );
-            let name = i.annotations.get(NAME_ANNOTATION).unwrap_or(&i.name);
-            let description = i
-                .annotations
-                .get(DESCRIPTION_ANNOTATION)
-                .map(|s| s.to_owned())
-                .unwrap_or_default();
-            IngressInfo {
-                name: name.to_owned(),
-                description,
-                url,
+/// Collapses ingresses with the same URL across all clusters into one entry.
+/// The entry with the most information is kept and records the clusters the URL was found in.
+fn dedup_by_url(collection: &mut IngressCollection) {
+    // Position of an ingress as indices of group, cluster and ingress
+    type Position = (usize, usize, usize);
+    // For every URL the position of the entry to keep and the clusters it was found in
+    let mut by_url: HashMap<String, (Position, Vec<String>)> = HashMap::new();
+    for (group_index, group) in collection.iter().enumerate() {
+        for (cluster_index, cluster) in group.clusters.iter().enumerate() {
+            for (ingress_index, ingress) in cluster.ingresses.iter().enumerate() {
+                let position = (group_index, cluster_index, ingress_index);
+                let (best, sources) = by_url
+                    .entry(canonical_url(&ingress.url))
+                    .or_insert_with(|| (position, Vec::new()));
+                let (best_group, best_cluster, best_ingress) = *best;
+                let best_ingress =
+                    &collection[best_group].clusters[best_cluster].ingresses[best_ingress];
+                if richness(ingress) > richness(best_ingress) {
+                    *best = position;
+                }
+                sources.push(cluster.name.clone());
             }
-        })
-        .collect();
-    ClusterInfo {
-        name: cluster_name,
-        description: description.clone().unwrap_or_default(),
+        }
+    }
+
+    for (group_index, group) in collection.iter_mut().enumerate() {
+        for (cluster_index, cluster) in group.clusters.iter_mut().enumerate() {
+            let mut ingress_index = 0;
+            cluster.ingresses.retain_mut(|ingress| {
+                let position = (group_index, cluster_index, ingress_index);
+                ingress_index += 1;
+                let (best, sources) = &by_url[&canonical_url(&ingress.url)];
+                if *best != position {
+                    return false;
+                }
+                let mut sources = sources.clone();
+                sources.sort();
+                sources.dedup();
+                if sources.len() > 1 {
+                    ingress.sources = sources;
+                }
+                true
+            });
+        }
+    }
+}
+
+fn canonical_url(url: &str) -> String {
+    // Parsing normalizes the case of scheme and host and removes default ports
+    Url::parse(url)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| url.to_owned())
+}
+
+/// How much information an ingress carries, used to pick the entry to keep when deduplicating
+fn richness(ingress: &IngressInfo) -> usize {
+    ingress.annotations.len()
+        + usize::from(!ingress.description.is_empty())
+        + usize::from(ingress.docs_url.is_some())
+        + usize::from(ingress.secondary_url.is_some())
+}
+
+/// Orders the ingresses of every cluster by their category so each category forms a section.
+/// Categories in `category_order` come first in that order, the others follow alphabetically
+/// and ingresses without category come last. Within a category the existing order is kept.
+fn sort_by_category(collection: &mut IngressCollection, category_order: &[String]) {
+    for cluster in collection
+        .iter_mut()
+        .flat_map(|group| group.clusters.iter_mut())
+    {
+        cluster.ingresses.sort_by(|a, b| {
+            category_rank(a, category_order).cmp(&category_rank(b, category_order))
+        });
+    }
+}
+
+fn category_rank<'a>(
+    ingress: &'a IngressInfo,
+    category_order: &[String],
+) -> (usize, Option<&'a str>) {
+    match ingress.category.as_deref() {
+        Some(category) => match category_order.iter().position(|c| c == category) {
+            Some(index) => (index, None),
+            None => (category_order.len(), Some(category)),
+        },
+        None => (category_order.len() + 1, None),
+    }
+}
+
+/// Returns the group with the given name, it is created if it does not exist
+fn group_entry<'a>(collection: &'a mut IngressCollection, group_name: &str) -> &'a mut GroupInfo {
+    match collection.iter().position(|g| g.name == group_name) {
+        Some(index) => &mut collection[index],
+        None => {
+            collection.push(GroupInfo {
+                name: group_name.to_owned(),
+                description: String::new(),
+                clusters: Vec::new(),
+                links: Vec::new(),
+            });
+            collection.last_mut().expect("group was just added")
+        }
+    }
+}
+
+/// Adds the static links from the config to their groups or clusters, both are created if they do not exist
+fn add_static_links(config: &Config, collection: &mut IngressCollection) {
+    let url_options = UrlOptions::from_config(config);
+    for link in config.static_links.iter().flatten() {
+        let url = match normalize_url(&link.url, "", true, url_options) {
+            Ok(url) => url,
+            Err(err) => {
+                tracing::warn!("Ignoring static link {}: {err}", link.name);
+                continue;
+            }
+        };
+        let icon =
+            link.icon
+                .as_ref()
+                .and_then(|icon| match normalize_url(icon, "", true, url_options) {
+                    Ok(icon) => Some(icon),
+                    Err(err) => {
+                        tracing::warn!("Ignoring icon of static link {}: {err}", link.name);
+                        None
+                    }
+                });
+        let info = LinkInfo {
+            name: link.name.clone(),
+            description: link.description.clone().unwrap_or_default(),
+            url,
+            icon,
+        };
+        match link.cluster.as_deref() {
+            Some(cluster_name) => cluster_entry(collection, &link.group, cluster_name, "")
+                .links
+                .push(info),
+            None => group_entry(collection, &link.group).links.push(info),
+        }
+    }
+}
+
+/// Returns the cluster with the given name in the given group, both are created if they do not exist
+fn cluster_entry<'a>(
+    collection: &'a mut IngressCollection,
+    group_name: &str,
+    cluster_name: &str,
+    cluster_description: &str,
+) -> &'a mut ClusterInfo {
+    let group = group_entry(collection, group_name);
+    match group.clusters.iter().position(|c| c.name == cluster_name) {
+        Some(index) => &mut group.clusters[index],
+        None => {
+            group.clusters.push(ClusterInfo {
+                name: cluster_name.to_owned(),
+                description: cluster_description.to_owned(),
+                ingresses: Vec::new(),
+                truncated: false,
+                hidden_count: 0,
+                error: None,
+                gateways: Vec::new(),
+                links: Vec::new(),
+                last_updated: Utc::now(),
+                stale: false,
+            });
+            group.clusters.last_mut().expect("cluster was just added")
+        }
+    }
+}
+
+/// Keeps ingresses of the previous collection that are missing from the current one
+/// until they were not seen for longer than the grace period. Retained ingresses are marked as stale.
+fn retain_disappeared(
+    previous: &IngressCollection,
+    current: &mut IngressCollection,
+    grace_period_seconds: i64,
+    now: DateTime<Utc>,
+) {
+    for group in previous.iter() {
+        for cluster in group.clusters.iter() {
+            for ingress in cluster.ingresses.iter() {
+                if (now - ingress.last_seen).num_seconds() > grace_period_seconds {
+                    continue;
+                }
+                let entry =
+                    cluster_entry(current, &group.name, &cluster.name, &cluster.description);
+                if entry
+                    .ingresses
+                    .iter()
+                    .any(|i| i.name == ingress.name && i.url == ingress.url)
+                {
+                    continue;
+                }
+                tracing::debug!(
+                    "Ingress {} in cluster {} disappeared, keeping it for the grace period",
+                    ingress.name,
+                    cluster.name
+                );
+                entry.ingresses.push(IngressInfo {
+                    stale: true,
+                    ..ingress.clone()
+                });
+                sort_ingresses(&mut entry.ingresses);
+            }
+        }
+    }
+}
+
+/// Removes clusters without ingresses and groups without clusters.
+/// Clusters with collection errors are kept as they are not necessarily empty.
+fn hide_empty(collection: &mut IngressCollection, clusters: bool, groups: bool) {
+    if clusters {
+        for group in collection.iter_mut() {
+            group.clusters.retain(|cluster| !cluster_is_empty(cluster));
+        }
+    }
+    if groups {
+        collection.retain(|group| {
+            !group.links.is_empty()
+                || group
+                    .clusters
+                    .iter()
+                    .any(|cluster| !cluster_is_empty(cluster))
+        });
+    }
+}
+
+fn cluster_is_empty(cluster: &ClusterInfo) -> bool {
+    cluster.ingresses.is_empty()
+        && cluster.gateways.is_empty()
+        && cluster.links.is_empty()
+        && cluster.error.is_none()
+}
+
+/// Lists the names of all namespaces in the cluster, optionally only those matching the label selector
+async fn discover_namespaces(client: Client, selector: Option<&str>) -> Result<Vec<String>> {
+    let api = Api::<Namespace>::all(client);
+    let mut params = ListParams::default();
+    if let Some(selector) = selector {
+        params = params.labels(selector);
+    }
+    let namespaces = api.list_metadata(&params).await?;
+    Ok(namespaces.into_iter().map(|ns| ns.name_any()).collect())
+}
+
+/// Namespaces to collect from as given by `namespaces` and `namespaceSelector`.
+/// If both are set only the listed namespaces that match the selector are used.
+/// Returns `None` if neither is set.
+async fn configured_namespaces(
+    cluster: &impl ClusterConfig,
+    client: Client,
+) -> Result<Option<Vec<String>>> {
+    let Some(selector) = cluster.namespace_selector() else {
+        return Ok(cluster.namespaces().map(<[String]>::to_vec));
+    };
+    let mut selected = discover_namespaces(client, Some(selector)).await?;
+    if let Some(namespaces) = cluster.namespaces() {
+        selected.retain(|namespace| namespaces.contains(namespace));
+    }
+    Ok(Some(selected))
+}
+
+async fn collect_local(
+    config: &Config,
+    local: &LocalCluster,
+    client: Client,
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Result<Vec<ClusterInfo>> {
+    let namespaces = configured_namespaces(local, client.clone()).await?;
+    let gateways = collect_gateways(local, client.clone(), namespaces.as_deref()).await;
+    let collected = if local.auto_detect_permissions {
+        match permitted_namespaces(local, client.clone(), namespaces).await? {
+            None => collect_ingresses(config, local, client, None, limit, ingresses).await?,
+            Some(namespaces) => {
+                let mut collected = Vec::new();
+                for (_, specs) in
+                    collect_namespaces(config, local, client, &namespaces, limit, ingresses).await
+                {
+                    collected.append(&mut specs?);
+                }
+                collected
+            }
+        }
+    } else if local.discover_namespaces {
+        let namespaces = discover_namespaces(client.clone(), local.namespace_selector()).await?;
+        let mut collected = Vec::new();
+        for (namespace, specs) in
+            collect_namespaces(config, local, client, &namespaces, limit, ingresses).await
+        {
+            match specs {
+                Ok(mut specs) => collected.append(&mut specs),
+                Err(err) if is_forbidden(&err) => {
+                    tracing::debug!("Not allowed to list ingresses in namespace {namespace}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        collected
+    } else if let Some(namespaces) = namespaces {
+        let mut collected = Vec::new();
+        for (_, specs) in
+            collect_namespaces(config, local, client, &namespaces, limit, ingresses).await
+        {
+            collected.append(&mut specs?);
+        }
+        collected
+    } else {
+        collect_ingresses(config, local, client, None, limit, ingresses).await?
+    };
+    if !local.split_by_namespace {
+        let mut cluster_info =
+            transform_to_info(config, "local".to_owned(), &local.description, collected);
+        cluster_info.gateways = gateways;
+        return Ok(vec![cluster_info]);
+    }
+    let mut by_namespace: BTreeMap<String, (Vec<IngressSpec>, Vec<GatewayInfo>)> = BTreeMap::new();
+    for spec in collected {
+        by_namespace
+            .entry(spec.namespace.clone())
+            .or_default()
+            .0
+            .push(spec);
+    }
+    for gateway in gateways {
+        by_namespace
+            .entry(gateway.namespace.clone())
+            .or_default()
+            .1
+            .push(gateway);
+    }
+    Ok(by_namespace
+        .into_iter()
+        .map(|(namespace, (specs, gateways))| {
+            let mut cluster_info = transform_to_info(config, namespace, &None, specs);
+            cluster_info.gateways = gateways;
+            cluster_info
+        })
+        .collect())
+}
+
+/// Lists the gateways if the kind `gateway` is enabled for the cluster.
+/// Errors are only logged as they do not affect the entries of the cluster.
+async fn collect_gateways(
+    cluster: &impl ClusterConfig,
+    client: Client,
+    namespaces: Option<&[String]>,
+) -> Vec<GatewayInfo> {
+    if !cluster.kinds().contains(&ResourceKind::Gateway) {
+        return Vec::new();
+    }
+    let result = match namespaces {
+        Some(namespaces) => try_join_all(
+            namespaces
+                .iter()
+                .map(|namespace| routes::gateways(client.clone(), Some(namespace))),
+        )
+        .await
+        .map(|gateways| gateways.into_iter().flatten().collect()),
+        None => routes::gateways(client, None).await,
+    };
+    let mut gateways = result.unwrap_or_else(|err| {
+        tracing::warn!("Could not read gateways: {err}");
+        Vec::new()
+    });
+    gateways.retain(|gateway| !cluster.exclude_namespaces().contains(&gateway.namespace));
+    gateways
+}
+
+/// Collects from all given namespaces concurrently, the results are in the order of the namespaces
+async fn collect_namespaces<'a>(
+    config: &Config,
+    cluster: &impl ClusterConfig,
+    client: Client,
+    namespaces: &'a [String],
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Vec<(&'a str, Result<Vec<IngressSpec>>)> {
+    join_all(namespaces.iter().map(|namespace| {
+        let client = client.clone();
+        async move {
+            let specs =
+                collect_ingresses(config, cluster, client, Some(namespace), limit, ingresses).await;
+            (namespace.as_str(), specs)
+        }
+    }))
+    .await
+}
+
+/// Uses access reviews to find the namespaces ingresses can be listed in.
+/// Returns `None` if they can be listed in all namespaces.
+/// With a namespace selector only the selected namespaces are considered.
+async fn permitted_namespaces(
+    local: &LocalCluster,
+    client: Client,
+    configured: Option<Vec<String>>,
+) -> Result<Option<Vec<String>>> {
+    if local.namespace_selector.is_none() && can_list_ingresses(local, client.clone(), None).await?
+    {
+        return Ok(None);
+    }
+    let candidates = match configured {
+        Some(namespaces) => namespaces,
+        None => discover_namespaces(client.clone(), None).await?,
+    };
+    let mut permitted = Vec::new();
+    for namespace in candidates {
+        if can_list_ingresses(local, client.clone(), Some(&namespace)).await? {
+            permitted.push(namespace);
+        } else {
+            tracing::debug!("Not allowed to list ingresses in namespace {namespace}");
+        }
+    }
+    Ok(Some(permitted))
+}
+
+async fn can_list_ingresses(
+    local: &LocalCluster,
+    client: Client,
+    namespace: Option<&str>,
+) -> Result<bool> {
+    let group = match local.ingress_api_version() {
+        Some(api_version) => ingress_api_resource(api_version).group,
+        None => "networking.k8s.io".to_owned(),
+    };
+    let review = SelfSubjectAccessReview {
+        spec: SelfSubjectAccessReviewSpec {
+            resource_attributes: Some(ResourceAttributes {
+                group: Some(group),
+                namespace: namespace.map(str::to_owned),
+                resource: Some("ingresses".to_owned()),
+                verb: Some("list".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let api = Api::<SelfSubjectAccessReview>::all(client);
+    let review = api.create(&PostParams::default(), &review).await?;
+    Ok(review.status.is_some_and(|status| status.allowed))
+}
+
+fn is_forbidden(err: &Error) -> bool {
+    matches!(err, Error::Kube(kube::Error::Api(response)) if response.code == 403)
+}
+
+/// Sets the descriptions of the groups that are described by ConfigMaps
+async fn describe_groups(
+    group_descriptions: &GroupDescriptions,
+    collection: &mut IngressCollection,
+    client: Client,
+) {
+    match collect_group_descriptions(group_descriptions, client).await {
+        Ok(descriptions) => {
+            for group in collection.iter_mut() {
+                if let Some(description) = descriptions.get(&group.name) {
+                    group.description = description.clone();
+                }
+            }
+        }
+        Err(err) => tracing::error!("Could not read group descriptions: {err}"),
+    }
+}
+
+async fn collect_group_descriptions(
+    group_descriptions: &GroupDescriptions,
+    client: Client,
+) -> Result<BTreeMap<String, String>> {
+    let api = if let Some(namespace) = group_descriptions.namespace.as_ref() {
+        Api::<ConfigMap>::namespaced(client, namespace)
+    } else {
+        Api::<ConfigMap>::all(client)
+    };
+    let params = ListParams::default().labels(&group_descriptions.label_selector);
+    let mut config_maps = api.list(&params).await?.items;
+    // Sort so that the result is stable if several ConfigMaps describe the same group
+    config_maps.sort_by_key(|cm| (cm.metadata.namespace.clone(), cm.name_any()));
+
+    let mut result = BTreeMap::new();
+    for config_map in config_maps {
+        for (group, description) in config_map.data.unwrap_or_default() {
+            result.entry(group).or_insert(description);
+        }
+    }
+    Ok(result)
+}
+
+/// Maximum time the collection of a single cluster may take
+fn cluster_timeout(config: &Config) -> Option<Duration> {
+    config
+        .global
+        .as_ref()
+        .and_then(|g| g.cluster_timeout_seconds)
+        .map(Duration::from_secs)
+}
+
+/// A remote cluster that takes longer than `clusterTimeoutSeconds` is treated like a failed cluster
+async fn collect_from_remote_with_timeout(
+    config: &Config,
+    remote: &RemoteCluster,
+    client: Client,
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Result<ClusterInfo> {
+    let collection = collect_from_remote(config, remote, client, limit, ingresses);
+    let Some(timeout) = cluster_timeout(config) else {
+        return collection.await;
+    };
+    match tokio::time::timeout(timeout, collection).await {
+        Ok(result) => result,
+        Err(_) => {
+            tracing::debug!(
+                "Collecting remote cluster {} timed out after {}s",
+                remote.name,
+                timeout.as_secs()
+            );
+            Err(Error::Timeout(format!(
+                "Collection timed out after {}s",
+                timeout.as_secs()
+            )))
+        }
+    }
+}
+
+#[tracing::instrument(skip_all, fields(cluster = %remote.name))]
+async fn collect_from_remote(
+    config: &Config,
+    remote: &RemoteCluster,
+    client: Client,
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Result<ClusterInfo> {
+    let permit = limit.acquire().await.expect("semaphore is never closed");
+    let remote_client = remote_client(
+        remote,
+        client,
+        &retry::policy(config),
+        Some(remote.request_timeout()),
+    )
+    .await;
+    drop(permit);
+    let remote_client = match remote_client {
+        Ok(client) => client,
+        Err(Error::MissingKubeconfig(err)) => {
+            // Secret does not exist (yet), e.g. because the cluster is still being provisioned
+            tracing::warn!("Skipping remote cluster {}: {err}", remote.name);
+            return Err(Error::MissingKubeconfig(err));
+        }
+        Err(err) => {
+            tracing::debug!(
+                "Could not create client to remote cluster {}: {err}",
+                remote.name
+            );
+            return Err(err);
+        }
+    };
+
+    let namespaces = match configured_namespaces(remote, remote_client.clone()).await {
+        Ok(namespaces) => namespaces,
+        Err(err) => {
+            tracing::debug!("Could not select namespaces of cluster: {err}");
+            return Err(err);
+        }
+    };
+    let gateways = collect_gateways(remote, remote_client.clone(), namespaces.as_deref()).await;
+    let mut cluster_info = if let Some(namespaces) = namespaces {
+        let mut collected = Vec::new();
+        let mut errors = Vec::new();
+        for (namespace, specs) in
+            collect_namespaces(config, remote, remote_client, &namespaces, limit, ingresses).await
+        {
+            match specs {
+                Ok(mut specs) => collected.append(&mut specs),
+                Err(err) => {
+                    tracing::error!("Could not read ingressess from cluster: {err}");
+                    errors.push(format!("namespace {namespace}: {err}"));
+                }
+            }
+        }
+        let mut cluster_info =
+            transform_to_info(config, remote.name.clone(), &remote.description, collected);
+        if !errors.is_empty() {
+            cluster_info.error = Some(errors.join(", "));
+        }
+        cluster_info
+    } else {
+        match collect_ingresses(config, remote, remote_client, None, limit, ingresses).await {
+            Ok(specs) => transform_to_info(config, remote.name.clone(), &remote.description, specs),
+            Err(err) => {
+                tracing::debug!("Could not read ingressess from cluster: {err}");
+                return Err(err);
+            }
+        }
+    };
+    cluster_info.gateways = gateways;
+    Ok(cluster_info)
+}
+
+/// With `strictRemotes` the kubeconfigs of all remote clusters are checked at startup and an
+/// existing but invalid one is an error. Missing secrets and unreachable clusters are not checked.
+pub async fn check_remotes(config: &Config) -> Result<()> {
+    if !config.global.as_ref().is_some_and(|g| g.strict_remotes) {
+        return Ok(());
+    }
+    check_remote_kubeconfigs(config, Client::try_default().await?).await
+}
+
+async fn check_remote_kubeconfigs(config: &Config, client: Client) -> Result<()> {
+    let retry = retry::policy(config);
+    for remote in config
+        .remote
+        .iter()
+        .flat_map(|remote| remote.values().flatten())
+    {
+        match remote_client(
+            remote,
+            client.clone(),
+            &retry,
+            Some(remote.request_timeout()),
+        )
+        .await
+        {
+            Ok(_) => {}
+            Err(Error::InvalidKubeconfig(err)) => {
+                return Err(Error::InvalidKubeconfig(format!(
+                    "Remote cluster {}: {err}",
+                    remote.name
+                )));
+            }
+            Err(err) => tracing::debug!(
+                "Not checking the kubeconfig of remote cluster {}: {err}",
+                remote.name
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Placeholder for a cluster that could not be collected, only shown if `showClusterErrors` is set
+fn failed_cluster(
+    config: &Config,
+    name: &str,
+    description: &Option<String>,
+    error: String,
+) -> Option<ClusterInfo> {
+    if !show_cluster_errors(config) {
+        return None;
+    }
+    let mut cluster_info = transform_to_info(config, name.to_owned(), description, Vec::new());
+    cluster_info.error = Some(error);
+    Some(cluster_info)
+}
+
+fn show_cluster_errors(config: &Config) -> bool {
+    config
+        .global
+        .as_ref()
+        .map(|g| g.show_cluster_errors)
+        .unwrap_or_default()
+}
+
+/// Creates a client for the remote cluster. If fallback secrets are configured the connection is
+/// verified and the secrets are tried in order until one works.
+/// The request timeout limits connecting and every read and write, the kube defaults are used if it is not set.
+pub(crate) async fn remote_client(
+    remote: &RemoteCluster,
+    client: Client,
+    retry: &Retry,
+    request_timeout: Option<Duration>,
+) -> Result<Client> {
+    let options = ClientOptions::new(remote);
+    if let (Some(api_server_url), Some(eks)) = (remote.api_server_url.as_deref(), &remote.eks) {
+        let provider = EksTokenProvider::new(eks);
+        return provider_client(
+            api_server_url,
+            &provider,
+            &options,
+            client,
+            retry,
+            request_timeout,
+        )
+        .await;
+    }
+    let primary = Credentials::primary(remote)?;
+    if remote.fallback_kubeconfig_secrets.is_empty() {
+        return credentials_client(primary, &options, client, retry, request_timeout).await;
+    }
+    let credentials = std::iter::once(primary).chain(
+        remote
+            .fallback_kubeconfig_secrets
+            .iter()
+            .map(Credentials::Kubeconfig),
+    );
+    let mut primary_error = None;
+    for (index, credentials) in credentials.enumerate() {
+        let result = match credentials_client(
+            credentials,
+            &options,
+            client.clone(),
+            retry,
+            request_timeout,
+        )
+        .await
+        {
+            Ok(remote_client) => match remote_client.apiserver_version().await {
+                Ok(_) => Ok(remote_client),
+                Err(err) => Err(Error::from(err)),
+            },
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(remote_client) => {
+                if index > 0 {
+                    tracing::info!(
+                        "Connected to remote cluster {} using fallback {}",
+                        remote.name,
+                        credentials.describe()
+                    );
+                }
+                return Ok(remote_client);
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Could not connect to remote cluster {} using {}: {err}",
+                    remote.name,
+                    credentials.describe()
+                );
+                primary_error.get_or_insert(err);
+            }
+        }
+    }
+    Err(primary_error.expect("the primary secret is always tried"))
+}
+
+/// The secret a client for a remote cluster is built from
+#[derive(Clone, Copy)]
+enum Credentials<'a> {
+    /// A secret with a full kubeconfig
+    Kubeconfig(&'a KubeconfigSecret),
+    /// An API server URL and a service account token secret
+    Token(&'a str, &'a SecretReference),
+    /// An API server URL and a TLS secret with a client certificate
+    ClientCertificate(&'a str, &'a SecretReference),
+}
+
+impl<'a> Credentials<'a> {
+    /// The credentials configured for the cluster itself, validation ensures one of them is set
+    fn primary(remote: &'a RemoteCluster) -> Result<Self> {
+        if let Some(kubeconfig_secret) = remote.kubeconfig_secret.as_ref() {
+            return Ok(Self::Kubeconfig(kubeconfig_secret));
+        }
+        match (
+            remote.api_server_url.as_deref(),
+            remote.token_secret.as_ref(),
+            remote.client_certificate_secret.as_ref(),
+        ) {
+            (Some(api_server_url), Some(token_secret), _) => {
+                Ok(Self::Token(api_server_url, token_secret))
+            }
+            (Some(api_server_url), None, Some(certificate_secret)) => {
+                Ok(Self::ClientCertificate(api_server_url, certificate_secret))
+            }
+            _ => Err(Error::InvalidConfig(format!(
+                "Remote cluster {} needs either kubeconfigSecret or apiServerUrl and tokenSecret or clientCertificateSecret",
+                remote.name
+            ))),
+        }
+    }
+
+    /// Only set for credentials without a kubeconfig
+    fn api_server_url(&self) -> Option<&'a str> {
+        match self {
+            Self::Kubeconfig(_) => None,
+            Self::Token(api_server_url, _) | Self::ClientCertificate(api_server_url, _) => {
+                Some(api_server_url)
+            }
+        }
+    }
+
+    fn namespace(&self) -> &'a str {
+        match self {
+            Self::Kubeconfig(secret) => &secret.namespace,
+            Self::Token(_, secret) | Self::ClientCertificate(_, secret) => &secret.namespace,
+        }
+    }
+
+    fn name(&self) -> &'a str {
+        match self {
+            Self::Kubeconfig(secret) => &secret.name,
+            Self::Token(_, secret) | Self::ClientCertificate(_, secret) => &secret.name,
+        }
+    }
+
+    /// Prefix for error messages, the errors are capitalized
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Kubeconfig(_) => "Kubeconfig",
+            Self::Token(_, _) => "Token",
+            Self::ClientCertificate(_, _) => "Client certificate",
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} secret {}/{}",
+            self.kind().to_lowercase(),
+            self.namespace(),
+            self.name()
+        )
+    }
+}
+
+/// Settings of the remote cluster that apply to every secret a client is built from
+struct ClientOptions<'a> {
+    /// Selects the context of a kubeconfig
+    kubeconfig: KubeConfigOptions,
+    insecure_skip_tls_verify: bool,
+    ca_secret: Option<&'a SecretReference>,
+    impersonate: Option<&'a Impersonate>,
+}
+
+impl<'a> ClientOptions<'a> {
+    fn new(remote: &'a RemoteCluster) -> Self {
+        ClientOptions {
+            kubeconfig: KubeConfigOptions {
+                context: remote.context.clone(),
+                cluster: remote.cluster.clone(),
+                user: remote.user.clone(),
+            },
+            insecure_skip_tls_verify: remote.insecure_skip_tls_verify,
+            ca_secret: remote.ca_secret.as_ref(),
+            impersonate: remote.impersonate.as_ref(),
+        }
+    }
+}
+
+/// Everything a cached client was built from, apart from the content of the secrets
+#[derive(PartialEq, Eq, Hash)]
+struct ClientKey {
+    kind: &'static str,
+    namespace: String,
+    name: String,
+    /// Only set for credentials without a kubeconfig
+    api_server_url: Option<String>,
+    context: Option<String>,
+    cluster: Option<String>,
+    user: Option<String>,
+    insecure_skip_tls_verify: bool,
+    /// Namespace and name of the CA secret
+    ca_secret: Option<(String, String)>,
+    /// User and groups that are impersonated
+    impersonate: Option<(String, Vec<String>)>,
+    request_timeout: Option<Duration>,
+}
+
+/// Clients together with the resource versions of the secrets they were built from
+type ClientCache = HashMap<ClientKey, (String, Client)>;
+
+static CLIENT_CACHE: LazyLock<Mutex<ClientCache>> = LazyLock::new(Mutex::default);
+
+/// Returns a client for the credentials. Clients are reused until the secret (or the CA secret)
+/// changes, so usually only the metadata of the secrets has to be read.
+async fn credentials_client(
+    credentials: Credentials<'_>,
+    options: &ClientOptions<'_>,
+    client: Client,
+    retry: &Retry,
+    request_timeout: Option<Duration>,
+) -> Result<Client> {
+    let secret_api = Api::<Secret>::namespaced(client.clone(), credentials.namespace());
+    let error_name = format!("{}/{}", credentials.namespace(), credentials.name());
+    let key = ClientKey {
+        kind: credentials.kind(),
+        namespace: credentials.namespace().to_owned(),
+        name: credentials.name().to_owned(),
+        api_server_url: credentials.api_server_url().map(ToOwned::to_owned),
+        context: options.kubeconfig.context.clone(),
+        cluster: options.kubeconfig.cluster.clone(),
+        user: options.kubeconfig.user.clone(),
+        insecure_skip_tls_verify: options.insecure_skip_tls_verify,
+        ca_secret: options
+            .ca_secret
+            .map(|ca_secret| (ca_secret.namespace.clone(), ca_secret.name.clone())),
+        impersonate: options
+            .impersonate
+            .map(|impersonate| (impersonate.user.clone(), impersonate.groups.clone())),
+        request_timeout,
+    };
+
+    let metadata = read_secret(retry, credentials.kind(), &error_name, || {
+        secret_api.get_metadata_opt(credentials.name())
+    })
+    .await?;
+    let mut version = metadata.metadata.resource_version.unwrap_or_default();
+    if let Some(ca_secret) = options.ca_secret {
+        let ca_api = Api::<Secret>::namespaced(client.clone(), &ca_secret.namespace);
+        let ca_name = format!("{}/{}", ca_secret.namespace, ca_secret.name);
+        let metadata = read_secret(retry, "CA", &ca_name, || {
+            ca_api.get_metadata_opt(&ca_secret.name)
+        })
+        .await?;
+        version = format!(
+            "{version}/{}",
+            metadata.metadata.resource_version.unwrap_or_default()
+        );
+    }
+    if let Some((cached_version, client)) = CLIENT_CACHE
+        .lock()
+        .expect("client cache lock is never poisoned")
+        .get(&key)
+        && *cached_version == version
+    {
+        return Ok(client.clone());
+    }
+
+    let secret = read_secret(retry, credentials.kind(), &error_name, || {
+        secret_api.get_opt(credentials.name())
+    })
+    .await?;
+    let mut config = match credentials {
+        Credentials::Kubeconfig(kubeconfig_secret) => {
+            kubeconfig_from_secret(
+                &secret,
+                &error_name,
+                kubeconfig_secret.key.as_deref(),
+                &options.kubeconfig,
+            )
+            .await?
+        }
+        Credentials::Token(api_server_url, _) => {
+            token_config_from_secret(&secret, &error_name, api_server_url)?
+        }
+        Credentials::ClientCertificate(api_server_url, _) => {
+            client_certificate_config_from_secret(&secret, &error_name, api_server_url)?
+        }
+    };
+    apply_client_options(&mut config, options, client, retry, request_timeout).await?;
+    let client: Client = config.try_into().map_err(|err| {
+        Error::InvalidKubeconfig(format!("{} secret {error_name}: {err}", credentials.kind()))
+    })?;
+    tracing::debug!("Created client from {}", credentials.describe());
+    CLIENT_CACHE
+        .lock()
+        .expect("client cache lock is never poisoned")
+        .insert(key, (version, client.clone()));
+    Ok(client)
+}
+
+/// Builds a client with a fresh token of the provider. These clients are not cached, the tokens expire after a few minutes.
+async fn provider_client(
+    api_server_url: &str,
+    provider: &dyn TokenProvider,
+    options: &ClientOptions<'_>,
+    client: Client,
+    retry: &Retry,
+    request_timeout: Option<Duration>,
+) -> Result<Client> {
+    let token = provider.token().await?;
+    let mut config = api_server_config(api_server_url, None, api_server_url)?;
+    config.auth_info.token = Some(token.into());
+    apply_client_options(&mut config, options, client, retry, request_timeout).await?;
+    config
+        .try_into()
+        .map_err(|err| Error::InvalidKubeconfig(format!("Client for {api_server_url}: {err}")))
+}
+
+/// Trusts the CA secret and applies the TLS and timeout settings of the remote cluster
+async fn apply_client_options(
+    config: &mut kube::Config,
+    options: &ClientOptions<'_>,
+    client: Client,
+    retry: &Retry,
+    request_timeout: Option<Duration>,
+) -> Result<()> {
+    if let Some(ca_secret) = options.ca_secret {
+        let ca_api = Api::<Secret>::namespaced(client, &ca_secret.namespace);
+        let ca_name = format!("{}/{}", ca_secret.namespace, ca_secret.name);
+        let ca = read_secret(retry, "CA", &ca_name, || ca_api.get_opt(&ca_secret.name)).await?;
+        let Some(bundle) = ca.data.as_ref().and_then(|data| data.get("ca.crt")) else {
+            return Err(Error::InvalidKubeconfig(format!(
+                "CA secret {ca_name} has no data field ca.crt"
+            )));
+        };
+        let certificates = parse_certificates(&bundle.0, &ca_name)?;
+        config
+            .root_cert
+            .get_or_insert_with(Vec::new)
+            .extend(certificates);
+    }
+    if options.insecure_skip_tls_verify {
+        config.accept_invalid_certs = true;
+    }
+    if let Some(impersonate) = options.impersonate {
+        apply_impersonation(config, impersonate);
+    }
+    if let Some(request_timeout) = request_timeout {
+        config.connect_timeout = Some(request_timeout);
+        config.read_timeout = Some(request_timeout);
+        config.write_timeout = Some(request_timeout);
+    }
+    Ok(())
+}
+
+/// Client for the local cluster, impersonating the configured identity if there is one
+pub(crate) async fn local_client(local: &LocalCluster) -> Result<Client> {
+    let Some(impersonate) = local.impersonate.as_ref() else {
+        return Ok(Client::try_default().await?);
+    };
+    let mut config = kube::Config::infer().await.map_err(|err| {
+        Error::Generic(format!(
+            "Could not load the config of the local cluster: {err}"
+        ))
+    })?;
+    apply_impersonation(&mut config, impersonate);
+    Ok(config.try_into()?)
+}
+
+fn apply_impersonation(config: &mut kube::Config, impersonate: &Impersonate) {
+    config.auth_info.impersonate = Some(impersonate.user.clone());
+    config.auth_info.impersonate_groups =
+        Some(impersonate.groups.clone()).filter(|groups| !groups.is_empty());
+}
+
+/// Reads the secret (or its metadata) with retries, a missing secret is a `MissingKubeconfig` error
+async fn read_secret<T, F, Fut>(retry: &Retry, kind: &str, error_name: &str, read: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = kube::Result<Option<T>>>,
+{
+    let lowercase_kind = kind.to_lowercase();
+    let result = retry::retry(
+        retry,
+        &format!("reading {lowercase_kind} secret {error_name}"),
+        retry::is_transient_kube,
+        read,
+    )
+    .await;
+    match result {
+        Ok(Some(result)) => Ok(result),
+        Ok(None) => Err(Error::MissingKubeconfig(format!(
+            "{kind} secret {error_name} does not exist"
+        ))),
+        Err(err) => Err(Error::Generic(format!(
+            "Could not get {lowercase_kind} secret {error_name}: {err}"
+        ))),
+    }
+}
+
+/// Data keys tried in order if the kubeconfig secret has no explicit key: the one of Cluster API
+/// secrets, followed by names commonly used by other tools
+const DEFAULT_KUBECONFIG_KEYS: [&str; 3] = ["value", "kubeconfig", "config"];
+
+async fn kubeconfig_from_secret(
+    secret: &Secret,
+    error_name: &str,
+    key: Option<&str>,
+    options: &KubeConfigOptions,
+) -> Result<kube::Config> {
+    let Some(data) = secret.data.as_ref() else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Kubeconfig secret {error_name} has no data"
+        )));
+    };
+    let keys = match key {
+        Some(key) => vec![key],
+        None => DEFAULT_KUBECONFIG_KEYS.to_vec(),
+    };
+    let Some(kubeconfig_data) = keys.iter().find_map(|key| data.get(*key)) else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Kubeconfig secret {error_name} has no data field {}",
+            keys.join(" or ")
+        )));
+    };
+
+    let kubeconfig: Kubeconfig = serde_yaml::from_slice(&kubeconfig_data.0).map_err(|err| {
+        Error::InvalidKubeconfig(format!("Kubeconfig in secret {error_name}: {err}"))
+    })?;
+    kube::Config::from_custom_kubeconfig(kubeconfig, options)
+        .await
+        .map_err(|err| {
+            Error::InvalidKubeconfig(format!("Kubeconfig in secret {error_name}: {err}"))
+        })
+}
+
+/// Builds the config from a secret in the format of a service account token secret,
+/// `ca.crt` is optional in case the API server uses a publicly trusted certificate
+fn token_config_from_secret(
+    secret: &Secret,
+    error_name: &str,
+    api_server_url: &str,
+) -> Result<kube::Config> {
+    let Some(data) = secret.data.as_ref() else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Token secret {error_name} has no data"
+        )));
+    };
+    let Some(token) = data.get("token") else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Token secret {error_name} has no data field token"
+        )));
+    };
+    let token = String::from_utf8(token.0.clone())
+        .map_err(|err| Error::InvalidKubeconfig(format!("Token in secret {error_name}: {err}")))?;
+    let mut config = api_server_config(api_server_url, data.get("ca.crt"), error_name)?;
+    config.auth_info.token = Some(token.trim().to_owned().into());
+    Ok(config)
+}
+
+/// Builds the config from a secret in the format of a TLS secret, `ca.crt` is used
+/// as the CA of the API server if present (like in secrets created by cert-manager)
+fn client_certificate_config_from_secret(
+    secret: &Secret,
+    error_name: &str,
+    api_server_url: &str,
+) -> Result<kube::Config> {
+    let Some(data) = secret.data.as_ref() else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Client certificate secret {error_name} has no data"
+        )));
+    };
+    let (Some(certificate), Some(key)) = (data.get("tls.crt"), data.get("tls.key")) else {
+        return Err(Error::InvalidKubeconfig(format!(
+            "Client certificate secret {error_name} needs the data fields tls.crt and tls.key"
+        )));
+    };
+    let mut config = api_server_config(api_server_url, data.get("ca.crt"), error_name)?;
+    // kube expects the PEM data base64 encoded, like in a kubeconfig
+    config.auth_info.client_certificate_data = Some(BASE64_STANDARD.encode(&certificate.0));
+    config.auth_info.client_key_data = Some(BASE64_STANDARD.encode(&key.0).into());
+    Ok(config)
+}
+
+/// Config for the API server without credentials, with the PEM encoded CA from the secret if there is one
+fn api_server_config(
+    api_server_url: &str,
+    ca: Option<&ByteString>,
+    error_name: &str,
+) -> Result<kube::Config> {
+    let url = api_server_url.parse().map_err(|err| {
+        Error::InvalidKubeconfig(format!("API server URL {api_server_url}: {err}"))
+    })?;
+    let mut config = kube::Config::new(url);
+    if let Some(ca) = ca {
+        config.root_cert = Some(parse_certificates(&ca.0, error_name)?);
+    }
+    Ok(config)
+}
+
+/// Parses PEM encoded certificates into the DER encoding kube expects
+fn parse_certificates(pem: &[u8], error_name: &str) -> Result<Vec<Vec<u8>>> {
+    let certificates = pem::parse_many(pem).map_err(|err| {
+        Error::InvalidKubeconfig(format!("CA certificate in secret {error_name}: {err}"))
+    })?;
+    Ok(certificates
+        .into_iter()
+        .map(|certificate| certificate.into_contents())
+        .collect())
+}
+
+/// Collects the entries of all configured kinds. Ingresses are taken from the store while the
+/// cluster is watched and listed otherwise.
+#[tracing::instrument(skip(config, cluster, client, limit, ingresses))]
+async fn collect_ingresses(
+    config: &Config,
+    cluster: &impl ClusterConfig,
+    client: Client,
+    namespace: Option<&str>,
+    limit: &Semaphore,
+    ingresses: &IngressStore,
+) -> Result<Vec<IngressSpec>> {
+    if let Some(namespace) = namespace
+        && cluster
+            .exclude_namespaces()
+            .iter()
+            .any(|excluded| excluded == namespace)
+    {
+        return Ok(Vec::new());
+    }
+    let _permit = limit.acquire().await.expect("semaphore is never closed");
+    let only_with_annotation = config
+        .global
+        .as_ref()
+        .map(|g| g.only_with_annotation)
+        .unwrap_or_default();
+    let require_ready_endpoints = config
+        .global
+        .as_ref()
+        .map(|g| g.require_ready_endpoints)
+        .unwrap_or_default();
+    // Looked up once for all ingresses of this collection
+    let ready_services = if require_ready_endpoints {
+        Some(ready_services(client.clone(), namespace).await?)
+    } else {
+        None
+    };
+    let host_filter = HostFilter::new(config, cluster)?;
+    let params = list_params(config, cluster);
+    let mut objects: Vec<ObjectSpecs> = Vec::new();
+    for kind in cluster.kinds() {
+        match kind {
+            ResourceKind::Ingress => {
+                let listed = match ingresses.list(namespace) {
+                    Some(listed) => listed,
+                    None => list_ingresses(cluster, client.clone(), namespace, &params).await?,
+                };
+                for ingress in listed {
+                    let specs = ingress_specs(&ingress, cluster.assume_tls());
+                    objects.push((ingress.metadata, specs));
+                }
+            }
+            // Gateways are not entries but a separate section of the cluster, see `collect_gateways`
+            ResourceKind::Gateway => {}
+            kind => {
+                let mut specs = routes::collect(
+                    config,
+                    kind,
+                    client.clone(),
+                    namespace,
+                    &params,
+                    cluster.assume_tls(),
+                )
+                .await?;
+                objects.append(&mut specs);
+            }
+        }
+    }
+    let now = Utc::now();
+
+    let mut result = Vec::new();
+
+    for (metadata, specs) in objects {
+        if let Some(global) = config.global.as_ref()
+            && let Some(created) = metadata.creation_timestamp.as_ref()
+            && !within_age_limits(global, created.0, now)
+        {
+            continue;
+        }
+        if metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(HIDE_ANNOTATION))
+            .is_some_and(|value| value == "true")
+        {
+            continue;
+        }
+        if only_with_annotation {
+            if let Some(annotations) = metadata.annotations.as_ref() {
+                if annotations.get(NAME_ANNOTATION).is_none()
+                    && annotations.get(DESCRIPTION_ANNOTATION).is_none()
+                {
+                    // none of our annotations, filter it out
+                    continue;
+                }
+            } else {
+                // no annotations at all, filter it out
+                continue;
+            }
+        }
+        for mut spec in specs {
+            if !host_filter.allows(&spec.host) {
+                continue;
+            }
+            if let Some(tls_used) = scheme_override(&spec) {
+                spec.tls_used = tls_used;
+            }
+            if let Some(ready_services) = ready_services.as_ref()
+                && let Some(BackendInfo::Service { name: service, .. }) = spec.backend.as_ref()
+                && !ready_services.contains(&(spec.namespace.clone(), service.clone()))
+            {
+                tracing::debug!(
+                    "Skipping {}/{}, service {service} has no ready endpoints",
+                    spec.namespace,
+                    spec.name
+                );
+                continue;
+            }
+            result.push(spec);
+        }
+    }
+
+    Ok(result)
+}
+
+async fn list_ingresses(
+    cluster: &impl ClusterConfig,
+    client: Client,
+    namespace: Option<&str>,
+    params: &ListParams,
+) -> Result<Vec<Ingress>> {
+    if let Some(api_version) = cluster.ingress_api_version() {
+        return list_dynamic_ingresses(client, namespace, api_version, params).await;
+    }
+    let api = if let Some(namespace) = namespace {
+        Api::<Ingress>::namespaced(client, namespace)
+    } else {
+        Api::<Ingress>::all(client)
+    };
+    Ok(api.list(params).await?.items)
+}
+
+/// Include and exclude regexes for hosts from the global and the cluster config
+struct HostFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl HostFilter {
+    fn new(config: &Config, cluster: &impl ClusterConfig) -> Result<Self> {
+        let global = config.global.as_ref();
+        let compile = |expressions: [Option<&str>; 2]| {
+            expressions
+                .into_iter()
+                .flatten()
+                .map(|expression| {
+                    Regex::new(expression).map_err(|err| {
+                        Error::InvalidConfig(format!("Invalid hosts regex {expression}: {err}"))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(HostFilter {
+            include: compile([
+                global.and_then(|g| g.include_hosts_regex.as_deref()),
+                cluster.include_hosts_regex(),
+            ])?,
+            exclude: compile([
+                global.and_then(|g| g.exclude_hosts_regex.as_deref()),
+                cluster.exclude_hosts_regex(),
+            ])?,
+        })
+    }
+
+    /// The host has to match all include regexes and none of the exclude regexes
+    fn allows(&self, host: &str) -> bool {
+        self.include.iter().all(|regex| regex.is_match(host))
+            && !self.exclude.iter().any(|regex| regex.is_match(host))
+    }
+}
+
+/// Combines the label selectors of the global and the cluster config, objects have to match both.
+/// Excluded namespaces are left out with a field selector.
+pub(crate) fn list_params(config: &Config, cluster: &impl ClusterConfig) -> ListParams {
+    let selectors: Vec<&str> = config
+        .global
+        .as_ref()
+        .and_then(|g| g.label_selector.as_deref())
+        .into_iter()
+        .chain(cluster.label_selector())
+        .filter(|selector| !selector.trim().is_empty())
+        .collect();
+    let mut params = ListParams::default();
+    if !selectors.is_empty() {
+        params = params.labels(&selectors.join(","));
+    }
+    if !cluster.exclude_namespaces().is_empty() {
+        let fields: Vec<String> = cluster
+            .exclude_namespaces()
+            .iter()
+            .map(|namespace| format!("metadata.namespace!={namespace}"))
+            .collect();
+        params = params.fields(&fields.join(","));
+    }
+    params
+}
+
+/// Reads the scheme annotation, `https` forces TLS and `http` plain URLs
+fn scheme_override(spec: &IngressSpec) -> Option<bool> {
+    let scheme = spec.annotations.get(SCHEME_ANNOTATION)?;
+    match scheme.trim().to_lowercase().as_str() {
+        "https" => Some(true),
+        "http" => Some(false),
+        _ => {
+            tracing::warn!(
+                "Ignoring annotation {SCHEME_ANNOTATION} of ingress {}/{}: unknown scheme {scheme}",
+                spec.namespace,
+                spec.name
+            );
+            None
+        }
+    }
+}
+
+/// One entry per host and path of the ingress, rules without host are skipped.
+/// Unless `assume_tls` is set, hosts listed in `spec.tls` are linked with https and all others with http.
+fn ingress_specs(ingress: &Ingress, assume_tls: Option<bool>) -> Vec<IngressSpec> {
+    let Some(spec) = ingress.spec.as_ref() else {
+        return Vec::new();
+    };
+    let tls_hosts: Vec<&str> = spec
+        .tls
+        .iter()
+        .flatten()
+        .flat_map(|tls| match tls.hosts.as_ref() {
+            Some(hosts) if !hosts.is_empty() => hosts.iter().map(String::as_str).collect(),
+            // TLS without hosts uses the default certificate of the controller for all hosts
+            _ => vec!["*"],
+        })
+        .collect();
+    let path_regex = ingress
+        .annotations()
+        .get(USE_REGEX_ANNOTATION)
+        .is_some_and(|value| value == "true");
+    let mut result = Vec::new();
+    for rule in spec.rules.iter().flatten() {
+        let Some(host) = rule.host.as_ref() else {
+            continue;
+        };
+        let tls_used = assume_tls.unwrap_or_else(|| {
+            tls_hosts
+                .iter()
+                .any(|tls_host| tls_host_matches(tls_host, host))
+        });
+        for path in rule.http.iter().flat_map(|http| http.paths.iter()) {
+            result.push(IngressSpec {
+                path: path.path.clone(),
+                path_regex: is_regex_path(path, path_regex),
+                backend: BackendInfo::from_ingress_backend(&path.backend),
+                ..IngressSpec::for_object(&ingress.metadata, host.clone(), tls_used)
+            });
+        }
+    }
+    result
+}
+
+/// Compares a host of `spec.tls` with the host of a rule, a leading `*.` matches exactly one label
+fn tls_host_matches(tls_host: &str, host: &str) -> bool {
+    if tls_host == "*" || tls_host.eq_ignore_ascii_case(host) {
+        return true;
+    }
+    match (tls_host.strip_prefix("*."), host.split_once('.')) {
+        (Some(domain), Some((_, host_domain))) => domain.eq_ignore_ascii_case(host_domain),
+        _ => false,
+    }
+}
+
+/// `Exact` and `Prefix` paths are always literal. `ImplementationSpecific` paths are regexes
+/// if the ingress has the `use-regex` annotation or if they contain regex characters other than `.`
+/// (like `/api(/|$)(.*)`, as used with rewrite targets).
+fn is_regex_path(path: &HTTPIngressPath, use_regex: bool) -> bool {
+    if path.path_type != "ImplementationSpecific" {
+        return false;
+    }
+    use_regex
+        || path
+            .path
+            .as_deref()
+            .is_some_and(|path| path.contains(|c: char| c != '.' && REGEX_CHARACTERS.contains(&c)))
+}
+
+/// Finds all services (as namespace and name) that have at least one ready endpoint
+async fn ready_services(
+    client: Client,
+    namespace: Option<&str>,
+) -> Result<HashSet<(String, String)>> {
+    let api = if let Some(namespace) = namespace {
+        Api::<EndpointSlice>::namespaced(client, namespace)
+    } else {
+        Api::<EndpointSlice>::all(client)
+    };
+    let slices = api.list(&ListParams::default()).await?;
+    Ok(slices
+        .into_iter()
+        .filter(|slice| {
+            // An endpoint without ready condition counts as ready
+            slice.endpoints.iter().any(|endpoint| {
+                endpoint
+                    .conditions
+                    .as_ref()
+                    .and_then(|conditions| conditions.ready)
+                    .unwrap_or(true)
+            })
+        })
+        .filter_map(|slice| {
+            let service = slice.labels().get(SERVICE_NAME_LABEL)?.clone();
+            Some((slice.namespace().unwrap_or_default(), service))
+        })
+        .collect())
+}
+
+/// Lists ingresses of the given API version (like `networking.k8s.io/v1beta1`) and converts them to `networking.k8s.io/v1`
+async fn list_dynamic_ingresses(
+    client: Client,
+    namespace: Option<&str>,
+    api_version: &str,
+    params: &ListParams,
+) -> Result<Vec<Ingress>> {
+    let resource = ingress_api_resource(api_version);
+    let api = if let Some(namespace) = namespace {
+        Api::<DynamicObject>::namespaced_with(client, namespace, &resource)
+    } else {
+        Api::<DynamicObject>::all_with(client, &resource)
+    };
+    api.list(params)
+        .await?
+        .items
+        .into_iter()
+        .map(ingress_from_dynamic)
+        .collect()
+}
+
+fn ingress_api_resource(api_version: &str) -> ApiResource {
+    let (group, version) = api_version.split_once('/').unwrap_or(("", api_version));
+    ApiResource::from_gvk(&GroupVersionKind::gvk(group, version, "Ingress"))
+}
+
+fn ingress_from_dynamic(object: DynamicObject) -> Result<Ingress> {
+    let spec = match object.data.get("spec") {
+        Some(spec) => {
+            let mut spec = spec.clone();
+            upgrade_ingress_spec(&mut spec);
+            let spec = serde_json::from_value(spec).map_err(|err| {
+                Error::Generic(format!(
+                    "Could not read ingress {}: {err}",
+                    object.name_any()
+                ))
+            })?;
+            Some(spec)
+        }
+        None => None,
+    };
+    Ok(Ingress {
+        metadata: object.metadata,
+        spec,
+        status: None,
+    })
+}
+
+/// Converts the fields of a `v1beta1` ingress spec that differ from `v1`, a `v1` spec is left unchanged
+fn upgrade_ingress_spec(spec: &mut serde_json::Value) {
+    if let Some(backend) = spec.as_object_mut().and_then(|spec| spec.remove("backend")) {
+        spec["defaultBackend"] = backend;
+    }
+    if let Some(backend) = spec.get_mut("defaultBackend") {
+        upgrade_ingress_backend(backend);
+    }
+    let Some(rules) = spec.get_mut("rules").and_then(|rules| rules.as_array_mut()) else {
+        return;
+    };
+    for rule in rules {
+        let Some(paths) = rule
+            .pointer_mut("/http/paths")
+            .and_then(|paths| paths.as_array_mut())
+        else {
+            continue;
+        };
+        for path in paths {
+            if path.get("pathType").is_none() {
+                path["pathType"] = "ImplementationSpecific".into();
+            }
+            if let Some(backend) = path.get_mut("backend") {
+                upgrade_ingress_backend(backend);
+            }
+        }
+    }
+}
+
+fn upgrade_ingress_backend(backend: &mut serde_json::Value) {
+    let Some(backend) = backend.as_object_mut() else {
+        return;
+    };
+    let Some(service_name) = backend.remove("serviceName") else {
+        return;
+    };
+    let port = match backend.remove("servicePort") {
+        Some(serde_json::Value::String(name)) => serde_json::json!({ "name": name }),
+        Some(number) => serde_json::json!({ "number": number }),
+        None => serde_json::Value::Null,
+    };
+    backend.insert(
+        "service".to_owned(),
+        serde_json::json!({ "name": service_name, "port": port }),
+    );
+}
+
+/// Checks the age of an ingress against `minAgeSeconds` and `maxAgeSeconds`
+fn within_age_limits(global: &Global, created: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let age = (now - created).num_seconds();
+    global.min_age_seconds.is_none_or(|min_age| age >= min_age)
+        && global.max_age_seconds.is_none_or(|max_age| age <= max_age)
+}
+
+fn transform_to_info(
+    config: &Config,
+    cluster_name: String,
+    description: &Option<String>,
+    input: Vec<IngressSpec>,
+) -> ClusterInfo {
+    let expose_all_annotations = config
+        .global
+        .as_ref()
+        .map(|g| g.expose_all_annotations)
+        .unwrap_or_default();
+    let show_backend = config
+        .global
+        .as_ref()
+        .map(|g| g.show_backend)
+        .unwrap_or_default();
+    let html_description = config
+        .global
+        .as_ref()
+        .map(|g| g.html_description)
+        .unwrap_or_default();
+    let regex_path_handling = config
+        .global
+        .as_ref()
+        .map(|g| g.regex_path_handling)
+        .unwrap_or_default();
+    let now = Utc::now();
+    let url_options = UrlOptions::from_config(config);
+    let protection_annotations: Vec<&str> = match config
+        .global
+        .as_ref()
+        .and_then(|g| g.protection_annotations.as_ref())
+    {
+        Some(annotations) => annotations.iter().map(String::as_str).collect(),
+        None => DEFAULT_PROTECTION_ANNOTATIONS.to_vec(),
+    };
+    let dedup_hosts = config
+        .global
+        .as_ref()
+        .map(|g| g.dedup_hosts)
+        .unwrap_or_default();
+    let input = input
+        .into_iter()
+        .filter(|i| {
+            if regex_path_handling == RegexPathHandling::Drop && !is_linkable(i) {
+                tracing::debug!(
+                    "Skipping {}/{}, path {:?} can not be linked",
+                    i.namespace,
+                    i.name,
+                    i.path
+                );
+                return false;
+            }
+            true
+        })
+        .collect();
+    let mut ingresses: Vec<IngressInfo> = dedup_hosts_of_cluster(input, dedup_hosts)
+        .into_iter()
+        .map(|i| {
+            let url = ingress_url(&i, regex_path_handling, url_options);
+            let name = display_name(&i);
+            let description = i
+                .annotations
+                .get(DESCRIPTION_ANNOTATION)
+                .or(i.description.as_ref())
+                .map(|s| s.to_owned())
+                .unwrap_or_default();
+            let description_html =
+                (html_description && !description.is_empty()).then(|| sanitize_html(&description));
+            let secondary_url =
+                i.annotations
+                    .get(SECONDARY_URL_ANNOTATION)
+                    .and_then(|secondary_url| {
+                        annotation_url(&i, SECONDARY_URL_ANNOTATION, secondary_url, url_options)
+                    });
+            let docs_url = i.annotations.get(DOCS_URL_ANNOTATION).and_then(|docs_url| {
+                annotation_url(&i, DOCS_URL_ANNOTATION, docs_url, url_options)
+            });
+            let color = parse_annotation(&i, COLOR_ANNOTATION);
+            let protection: Vec<&str> = protection_annotations
+                .iter()
+                .filter(|annotation| i.annotations.contains_key(**annotation))
+                .copied()
+                .collect();
+            let category = i
+                .annotations
+                .get(CATEGORY_ANNOTATION)
+                .map(|category| category.trim().to_owned())
+                .filter(|category| !category.is_empty());
+            let health_method = parse_annotation(&i, HEALTH_METHOD_ANNOTATION);
+            let health_expect = parse_annotation(&i, HEALTH_EXPECT_ANNOTATION);
+            let expires = i.annotations.get(EXPIRES_ANNOTATION).and_then(|expires| {
+                match DateTime::parse_from_rfc3339(expires) {
+                    Ok(expires) => Some(expires.to_utc()),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Ignoring annotation {EXPIRES_ANNOTATION} of ingress {}/{}: {err}",
+                            i.namespace,
+                            i.name
+                        );
+                        None
+                    }
+                }
+            });
+            let mut groups: Vec<String> = i
+                .annotations
+                .get(GROUPS_ANNOTATION)
+                .map(|groups| {
+                    groups
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|group| !group.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default();
+            groups.sort();
+            groups.dedup();
+            let annotations = if expose_all_annotations {
+                filter_annotations(&i.annotations)
+            } else {
+                BTreeMap::new()
+            };
+            IngressInfo {
+                name: name.to_owned(),
+                ingress_name: i.name.clone(),
+                description,
+                description_html,
+                url,
+                secondary_url,
+                docs_url,
+                expires,
+                annotations,
+                labels: i.labels,
+                backend: if show_backend { i.backend } else { None },
+                health: HealthStatus::Unknown,
+                failed_over: false,
+                color,
+                category,
+                protected: !protection.is_empty(),
+                protection: (!protection.is_empty()).then(|| protection.join(", ")),
+                health_method,
+                health_expect,
+                groups,
+                sources: Vec::new(),
+                stale: false,
+                last_seen: now,
+            }
+        })
+        .collect();
+    sort_ingresses(&mut ingresses);
+
+    ClusterInfo {
+        name: cluster_name,
+        description: description.clone().unwrap_or_default(),
         ingresses,
+        truncated: false,
+        hidden_count: 0,
+        error: None,
+        gateways: Vec::new(),
+        links: Vec::new(),
+        last_updated: now,
+        stale: false,
+    }
+}
+
+fn sort_ingresses(ingresses: &mut [IngressInfo]) {
+    ingresses.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.url.cmp(&b.url))
+    });
+}
+
+/// Name shown for the entry: the name annotation, the name read from the resource or the name of the resource
+fn display_name(ingress: &IngressSpec) -> &str {
+    ingress
+        .annotations
+        .get(NAME_ANNOTATION)
+        .or(ingress.display_name.as_ref())
+        .unwrap_or(&ingress.name)
+}
+
+/// Collapses entries with the same host (and name) into the one with the shortest path.
+/// The order of the remaining entries is kept.
+fn dedup_hosts_of_cluster(input: Vec<IngressSpec>, dedup_hosts: DedupHosts) -> Vec<IngressSpec> {
+    let path_length = |ingress: &IngressSpec| ingress.path.as_deref().unwrap_or("/").len();
+    let mut result: Vec<IngressSpec> = Vec::new();
+    let mut index_by_key: HashMap<(String, Option<String>), usize> = HashMap::new();
+    for ingress in input {
+        let key = match dedup_hosts {
+            DedupHosts::Off => {
+                result.push(ingress);
+                continue;
+            }
+            DedupHosts::Host => (ingress.host.clone(), None),
+            DedupHosts::HostAndName => (
+                ingress.host.clone(),
+                Some(display_name(&ingress).to_owned()),
+            ),
+        };
+        match index_by_key.get(&key) {
+            Some(&index) => {
+                if path_length(&ingress) < path_length(&result[index]) {
+                    result[index] = ingress;
+                }
+            }
+            None => {
+                index_by_key.insert(key, result.len());
+                result.push(ingress);
+            }
+        }
+    }
+    result
+}
+
+fn ingress_url(
+    ingress: &IngressSpec,
+    regex_path_handling: RegexPathHandling,
+    url_options: UrlOptions,
+) -> String {
+    // The annotation replaces the generated URL completely, e.g. for deep links into an app
+    if let Some(url) = ingress
+        .annotations
+        .get(URL_ANNOTATION)
+        .and_then(|url| annotation_url(ingress, URL_ANNOTATION, url, url_options))
+    {
+        return url;
+    }
+    let scheme = if ingress.tls_used { "https" } else { "http" };
+    let path = ingress.path.as_deref().unwrap_or("/");
+    if !ingress.path_regex {
+        return format!("{scheme}://{}{path}", ingress.host);
+    }
+    let path = match regex_path_handling {
+        RegexPathHandling::Keep => path,
+        RegexPathHandling::Root => "/",
+        RegexPathHandling::Strip | RegexPathHandling::Drop => strip_regex(path),
+    };
+    format!("{scheme}://{}{path}", ingress.host)
+}
+
+/// Whether the URL of the entry can be built without changing its path
+fn is_linkable(ingress: &IngressSpec) -> bool {
+    if ingress.annotations.contains_key(URL_ANNOTATION) {
+        return true;
+    }
+    let path = ingress.path.as_deref().unwrap_or("/");
+    path.starts_with('/') && !(ingress.path_regex && path.contains(REGEX_CHARACTERS))
+}
+
+/// Reduces a regex path to its literal prefix, cut after the last complete segment
+fn strip_regex(path: &str) -> &str {
+    let Some(index) = path.find(REGEX_CHARACTERS) else {
+        return path;
+    };
+    match path[..index].rfind('/') {
+        Some(slash) => &path[..=slash],
+        None => "/",
+    }
+}
+
+fn parse_annotation<T: FromStr<Err = String>>(
+    ingress: &IngressSpec,
+    annotation: &str,
+) -> Option<T> {
+    match ingress.annotations.get(annotation)?.parse() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            tracing::warn!(
+                "Ignoring annotation {annotation} of ingress {}/{}: {err}",
+                ingress.namespace,
+                ingress.name
+            );
+            None
+        }
+    }
+}
+
+/// Normalizes a URL given by an annotation, invalid URLs are logged and dropped
+fn annotation_url(
+    ingress: &IngressSpec,
+    annotation: &str,
+    value: &str,
+    url_options: UrlOptions,
+) -> Option<String> {
+    match normalize_url(value, &ingress.host, ingress.tls_used, url_options) {
+        Ok(url) => Some(url),
+        Err(err) => {
+            tracing::warn!(
+                "Ignoring annotation {annotation} of ingress {}/{}: {err}",
+                ingress.namespace,
+                ingress.name
+            );
+            None
+        }
+    }
+}
+
+fn filter_annotations(annotations: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    annotations
+        .iter()
+        .filter(|(key, value)| {
+            let key = key.to_lowercase();
+            !IGNORED_ANNOTATIONS.contains(&key.as_str())
+                && !SENSITIVE_ANNOTATION_PARTS
+                    .iter()
+                    .any(|part| key.contains(part))
+                && value.len() <= MAX_ANNOTATION_VALUE_LENGTH
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::config::{ConfigFormat, parse_config};
+
+    fn config(yaml: &str) -> Config {
+        parse_config(yaml, ConfigFormat::Yaml).unwrap()
+    }
+
+    /// Client answering every request with the given status and JSON body
+    fn mock_client(status: u16, body: serde_json::Value) -> Client {
+        let service = tower::service_fn(move |_: axum::http::Request<kube::client::Body>| {
+            let response = axum::http::Response::builder()
+                .status(status)
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            async move { Ok::<_, std::convert::Infallible>(response) }
+        });
+        Client::new(service, "default")
+    }
+
+    fn spec(name: &str, host: &str, annotations: &[(&str, &str)]) -> IngressSpec {
+        let metadata = ObjectMeta {
+            name: Some(name.to_owned()),
+            namespace: Some("default".to_owned()),
+            annotations: Some(
+                annotations
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        IngressSpec::for_object(&metadata, host.to_owned(), true)
+    }
+
+    fn cluster(config: &Config, specs: Vec<IngressSpec>) -> ClusterInfo {
+        transform_to_info(config, "local".to_owned(), &None, specs)
+    }
+
+    fn group(name: &str, clusters: Vec<ClusterInfo>) -> GroupInfo {
+        GroupInfo {
+            name: name.to_owned(),
+            description: String::new(),
+            clusters,
+            links: Vec::new(),
+        }
+    }
+
+    fn strict_config(secret_name: &str) -> Config {
+        config(&format!(
+            "global:\n  strictRemotes: true\nremote:\n  prod:\n    - name: prod\n      kubeconfigSecret:\n        name: {secret_name}\n        namespace: default\n"
+        ))
+    }
+
+    #[tokio::test]
+    async fn missing_kubeconfig_does_not_fail_strict_check() {
+        let config = strict_config("missing-kubeconfig");
+        let client = mock_client(
+            404,
+            serde_json::json!({
+                "kind": "Status",
+                "apiVersion": "v1",
+                "status": "Failure",
+                "reason": "NotFound",
+                "message": "secrets \"missing-kubeconfig\" not found",
+                "code": 404,
+            }),
+        );
+        let remote = &config.remote.as_ref().unwrap().values().next().unwrap()[0];
+        let result = remote_client(remote, client.clone(), &retry::policy(&config), None).await;
+        assert!(matches!(result, Err(Error::MissingKubeconfig(_))));
+        assert!(check_remote_kubeconfigs(&config, client).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn invalid_kubeconfig_fails_strict_check() {
+        let config = strict_config("invalid-kubeconfig");
+        let client = mock_client(
+            200,
+            serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": {
+                    "name": "invalid-kubeconfig",
+                    "namespace": "default",
+                    "resourceVersion": "1",
+                },
+                "data": { "value": BASE64_STANDARD.encode("not a kubeconfig") },
+            }),
+        );
+        let result = check_remote_kubeconfigs(&config, client).await;
+        assert!(matches!(result, Err(Error::InvalidKubeconfig(_))));
+    }
+
+    fn local_source() -> ClusterSource {
+        ClusterSource::Local(Box::default())
+    }
+
+    #[test]
+    fn failed_first_collection_is_published() {
+        let err = Error::Generic("connection refused".to_owned());
+        let mut slice = None;
+        keep_stale(&config("{}"), &local_source(), &mut slice, &err);
+        assert_eq!(slice.map(|slice| slice.len()), Some(0));
+
+        let config = config("global:\n  showClusterErrors: true\n");
+        for source in [
+            local_source(),
+            ClusterSource::Discovered(Discovery::default()),
+            ClusterSource::ClusterApi(ClusterApi::default()),
+        ] {
+            let mut slice = None;
+            keep_stale(&config, &source, &mut slice, &err);
+            let slice = slice.expect("a failed source is published");
+            assert_eq!(slice.len(), 1);
+            assert_eq!(slice[0].1.name, source.name());
+            assert_eq!(slice[0].1.error, Some(err.to_string()));
+        }
+    }
+
+    #[test]
+    fn failed_collection_keeps_stale_clusters() {
+        let mut cluster = transform_to_info(&config("{}"), "local".to_owned(), &None, Vec::new());
+        cluster.last_updated = Utc::now();
+        let mut slice = Some(vec![("local".to_owned(), cluster)]);
+        let err = Error::Generic("connection refused".to_owned());
+        keep_stale(&config("{}"), &local_source(), &mut slice, &err);
+        let slice = slice.unwrap();
+        assert!(slice[0].1.stale);
+        assert_eq!(slice[0].1.error, Some(err.to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refreshes_once_per_burst_of_changes() {
+        let changed = Arc::new(Notify::new());
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let task = tokio::spawn({
+            let changed = changed.clone();
+            let refreshes = refreshes.clone();
+            async move {
+                loop {
+                    debounced_change(&changed, Duration::from_millis(2000)).await;
+                    refreshes.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        for _ in 0..10 {
+            changed.notify_one();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+
+        changed.notify_one();
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert_eq!(refreshes.load(Ordering::SeqCst), 2);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn reads_group_descriptions_from_config_maps() {
+        let client = mock_client(
+            200,
+            serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMapList",
+                "metadata": { "resourceVersion": "1" },
+                "items": [
+                    {
+                        "metadata": { "name": "b", "namespace": "default" },
+                        "data": { "prod": "Shadowed", "dev": "Development clusters" },
+                    },
+                    {
+                        "metadata": { "name": "a", "namespace": "default" },
+                        "data": { "prod": "Production clusters" },
+                    },
+                ],
+            }),
+        );
+        let group_descriptions = GroupDescriptions {
+            enabled: true,
+            label_selector: "landingpage.info/group-descriptions=true".to_owned(),
+            namespace: None,
+        };
+        let descriptions = collect_group_descriptions(&group_descriptions, client.clone())
+            .await
+            .unwrap();
+        assert_eq!(descriptions["prod"], "Production clusters");
+        assert_eq!(descriptions["dev"], "Development clusters");
+
+        let mut collection = vec![group("prod", Vec::new()), group("test", Vec::new())];
+        describe_groups(&group_descriptions, &mut collection, client).await;
+        assert_eq!(collection[0].description, "Production clusters");
+        assert_eq!(collection[1].description, "");
+    }
+
+    #[test]
+    fn exposes_filtered_annotations() {
+        let specs = || {
+            vec![spec(
+                "grafana",
+                "grafana.example.com",
+                &[
+                    ("team", "observability"),
+                    ("example.com/api-token", "secret"),
+                    ("kubectl.kubernetes.io/last-applied-configuration", "{}"),
+                    ("long", &"x".repeat(MAX_ANNOTATION_VALUE_LENGTH + 1)),
+                ],
+            )]
+        };
+        let info = cluster(&config("{}"), specs());
+        assert!(info.ingresses[0].annotations.is_empty());
+
+        let info = cluster(&config("global:\n  exposeAllAnnotations: true\n"), specs());
+        let annotations = &info.ingresses[0].annotations;
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations["team"], "observability");
+    }
+
+    #[tokio::test]
+    async fn discovers_namespaces_by_selector() {
+        let client = mock_client(
+            200,
+            serde_json::json!({
+                "apiVersion": "meta.k8s.io/v1",
+                "kind": "PartialObjectMetadataList",
+                "metadata": { "resourceVersion": "1" },
+                "items": [
+                    { "apiVersion": "meta.k8s.io/v1", "kind": "PartialObjectMetadata", "metadata": { "name": "team-a" } },
+                    { "apiVersion": "meta.k8s.io/v1", "kind": "PartialObjectMetadata", "metadata": { "name": "team-b" } },
+                ],
+            }),
+        );
+        let namespaces = discover_namespaces(client.clone(), None).await.unwrap();
+        assert_eq!(namespaces, ["team-a", "team-b"]);
+
+        let config = config(
+            "local:\n  enabled: true\n  namespaceSelector: landingpage=true\n  namespaces: [team-b, team-c]\n",
+        );
+        let namespaces = configured_namespaces(config.local.as_ref().unwrap(), client)
+            .await
+            .unwrap();
+        assert_eq!(namespaces, Some(vec!["team-b".to_owned()]));
+    }
+
+    #[tokio::test]
+    async fn truncates_ingresses_per_cluster() {
+        let config = config("global:\n  maxIngressesPerCluster: 2\n");
+        let specs = ["a", "b", "c"]
+            .into_iter()
+            .map(|name| spec(name, &format!("{name}.example.com"), &[]))
+            .collect();
+        let collection = vec![group("prod", vec![cluster(&config, specs)])];
+        let result = finish_collection(&config, collection).await;
+        let info = &result[0].clusters[0];
+        let names: Vec<_> = info.ingresses.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert!(info.truncated);
+        assert_eq!(info.hidden_count, 1);
+    }
+
+    #[tokio::test]
+    async fn truncates_ingresses_after_sorting_by_health() {
+        let config = config(
+            "global:\n  maxIngressesPerCluster: 2\n  sortByHealth: true\n  healthCheck:\n    enabled: true\n",
+        );
+        let specs = ["a", "b", "c"]
+            .into_iter()
+            .map(|name| spec(name, &format!("{name}.example.com"), &[]))
+            .collect();
+        let mut info = cluster(&config, specs);
+        for ingress in info.ingresses.iter_mut() {
+            ingress.health = if ingress.name == "c" {
+                HealthStatus::Down
+            } else {
+                HealthStatus::Up
+            };
+        }
+        let result = finish_collection(&config, vec![group("prod", vec![info])]).await;
+        let info = &result[0].clusters[0];
+        let names: Vec<_> = info.ingresses.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["c", "a"]);
+        assert!(info.truncated);
+        assert_eq!(info.hidden_count, 1);
+    }
+
+    #[test]
+    fn reads_docs_url_from_annotation() {
+        let info = cluster(
+            &config("{}"),
+            vec![spec(
+                "grafana",
+                "grafana.example.com",
+                &[(DOCS_URL_ANNOTATION, "/docs")],
+            )],
+        );
+        let ingress = &info.ingresses[0];
+        assert_eq!(ingress.url, "https://grafana.example.com/");
+        assert_eq!(
+            ingress.docs_url.as_deref(),
+            Some("https://grafana.example.com/docs")
+        );
+    }
+
+    #[test]
+    fn hides_empty_clusters_and_groups() {
+        let collection = || {
+            let config = config("{}");
+            let filled = cluster(&config, vec![spec("a", "a.example.com", &[])]);
+            let empty = cluster(&config, Vec::new());
+            vec![
+                group("filled", vec![filled, empty.clone()]),
+                group("empty", vec![empty]),
+            ]
+        };
+        let cluster_counts = |collection: &IngressCollection| {
+            collection
+                .iter()
+                .map(|group| (group.name.clone(), group.clusters.len()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut result = collection();
+        hide_empty(&mut result, false, false);
+        assert_eq!(
+            cluster_counts(&result),
+            [("filled".to_owned(), 2), ("empty".to_owned(), 1)]
+        );
+
+        let mut result = collection();
+        hide_empty(&mut result, true, false);
+        assert_eq!(
+            cluster_counts(&result),
+            [("filled".to_owned(), 1), ("empty".to_owned(), 0)]
+        );
+
+        let mut result = collection();
+        hide_empty(&mut result, false, true);
+        assert_eq!(cluster_counts(&result), [("filled".to_owned(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn shows_cluster_errors_only_if_enabled() {
+        let collection = |config: &Config| {
+            let mut failed = cluster(config, vec![spec("a", "a.example.com", &[])]);
+            failed.error = Some("Request timed out".to_owned());
+            vec![group("prod", vec![failed])]
+        };
+        let errors = |collection: IngressCollection| collection[0].clusters[0].error.clone();
+
+        let enabled = config("global:\n  showClusterErrors: true\n");
+        assert!(failed_cluster(&enabled, "prod", &None, "refused".to_owned()).is_some());
+        let result = finish_collection(&enabled, collection(&enabled)).await;
+        assert_eq!(errors(result), Some("Request timed out".to_owned()));
+
+        let disabled = config("{}");
+        assert!(failed_cluster(&disabled, "prod", &None, "refused".to_owned()).is_none());
+        let result = finish_collection(&disabled, collection(&disabled)).await;
+        assert_eq!(errors(result), None);
+    }
+
+    /// Ingress with one rule routing `/` of the host to the service `app` on port `http`
+    fn ingress(annotations: serde_json::Value, tls: serde_json::Value) -> Ingress {
+        serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app", "namespace": "default", "annotations": annotations },
+            "spec": {
+                "tls": tls,
+                "rules": [{
+                    "host": "app.example.com",
+                    "http": { "paths": [{
+                        "path": "/",
+                        "pathType": "Prefix",
+                        "backend": { "service": { "name": "app", "port": { "name": "http" } } },
+                    }] },
+                }],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn shows_backend_if_enabled() {
+        let specs = || ingress_specs(&ingress(serde_json::json!({}), serde_json::json!([])), None);
+        let info = cluster(&config("{}"), specs());
+        assert!(info.ingresses[0].backend.is_none());
+
+        let info = cluster(&config("global:\n  showBackend: true\n"), specs());
+        let Some(BackendInfo::Service { name, port }) = info.ingresses[0].backend.as_ref() else {
+            panic!("expected a service backend");
+        };
+        assert_eq!(name, "app");
+        assert_eq!(port.as_deref(), Some("http"));
+    }
+
+    #[test]
+    fn filters_ingresses_by_age() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let created = |seconds: i64| now - chrono::Duration::seconds(seconds);
+        let config = config("global:\n  minAgeSeconds: 60\n  maxAgeSeconds: 3600\n");
+        let global = config.global.as_ref().unwrap();
+        assert!(!within_age_limits(global, created(59), now));
+        assert!(within_age_limits(global, created(60), now));
+        assert!(within_age_limits(global, created(3600), now));
+        assert!(!within_age_limits(global, created(3601), now));
+
+        let unlimited = Global::default();
+        assert!(within_age_limits(&unlimited, created(0), now));
+        assert!(within_age_limits(&unlimited, created(1_000_000), now));
+    }
+
+    #[test]
+    fn assume_tls_overrides_tls_block() {
+        let without_tls = ingress(serde_json::json!({}), serde_json::json!([]));
+        assert!(!ingress_specs(&without_tls, None)[0].tls_used);
+        assert!(ingress_specs(&without_tls, Some(true))[0].tls_used);
+
+        let with_tls = ingress(
+            serde_json::json!({}),
+            serde_json::json!([{ "hosts": ["app.example.com"] }]),
+        );
+        assert!(ingress_specs(&with_tls, None)[0].tls_used);
+        assert!(!ingress_specs(&with_tls, Some(false))[0].tls_used);
+
+        let config = config(
+            "remote:\n  prod:\n    - name: prod\n      assumeTls: true\n      kubeconfigSecret:\n        name: prod\n        namespace: default\n",
+        );
+        let remote = &config.remote.as_ref().unwrap().values().next().unwrap()[0];
+        let info = cluster(&config, ingress_specs(&without_tls, remote.assume_tls()));
+        assert_eq!(info.ingresses[0].url, "https://app.example.com/");
+    }
+
+    #[test]
+    fn shows_ingress_in_all_assigned_groups() {
+        let config = config("{}");
+        let info = cluster(
+            &config,
+            vec![
+                spec(
+                    "grafana",
+                    "grafana.example.com",
+                    &[(GROUPS_ANNOTATION, "ops, dev")],
+                ),
+                spec("app", "app.example.com", &[]),
+            ],
+        );
+        let mut collection = vec![group("local", vec![info])];
+        regroup(&mut collection);
+        let names = |group_name: &str| {
+            let group = collection.iter().find(|g| g.name == group_name).unwrap();
+            group.clusters[0]
+                .ingresses
+                .iter()
+                .map(|i| i.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("local"), ["app"]);
+        assert_eq!(names("ops"), ["grafana"]);
+        assert_eq!(names("dev"), ["grafana"]);
+    }
+
+    #[test]
+    fn strips_regex_from_paths() {
+        assert_eq!(strip_regex("/api(/|$)(.*)"), "/");
+        assert_eq!(strip_regex("/app/v[0-9]+/ui"), "/app/");
+        assert_eq!(strip_regex("/app/ui/.*"), "/app/ui/");
+        assert_eq!(strip_regex("/app/ui"), "/app/ui");
+    }
+
+    #[test]
+    fn handles_regex_paths() {
+        let regex_spec = || IngressSpec {
+            path: Some("/app/v[0-9]+/ui".to_owned()),
+            path_regex: true,
+            ..spec("app", "app.example.com", &[])
+        };
+        let urls = |yaml: &str| {
+            cluster(&config(yaml), vec![regex_spec()])
+                .ingresses
+                .into_iter()
+                .map(|i| i.url)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(urls("{}"), ["https://app.example.com/app/"]);
+        assert_eq!(
+            urls("global:\n  regexPathHandling: root\n"),
+            ["https://app.example.com/"]
+        );
+        assert_eq!(
+            urls("global:\n  regexPathHandling: keep\n"),
+            ["https://app.example.com/app/v[0-9]+/ui"]
+        );
+        assert!(urls("global:\n  regexPathHandling: drop\n").is_empty());
+    }
+
+    #[test]
+    fn keeps_disappeared_ingresses_for_grace_period() {
+        let config = config("{}");
+        let now = Utc::now();
+        let mut previous = cluster(
+            &config,
+            vec![
+                spec("recent", "recent.example.com", &[]),
+                spec("old", "old.example.com", &[]),
+                spec("current", "current.example.com", &[]),
+            ],
+        );
+        for ingress in previous.ingresses.iter_mut() {
+            ingress.last_seen = match ingress.name.as_str() {
+                "old" => now - chrono::Duration::seconds(120),
+                _ => now - chrono::Duration::seconds(30),
+            };
+        }
+        let previous = vec![group("local", vec![previous])];
+        let mut current = vec![group(
+            "local",
+            vec![cluster(
+                &config,
+                vec![spec("current", "current.example.com", &[])],
+            )],
+        )];
+        retain_disappeared(&previous, &mut current, 60, now);
+        let ingresses: Vec<_> = current[0].clusters[0]
+            .ingresses
+            .iter()
+            .map(|i| (i.name.as_str(), i.stale))
+            .collect();
+        assert_eq!(ingresses, [("current", false), ("recent", true)]);
+    }
+
+    #[test]
+    fn keeps_object_name_besides_display_name() {
+        let info = cluster(
+            &config("{}"),
+            vec![spec(
+                "grafana-ingress",
+                "grafana.example.com",
+                &[(NAME_ANNOTATION, "Grafana")],
+            )],
+        );
+        assert_eq!(info.ingresses[0].name, "Grafana");
+        assert_eq!(info.ingresses[0].ingress_name, "grafana-ingress");
+    }
+
+    #[test]
+    fn builds_api_for_ingress_api_version() {
+        let resource = ingress_api_resource("networking.k8s.io/v1beta1");
+        assert_eq!(resource.group, "networking.k8s.io");
+        assert_eq!(resource.version, "v1beta1");
+        assert_eq!(resource.api_version, "networking.k8s.io/v1beta1");
+        assert_eq!(resource.plural, "ingresses");
+    }
+
+    #[test]
+    fn reads_v1beta1_ingresses() {
+        let object: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "extensions/v1beta1",
+            "kind": "Ingress",
+            "metadata": { "name": "app", "namespace": "default" },
+            "spec": {
+                "backend": { "serviceName": "default", "servicePort": 80 },
+                "rules": [{
+                    "host": "app.example.com",
+                    "http": { "paths": [{
+                        "path": "/app",
+                        "backend": { "serviceName": "app", "servicePort": "http" },
+                    }] },
+                }],
+            },
+        }))
+        .unwrap();
+        let ingress = ingress_from_dynamic(object).unwrap();
+        let specs = ingress_specs(&ingress, None);
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].path.as_deref(), Some("/app"));
+        let Some(BackendInfo::Service { name, port }) = specs[0].backend.as_ref() else {
+            panic!("expected a service backend");
+        };
+        assert_eq!(name, "app");
+        assert_eq!(port.as_deref(), Some("http"));
+    }
+
+    #[tokio::test]
+    async fn adds_static_links() {
+        let config = config(
+            "staticLinks:\n  - group: tools\n    name: Wiki\n    url: https://wiki.example.com\n  - group: local\n    cluster: local\n    name: Dashboard\n    url: https://dashboard.example.com\n  - group: tools\n    name: Evil\n    url: javascript:alert(1)\n",
+        );
+        let collection = vec![group("local", vec![cluster(&config, Vec::new())])];
+        let result = finish_collection(&config, collection).await;
+        let links = |links: &[LinkInfo]| {
+            links
+                .iter()
+                .map(|link| (link.name.clone(), link.url.clone()))
+                .collect::<Vec<_>>()
+        };
+        let local = result.iter().find(|g| g.name == "local").unwrap();
+        assert_eq!(
+            links(&local.clusters[0].links),
+            [(
+                "Dashboard".to_owned(),
+                "https://dashboard.example.com/".to_owned()
+            )]
+        );
+        let tools = result.iter().find(|g| g.name == "tools").unwrap();
+        assert_eq!(
+            links(&tools.links),
+            [("Wiki".to_owned(), "https://wiki.example.com/".to_owned())]
+        );
+    }
+
+    #[test]
+    fn dedups_identical_urls_across_clusters() {
+        let config = config("{}");
+        let prod = cluster(
+            &config,
+            vec![spec(
+                "grafana",
+                "Grafana.example.com",
+                &[(DESCRIPTION_ANNOTATION, "Dashboards")],
+            )],
+        );
+        let mut dev = cluster(
+            &config,
+            vec![
+                spec("grafana", "grafana.example.com", &[]),
+                spec("app", "app.example.com", &[]),
+            ],
+        );
+        dev.name = "dev".to_owned();
+        let mut collection = vec![group("prod", vec![prod]), group("dev", vec![dev])];
+        dedup_by_url(&mut collection);
+
+        let grafana = &collection[0].clusters[0].ingresses[0];
+        assert_eq!(grafana.description, "Dashboards");
+        assert_eq!(grafana.sources, ["dev", "local"]);
+        let dev: Vec<_> = collection[1].clusters[0]
+            .ingresses
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(dev, ["app"]);
+    }
+
+    #[tokio::test]
+    async fn only_collects_from_permitted_namespaces() {
+        // Ingresses may only be listed in the namespace team-a
+        let service = tower::service_fn(
+            |request: axum::http::Request<kube::client::Body>| async move {
+                let body = if request.uri().path() == "/api/v1/namespaces" {
+                    serde_json::json!({
+                        "apiVersion": "meta.k8s.io/v1",
+                        "kind": "PartialObjectMetadataList",
+                        "metadata": {},
+                        "items": [
+                            { "apiVersion": "meta.k8s.io/v1", "kind": "PartialObjectMetadata", "metadata": { "name": "team-a" } },
+                            { "apiVersion": "meta.k8s.io/v1", "kind": "PartialObjectMetadata", "metadata": { "name": "team-b" } },
+                        ],
+                    })
+                } else {
+                    let body = axum::body::to_bytes(
+                        axum::body::Body::new(request.into_body()),
+                        usize::MAX,
+                    )
+                    .await
+                    .unwrap();
+                    let mut review: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                    let allowed = review["spec"]["resourceAttributes"]["namespace"] == "team-a";
+                    review["status"] = serde_json::json!({ "allowed": allowed });
+                    review
+                };
+                let response = axum::http::Response::new(axum::body::Body::from(body.to_string()));
+                Ok::<_, std::convert::Infallible>(response)
+            },
+        );
+        let client = Client::new(service, "default");
+        let local = LocalCluster::default();
+        assert!(
+            !can_list_ingresses(&local, client.clone(), None)
+                .await
+                .unwrap()
+        );
+        let namespaces = permitted_namespaces(&local, client, None).await.unwrap();
+        assert_eq!(namespaces, Some(vec!["team-a".to_owned()]));
+    }
+
+    #[test]
+    fn parses_colors() {
+        for color in ["#f00", "#FF0000", "teal", " Red "] {
+            assert!(color.parse::<Color>().is_ok(), "{color}");
+        }
+        for color in ["#ff00", "#ggg", "not-a-color", "red; background: url(x)"] {
+            assert!(color.parse::<Color>().is_err(), "{color}");
+        }
+
+        let info = cluster(
+            &config("{}"),
+            vec![
+                spec("a", "a.example.com", &[(COLOR_ANNOTATION, "#FF0000")]),
+                spec("b", "b.example.com", &[(COLOR_ANNOTATION, "javascript")]),
+            ],
+        );
+        assert_eq!(info.ingresses[0].color, Some(Color("#ff0000".to_owned())));
+        assert_eq!(info.ingresses[1].color, None);
+    }
+
+    #[tokio::test]
+    async fn limits_concurrent_collections() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let service = tower::service_fn({
+            let running = running.clone();
+            let max_running = max_running.clone();
+            move |_: axum::http::Request<kube::client::Body>| {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                async move {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now_running, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    let body = serde_json::json!({
+                        "apiVersion": "networking.k8s.io/v1",
+                        "kind": "IngressList",
+                        "metadata": {},
+                        "items": [],
+                    });
+                    let response =
+                        axum::http::Response::new(axum::body::Body::from(body.to_string()));
+                    Ok::<_, std::convert::Infallible>(response)
+                }
+            }
+        });
+        let client = Client::new(service, "default");
+        let config = config("local:\n  enabled: true\n");
+        let namespaces: Vec<String> = (0..6).map(|i| format!("team-{i}")).collect();
+        let results = collect_namespaces(
+            &config,
+            config.local.as_ref().unwrap(),
+            client,
+            &namespaces,
+            &Semaphore::new(2),
+            &IngressStore::default(),
+        )
+        .await;
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn sorts_ingresses_by_category() {
+        let info = cluster(
+            &config("{}"),
+            vec![
+                spec("a", "a.example.com", &[]),
+                spec("b", "b.example.com", &[(CATEGORY_ANNOTATION, "Tools")]),
+                spec("c", "c.example.com", &[(CATEGORY_ANNOTATION, "Apps")]),
+                spec("d", "d.example.com", &[(CATEGORY_ANNOTATION, "Monitoring")]),
+                spec("e", "e.example.com", &[(CATEGORY_ANNOTATION, "Tools")]),
+            ],
+        );
+        let mut collection = vec![group("local", vec![info])];
+        sort_by_category(&mut collection, &["Tools".to_owned()]);
+        let names: Vec<_> = collection[0].clusters[0]
+            .ingresses
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["b", "e", "c", "d", "a"]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_kubeconfig_secret() {
+        // API server of the remote cluster that only answers the version request
+        let remote_api = axum::Router::new().route(
+            "/version",
+            axum::routing::get(|| async {
+                axum::Json(serde_json::json!({
+                    "major": "1", "minor": "31", "gitVersion": "v1.31.0", "gitCommit": "",
+                    "gitTreeState": "", "buildDate": "", "goVersion": "", "compiler": "",
+                    "platform": "",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, remote_api).await.unwrap() });
+        let kubeconfig = format!(
+            "apiVersion: v1\nkind: Config\nclusters:\n- name: remote\n  cluster:\n    server: {server}\nusers:\n- name: remote\n  user:\n    token: secret\ncontexts:\n- name: remote\n  context:\n    cluster: remote\n    user: remote\ncurrent-context: remote\n"
+        );
+
+        let service = tower::service_fn(move |request: axum::http::Request<kube::client::Body>| {
+            let (status, body) = match request.uri().path() {
+                "/api/v1/namespaces/default/secrets/fallback-test-secondary" => (
+                    200,
+                    serde_json::json!({
+                        "apiVersion": "v1",
+                        "kind": "Secret",
+                        "metadata": {
+                            "name": "fallback-test-secondary",
+                            "namespace": "default",
+                            "resourceVersion": "1",
+                        },
+                        "data": { "value": BASE64_STANDARD.encode(&kubeconfig) },
+                    }),
+                ),
+                _ => (
+                    404,
+                    serde_json::json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "status": "Failure",
+                        "reason": "NotFound",
+                        "code": 404,
+                    }),
+                ),
+            };
+            let response = axum::http::Response::builder()
+                .status(status)
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            async move { Ok::<_, std::convert::Infallible>(response) }
+        });
+        let config = config(
+            "remote:\n  prod:\n    - name: prod\n      kubeconfigSecret:\n        name: fallback-test-primary\n        namespace: default\n      fallbackKubeconfigSecrets:\n        - name: fallback-test-secondary\n          namespace: default\n",
+        );
+        let remote = &config.remote.as_ref().unwrap().values().next().unwrap()[0];
+        let remote_client = remote_client(
+            remote,
+            Client::new(service, "default"),
+            &retry::policy(&config),
+            None,
+        )
+        .await
+        .unwrap();
+        let version = remote_client.apiserver_version().await.unwrap();
+        assert_eq!(version.git_version, "v1.31.0");
+    }
+
+    #[tokio::test]
+    async fn splits_local_cluster_by_namespace() {
+        let ingress = |namespace: &str, host: &str| {
+            serde_json::json!({
+                "metadata": { "name": "app", "namespace": namespace },
+                "spec": { "rules": [{
+                    "host": host,
+                    "http": { "paths": [{
+                        "path": "/",
+                        "pathType": "Prefix",
+                        "backend": { "service": { "name": "app", "port": { "number": 80 } } },
+                    }] },
+                }] },
+            })
+        };
+        let client = mock_client(
+            200,
+            serde_json::json!({
+                "apiVersion": "networking.k8s.io/v1",
+                "kind": "IngressList",
+                "metadata": {},
+                "items": [
+                    ingress("team-b", "b.example.com"),
+                    ingress("team-a", "a.example.com"),
+                    ingress("team-a", "a2.example.com"),
+                ],
+            }),
+        );
+        let config = config("local:\n  enabled: true\n  splitByNamespace: true\n");
+        let clusters = collect_local(
+            &config,
+            config.local.as_ref().unwrap(),
+            client,
+            &Semaphore::new(1),
+            &IngressStore::default(),
+        )
+        .await
+        .unwrap();
+        let clusters: Vec<_> = clusters
+            .iter()
+            .map(|cluster| (cluster.name.as_str(), cluster.ingresses.len()))
+            .collect();
+        assert_eq!(clusters, [("team-a", 2), ("team-b", 1)]);
+    }
+
+    #[tokio::test]
+    async fn hides_ingresses_without_ready_endpoints() {
+        let ingress = |service: &str| {
+            serde_json::json!({
+                "metadata": { "name": service, "namespace": "default" },
+                "spec": { "rules": [{
+                    "host": format!("{service}.example.com"),
+                    "http": { "paths": [{
+                        "path": "/",
+                        "pathType": "Prefix",
+                        "backend": { "service": { "name": service, "port": { "number": 80 } } },
+                    }] },
+                }] },
+            })
+        };
+        let endpoint_slice = |service: &str, ready: bool| {
+            serde_json::json!({
+                "metadata": {
+                    "name": format!("{service}-abcde"),
+                    "namespace": "default",
+                    "labels": { SERVICE_NAME_LABEL: service },
+                },
+                "addressType": "IPv4",
+                "endpoints": [{ "addresses": ["10.0.0.1"], "conditions": { "ready": ready } }],
+            })
+        };
+        let ingresses = serde_json::json!({
+            "apiVersion": "networking.k8s.io/v1",
+            "kind": "IngressList",
+            "metadata": {},
+            "items": [ingress("ready"), ingress("unready"), ingress("missing")],
+        });
+        let endpoint_slices = serde_json::json!({
+            "apiVersion": "discovery.k8s.io/v1",
+            "kind": "EndpointSliceList",
+            "metadata": {},
+            "items": [endpoint_slice("ready", true), endpoint_slice("unready", false)],
+        });
+        let service = tower::service_fn(move |request: axum::http::Request<kube::client::Body>| {
+            let body = if request.uri().path().ends_with("/endpointslices") {
+                &endpoint_slices
+            } else {
+                &ingresses
+            };
+            let response = axum::http::Response::new(axum::body::Body::from(body.to_string()));
+            async move { Ok::<_, std::convert::Infallible>(response) }
+        });
+        let config = config("global:\n  requireReadyEndpoints: true\nlocal:\n  enabled: true\n");
+        let specs = collect_ingresses(
+            &config,
+            config.local.as_ref().unwrap(),
+            Client::new(service, "default"),
+            None,
+            &Semaphore::new(1),
+            &IngressStore::default(),
+        )
+        .await
+        .unwrap();
+        let names: Vec<_> = specs.iter().map(|spec| spec.name.as_str()).collect();
+        assert_eq!(names, ["ready"]);
+    }
+
+    #[test]
+    fn marks_protected_ingresses() {
+        let specs = || {
+            vec![
+                spec(
+                    "admin",
+                    "admin.example.com",
+                    &[(
+                        "nginx.ingress.kubernetes.io/auth-url",
+                        "https://auth.example.com",
+                    )],
+                ),
+                spec("public", "public.example.com", &[]),
+            ]
+        };
+        let info = cluster(&config("{}"), specs());
+        assert!(info.ingresses[0].protected);
+        assert_eq!(
+            info.ingresses[0].protection.as_deref(),
+            Some("nginx.ingress.kubernetes.io/auth-url")
+        );
+        assert!(!info.ingresses[1].protected);
+
+        let info = cluster(
+            &config("global:\n  protectionAnnotations: [example.com/sso]\n"),
+            specs(),
+        );
+        assert!(!info.ingresses[0].protected);
+    }
+
+    #[test]
+    fn matches_tls_hosts() {
+        assert!(tls_host_matches("*", "app.example.com"));
+        assert!(tls_host_matches("App.Example.com", "app.example.com"));
+        assert!(tls_host_matches("*.example.com", "app.example.com"));
+        assert!(!tls_host_matches("*.example.com", "example.com"));
+        assert!(!tls_host_matches("*.example.com", "a.app.example.com"));
+        assert!(!tls_host_matches("other.example.com", "app.example.com"));
+    }
+
+    #[tokio::test]
+    async fn skips_forbidden_discovered_namespaces() {
+        let service = tower::service_fn(|request: axum::http::Request<kube::client::Body>| {
+            let namespace = |name: &str| serde_json::json!({ "metadata": { "name": name } });
+            let (status, body) = match request.uri().path() {
+                "/api/v1/namespaces" => (
+                    200,
+                    serde_json::json!({
+                        "apiVersion": "v1",
+                        "kind": "NamespaceList",
+                        "metadata": {},
+                        "items": [namespace("team-a"), namespace("team-b")],
+                    }),
+                ),
+                "/apis/networking.k8s.io/v1/namespaces/team-a/ingresses" => {
+                    let mut ingress =
+                        serde_json::to_value(ingress(serde_json::json!({}), serde_json::json!([])))
+                            .unwrap();
+                    ingress["metadata"]["namespace"] = "team-a".into();
+                    (
+                        200,
+                        serde_json::json!({
+                            "apiVersion": "networking.k8s.io/v1",
+                            "kind": "IngressList",
+                            "metadata": {},
+                            "items": [ingress],
+                        }),
+                    )
+                }
+                path => (
+                    403,
+                    serde_json::json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "status": "Failure",
+                        "reason": "Forbidden",
+                        "message": format!("access to {path} is forbidden"),
+                        "code": 403,
+                    }),
+                ),
+            };
+            let response = axum::http::Response::builder()
+                .status(status)
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            async move { Ok::<_, std::convert::Infallible>(response) }
+        });
+        let config = config("local:\n  enabled: true\n  discoverNamespaces: true\n");
+        let clusters = collect_local(
+            &config,
+            config.local.as_ref().unwrap(),
+            Client::new(service, "default"),
+            &Semaphore::new(2),
+            &IngressStore::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(clusters.len(), 1);
+        let urls: Vec<_> = clusters[0]
+            .ingresses
+            .iter()
+            .map(|i| i.url.as_str())
+            .collect();
+        assert_eq!(urls, ["http://app.example.com/"]);
     }
 }
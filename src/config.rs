@@ -1,51 +1,1220 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr, time::Duration};
 
+use jsonpath_rust::JsonPath;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::{
+    errors::{Error, Result},
+    health::{HealthMethod, StatusRanges},
+    locale::Locale,
+    toml,
+};
+
+const DEFAULT_REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
 #[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct GroupName(pub String);
 
 #[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Config {
     pub global: Option<Global>,
     pub local: Option<LocalCluster>,
     pub remote: Option<HashMap<GroupName, Vec<RemoteCluster>>>,
+    /// Alternative to `remote` with descriptions and display order
+    pub groups: Option<Vec<Group>>,
+    pub static_links: Option<Vec<StaticLink>>,
+    /// Registers remote clusters from labeled kubeconfig secrets
+    pub discovery: Option<Discovery>,
+    /// Registers remote clusters from Cluster API `Cluster` objects
+    pub cluster_api: Option<ClusterApi>,
+    /// `LandingPage` resource the config was read from
+    #[serde(skip)]
+    pub source_resource: Option<StatusResource>,
 }
 
+/// Remote clusters from the ready `Cluster` objects of Cluster API
 #[derive(Deserialize, Debug, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ClusterApi {
+    #[serde(default)]
+    pub enabled: bool,
+    /// All namespaces if not set
+    pub namespace: Option<String>,
+    pub label_selector: Option<String>,
+    /// Label of the `Cluster` objects with the group
+    pub group_label: Option<String>,
+    /// "cluster-api" if not set
+    pub default_group: Option<String>,
+}
+
+/// Remote clusters from secrets labeled `landingpage.info/kubeconfig=true`
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Discovery {
+    #[serde(default)]
+    pub enabled: bool,
+    pub namespace: String,
+    /// "discovered" if not set
+    pub default_group: Option<String>,
+}
+
+/// A group of clusters. Its remote clusters are added to `remote` when the config is read.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Group {
+    pub name: String,
+    pub description: Option<String>,
+    /// Ascending, groups without order follow
+    pub order: Option<i32>,
+    /// The local cluster is shown in this group instead of "local"
+    #[serde(default)]
+    pub local: bool,
+    #[serde(default)]
+    pub clusters: Vec<RemoteCluster>,
+}
+
+/// A link that is shown in a group or cluster independent of any ingress
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StaticLink {
+    pub group: String,
+    /// Show the link in this cluster instead of the group
+    pub cluster: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub url: String,
+    pub icon: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Global {
     #[serde(default)]
     pub only_with_annotation: bool,
+    pub label_selector: Option<String>,
+    pub include_hosts_regex: Option<String>,
+    pub exclude_hosts_regex: Option<String>,
     pub refresh_interval_seconds: Option<u64>,
+    /// Refresh shortly after an ingress changed
+    #[serde(default)]
+    pub watch: bool,
+    /// Changes within this time lead to one refresh
+    pub watch_debounce_ms: Option<u64>,
+    /// Maximum random delay added to every refresh interval
+    pub refresh_jitter_seconds: Option<u64>,
+    /// Maximum random delay of the first collection
+    pub startup_delay_seconds: Option<u64>,
+    /// Reload the config files if they changed, disabled if not set
+    pub reload_config_seconds: Option<u64>,
+    /// Clusters and namespaces that are collected at the same time
+    pub max_concurrent_collections: Option<usize>,
+    /// A cluster that takes longer counts as failed
+    pub cluster_timeout_seconds: Option<u64>,
+    #[serde(default)]
+    pub retry: Retry,
+    #[serde(default)]
+    pub strict_remotes: bool,
+    pub group_descriptions: Option<GroupDescriptions>,
+    #[serde(default)]
+    pub expose_all_annotations: bool,
+    pub max_ingresses_per_cluster: Option<usize>,
+    #[serde(default)]
+    pub hide_empty_clusters: bool,
+    #[serde(default)]
+    pub hide_empty_groups: bool,
+    #[serde(default)]
+    pub show_cluster_errors: bool,
+    pub health_check: Option<HealthCheck>,
+    /// Other categories follow alphabetically
+    #[serde(default)]
+    pub category_order: Vec<String>,
+    #[serde(default)]
+    pub require_ready_endpoints: bool,
+    /// Only with health checks
+    #[serde(default)]
+    pub sort_by_health: bool,
+    #[serde(default)]
+    pub show_backend: bool,
+    /// Allow simple HTML formatting in descriptions
+    #[serde(default)]
+    pub html_description: bool,
+    /// Additional key of the collection in the template context
+    pub context_root_key: Option<String>,
+    /// Replaces the default list of annotations that mark an ingress as protected
+    pub protection_annotations: Option<Vec<String>>,
+    /// Above this number of ingresses the index only shows an overview of the groups
+    pub summarize_above_count: Option<usize>,
+    #[serde(default)]
+    pub show_resource_name: bool,
+    pub locale: Option<Locale>,
+    pub min_age_seconds: Option<i64>,
+    pub max_age_seconds: Option<i64>,
+    #[serde(default = "default_true")]
+    pub cache_rendered_page: bool,
+    /// `no-store` if not set
+    pub html_cache_control: Option<String>,
+    /// Update the page over `/ws`
+    #[serde(default)]
+    pub live_updates: bool,
+    #[serde(default)]
+    pub swagger_ui: bool,
+    #[serde(default)]
+    pub strip_url_query: bool,
+    #[serde(default)]
+    pub strip_url_fragment: bool,
+    /// URLs of ingresses with regex paths (nginx `use-regex`)
+    #[serde(default)]
+    pub regex_path_handling: RegexPathHandling,
+    #[serde(default)]
+    pub dedup_by_url: bool,
+    #[serde(default)]
+    pub dedup_hosts: DedupHosts,
+    #[serde(default)]
+    pub on_duplicate_cluster_name: DuplicateClusterNames,
+    /// Summary of the collection is written into the status of this resource
+    pub status_resource: Option<StatusResource>,
+    /// Keep showing disappeared ingresses for this time
+    pub ingress_grace_period_seconds: Option<i64>,
+    /// Custom kinds by the name used in `kinds`
+    #[serde(default)]
+    pub custom_kinds: HashMap<String, CustomKind>,
 }
 
+/// Kind of resource whose entries are read with JSONPath expressions
 #[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomKind {
+    pub api_version: String,
+    pub kind: String,
+    /// Alternative to `host` and `path`
+    pub url: Option<String>,
+    pub host: Option<String>,
+    pub path: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+impl CustomKind {
+    fn validate(&self, name: &str) -> Result<()> {
+        if self.url.is_none() && self.host.is_none() {
+            return Err(Error::InvalidConfig(format!(
+                "Custom kind {name} needs either url or host"
+            )));
+        }
+        for expression in [
+            &self.url,
+            &self.host,
+            &self.path,
+            &self.name,
+            &self.description,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            JsonPath::<serde_json::Value>::from_str(expression).map_err(|err| {
+                Error::InvalidConfig(format!(
+                    "Invalid expression {expression} of custom kind {name}: {err}"
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RegexPathHandling {
+    /// Cut the path before the first regex character, keeping only complete segments
+    #[default]
+    Strip,
+    /// Link to the root of the host
+    Root,
+    /// Use the path unchanged
+    Keep,
+    /// Skip entries whose regex path can not be linked without changing it
+    Drop,
+}
+
+/// Reference to a `LandingPage` resource
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StatusResource {
+    pub name: String,
+    pub namespace: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateClusterNames {
+    /// Prefix the names with their group, or append a number within a group
+    #[default]
+    PrefixGroup,
+    /// Refuse to start
+    Error,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DedupHosts {
+    /// Show every path as its own entry
+    #[default]
+    Off,
+    /// One entry per host
+    Host,
+    /// One entry per host and display name, entries with different names stay separate
+    HostAndName,
+}
+
+/// Actively probe the URLs of all ingresses after each collection
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HealthCheck {
+    pub enabled: bool,
+    #[serde(default = "default_health_check_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub method: HealthMethod,
+    /// Everything except server errors if not set
+    pub expect: Option<StatusRanges>,
+}
+
+fn default_health_check_timeout_seconds() -> u64 {
+    5
+}
+
+/// Retries of failed remote cluster collections and kubeconfig secret reads
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Retry {
+    /// Number of attempts including the first one, `1` disables retrying
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for every further retry
+    #[serde(default = "default_retry_initial_delay_millis")]
+    pub initial_delay_millis: u64,
+    #[serde(default = "default_retry_max_delay_millis")]
+    pub max_delay_millis: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            attempts: default_retry_attempts(),
+            initial_delay_millis: default_retry_initial_delay_millis(),
+            max_delay_millis: default_retry_max_delay_millis(),
+        }
+    }
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_delay_millis() -> u64 {
+    500
+}
+
+fn default_retry_max_delay_millis() -> u64 {
+    10_000
+}
+
+/// Read group descriptions from ConfigMaps in the local cluster.
+/// Each key of a matching ConfigMap is a group name, its value the description of that group.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GroupDescriptions {
+    pub enabled: bool,
+    #[serde(default = "default_group_descriptions_label_selector")]
+    pub label_selector: String,
+    /// All namespaces if not set
+    pub namespace: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_group_descriptions_label_selector() -> String {
+    "landingpage.info/group-descriptions=true".to_owned()
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct LocalCluster {
     pub enabled: bool,
     pub description: Option<String>,
     pub namespaces: Option<Vec<String>>,
+    /// Collect from all namespaces that can be accessed
+    #[serde(default)]
+    pub discover_namespaces: bool,
+    /// Show every namespace as its own cluster
+    #[serde(default)]
+    pub split_by_namespace: bool,
+    /// Only collect from namespaces access reviews allow listing ingresses in
+    #[serde(default)]
+    pub auto_detect_permissions: bool,
+    pub assume_tls: Option<bool>,
+    pub ingress_api_version: Option<String>,
+    pub kinds: Option<Vec<ResourceKind>>,
+    pub label_selector: Option<String>,
+    pub include_hosts_regex: Option<String>,
+    pub exclude_hosts_regex: Option<String>,
+    #[serde(default)]
+    pub exclude_namespaces: Vec<String>,
+    pub namespace_selector: Option<String>,
+    pub refresh_interval_seconds: Option<u64>,
+    pub impersonate: Option<Impersonate>,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RemoteCluster {
     pub name: String,
     pub description: Option<String>,
-    pub kubeconfig_secret: KubeconfigSecret,
+    pub kubeconfig_secret: Option<KubeconfigSecret>,
+    /// Used with `token_secret`, `client_certificate_secret` or `eks` instead of a kubeconfig
+    pub api_server_url: Option<String>,
+    /// Service account token secret with the keys `token` and optionally `ca.crt`
+    pub token_secret: Option<SecretReference>,
+    /// TLS secret with the keys `tls.crt` and `tls.key`
+    pub client_certificate_secret: Option<SecretReference>,
+    /// Token signed with the AWS credentials of the environment
+    pub eks: Option<EksCluster>,
+    pub context: Option<String>,
+    pub cluster: Option<String>,
+    pub user: Option<String>,
+    #[serde(default)]
+    pub insecure_skip_tls_verify: bool,
+    /// CA bundle in the key `ca.crt`, trusted in addition to the CA of the credentials
+    pub ca_secret: Option<SecretReference>,
+    pub impersonate: Option<Impersonate>,
+    /// Tried in order if the cluster can not be reached with the first credentials
+    #[serde(default)]
+    pub fallback_kubeconfig_secrets: Vec<KubeconfigSecret>,
+    /// 10 seconds if not set
+    pub request_timeout_seconds: Option<u64>,
     pub namespaces: Option<Vec<String>>,
+    pub assume_tls: Option<bool>,
+    pub ingress_api_version: Option<String>,
+    pub kinds: Option<Vec<ResourceKind>>,
+    pub label_selector: Option<String>,
+    pub include_hosts_regex: Option<String>,
+    pub exclude_hosts_regex: Option<String>,
+    #[serde(default)]
+    pub exclude_namespaces: Vec<String>,
+    pub namespace_selector: Option<String>,
+    pub refresh_interval_seconds: Option<u64>,
+}
+
+/// Kinds of resources that are shown as entries on the page
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceKind {
+    Ingress,
+    /// Gateway API `HTTPRoute` (`gateway.networking.k8s.io/v1`)
+    HttpRoute,
+    /// OpenShift `Route` (`route.openshift.io/v1`)
+    Route,
+    /// Istio `VirtualService` (`networking.istio.io/v1beta1`)
+    VirtualService,
+    /// Traefik `IngressRoute` (`traefik.io/v1alpha1`)
+    IngressRoute,
+    /// Traefik `IngressRouteTCP` (`traefik.io/v1alpha1`), only routes matching a `HostSNI` are shown
+    #[serde(rename = "ingressroutetcp")]
+    IngressRouteTcp,
+    /// Contour `HTTPProxy` (`projectcontour.io/v1`)
+    HttpProxy,
+    /// Knative `Service` (`serving.knative.dev/v1`)
+    KnativeService,
+    /// Services of type `LoadBalancer` or `NodePort` and services with external IPs
+    Service,
+    /// Emissary-ingress `Mapping` (`getambassador.io/v3alpha1`)
+    Mapping,
+    /// Gateway API `Gateway` (`gateway.networking.k8s.io/v1`), its listeners are shown as a separate section of the cluster
+    Gateway,
+    /// The name of an entry of `customKinds`
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Settings that can be given for the local cluster as well as for remote clusters
+pub trait ClusterConfig {
+    /// Forces https (`true`) or http (`false`) URLs
+    fn assume_tls(&self) -> Option<bool>;
+    /// Lists ingresses with this API version instead of the typed `networking.k8s.io/v1` API
+    fn ingress_api_version(&self) -> Option<&str>;
+    /// Kinds of resources to collect, only ingresses if not set
+    fn kinds(&self) -> &[ResourceKind];
+    /// Added to the global label selector
+    fn label_selector(&self) -> Option<&str>;
+    fn exclude_namespaces(&self) -> &[String];
+    fn namespaces(&self) -> Option<&[String]>;
+    /// Only namespaces with matching labels, listed again on every refresh
+    fn namespace_selector(&self) -> Option<&str>;
+    /// Applied in addition to the global host regexes
+    fn include_hosts_regex(&self) -> Option<&str>;
+    fn exclude_hosts_regex(&self) -> Option<&str>;
+    /// Overrides the global refresh interval
+    fn refresh_interval_seconds(&self) -> Option<u64>;
+}
+
+impl ClusterConfig for LocalCluster {
+    fn assume_tls(&self) -> Option<bool> {
+        self.assume_tls
+    }
+
+    fn ingress_api_version(&self) -> Option<&str> {
+        self.ingress_api_version.as_deref()
+    }
+
+    fn kinds(&self) -> &[ResourceKind] {
+        self.kinds.as_deref().unwrap_or(&[ResourceKind::Ingress])
+    }
+
+    fn label_selector(&self) -> Option<&str> {
+        self.label_selector.as_deref()
+    }
+
+    fn exclude_namespaces(&self) -> &[String] {
+        &self.exclude_namespaces
+    }
+
+    fn namespaces(&self) -> Option<&[String]> {
+        self.namespaces.as_deref()
+    }
+
+    fn namespace_selector(&self) -> Option<&str> {
+        self.namespace_selector.as_deref()
+    }
+
+    fn include_hosts_regex(&self) -> Option<&str> {
+        self.include_hosts_regex.as_deref()
+    }
+
+    fn exclude_hosts_regex(&self) -> Option<&str> {
+        self.exclude_hosts_regex.as_deref()
+    }
+
+    fn refresh_interval_seconds(&self) -> Option<u64> {
+        self.refresh_interval_seconds
+    }
+}
+
+impl RemoteCluster {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.request_timeout_seconds
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECONDS),
+        )
+    }
+}
+
+impl ClusterConfig for RemoteCluster {
+    fn assume_tls(&self) -> Option<bool> {
+        self.assume_tls
+    }
+
+    fn ingress_api_version(&self) -> Option<&str> {
+        self.ingress_api_version.as_deref()
+    }
+
+    fn kinds(&self) -> &[ResourceKind] {
+        self.kinds.as_deref().unwrap_or(&[ResourceKind::Ingress])
+    }
+
+    fn label_selector(&self) -> Option<&str> {
+        self.label_selector.as_deref()
+    }
+
+    fn exclude_namespaces(&self) -> &[String] {
+        &self.exclude_namespaces
+    }
+
+    fn namespaces(&self) -> Option<&[String]> {
+        self.namespaces.as_deref()
+    }
+
+    fn namespace_selector(&self) -> Option<&str> {
+        self.namespace_selector.as_deref()
+    }
+
+    fn include_hosts_regex(&self) -> Option<&str> {
+        self.include_hosts_regex.as_deref()
+    }
+
+    fn exclude_hosts_regex(&self) -> Option<&str> {
+        self.exclude_hosts_regex.as_deref()
+    }
+
+    fn refresh_interval_seconds(&self) -> Option<u64> {
+        self.refresh_interval_seconds
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct KubeconfigSecret {
     pub name: String,
     pub namespace: String,
+    /// `value`, `kubeconfig` and `config` are tried if not set
+    pub key: Option<String>,
+}
+
+/// Identity the requests to a cluster are made as
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Impersonate {
+    pub user: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EksCluster {
+    pub cluster_name: String,
+    pub region: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SecretReference {
+    pub name: String,
+    pub namespace: String,
 }
 
+impl Config {
+    /// Checks the config for problems that can not be expressed by its types, like unknown kinds or invalid expressions.
+    /// Depending on `onDuplicateClusterName` duplicate cluster names are either an error or are made unique.
+    pub fn validate(&mut self) -> Result<()> {
+        let no_custom_kinds = HashMap::new();
+        let custom_kinds = self
+            .global
+            .as_ref()
+            .map_or(&no_custom_kinds, |g| &g.custom_kinds);
+        for (name, custom_kind) in custom_kinds {
+            custom_kind.validate(name)?;
+        }
+        let cluster_kinds = self.local.iter().map(|local| local.kinds()).chain(
+            self.remote
+                .iter()
+                .flat_map(|remote| remote.values().flatten())
+                .map(|remote| remote.kinds()),
+        );
+        for kind in cluster_kinds.flatten() {
+            if let ResourceKind::Custom(name) = kind
+                && !custom_kinds.contains_key(name)
+            {
+                return Err(Error::InvalidConfig(format!(
+                    "Unknown kind {name}, it is neither a supported kind nor defined in customKinds"
+                )));
+            }
+        }
+        if let Some(cache_control) = self
+            .global
+            .as_ref()
+            .and_then(|g| g.html_cache_control.as_deref())
+        {
+            axum::http::HeaderValue::from_str(cache_control).map_err(|_| {
+                Error::InvalidConfig(format!(
+                    "htmlCacheControl {cache_control:?} is not a valid header value"
+                ))
+            })?;
+        }
+        self.validate_groups()?;
+        self.validate_hosts_regexes()?;
+        self.validate_remote_credentials()?;
+        self.rename_duplicate_clusters()
+    }
+
+    /// Groups of remote clusters must not be empty and every cluster needs a name,
+    /// groups of the `groups` section need a unique name and only one can contain the local cluster
+    fn validate_groups(&self) -> Result<()> {
+        let groups = self.groups.as_deref().unwrap_or_default();
+        for (index, group) in groups.iter().enumerate() {
+            if group.name.trim().is_empty() {
+                return Err(Error::InvalidConfig(format!(
+                    "groups[{index}]: the group has no name"
+                )));
+            }
+            if groups[..index].iter().any(|other| other.name == group.name) {
+                return Err(Error::InvalidConfig(format!(
+                    "groups[{index}]: the group {} is defined several times",
+                    group.name
+                )));
+            }
+            for (cluster_index, remote) in group.clusters.iter().enumerate() {
+                if remote.name.trim().is_empty() {
+                    return Err(Error::InvalidConfig(format!(
+                        "groups[{index}].clusters[{cluster_index}]: the cluster has no name"
+                    )));
+                }
+            }
+        }
+        if groups.iter().filter(|group| group.local).count() > 1 {
+            return Err(Error::InvalidConfig(
+                "groups: the local cluster can only be shown in one group".to_owned(),
+            ));
+        }
+        for (group_name, clusters) in self.remote.iter().flatten() {
+            if clusters.is_empty() {
+                return Err(Error::InvalidConfig(format!(
+                    "remote.{}: the group has no clusters",
+                    group_name.0
+                )));
+            }
+            for (index, remote) in clusters.iter().enumerate() {
+                if remote.name.trim().is_empty() {
+                    return Err(Error::InvalidConfig(format!(
+                        "remote.{}[{index}]: the cluster has no name",
+                        group_name.0
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// All host regexes must compile, they are only used later during collection
+    fn validate_hosts_regexes(&self) -> Result<()> {
+        let global = self.global.iter().flat_map(|g| {
+            [
+                g.include_hosts_regex.as_deref(),
+                g.exclude_hosts_regex.as_deref(),
+            ]
+        });
+        let local = self
+            .local
+            .iter()
+            .flat_map(|local| [local.include_hosts_regex(), local.exclude_hosts_regex()]);
+        let remote = self
+            .remote
+            .iter()
+            .flat_map(|remote| remote.values().flatten())
+            .flat_map(|remote| [remote.include_hosts_regex(), remote.exclude_hosts_regex()]);
+        for expression in global.chain(local).chain(remote).flatten() {
+            Regex::new(expression).map_err(|err| {
+                Error::InvalidConfig(format!("Invalid hosts regex {expression}: {err}"))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Every remote cluster needs either a kubeconfig secret or an API server URL together with a token secret,
+    /// a client certificate secret or a token provider
+    fn validate_remote_credentials(&self) -> Result<()> {
+        for remote in self
+            .remote
+            .iter()
+            .flat_map(|remote| remote.values().flatten())
+        {
+            let credentials = [
+                remote.kubeconfig_secret.is_some(),
+                remote.token_secret.is_some(),
+                remote.client_certificate_secret.is_some(),
+                remote.eks.is_some(),
+            ];
+            if credentials.iter().filter(|set| **set).count() != 1 {
+                return Err(Error::InvalidConfig(format!(
+                    "Remote cluster {} needs exactly one of kubeconfigSecret, tokenSecret, clientCertificateSecret and eks",
+                    remote.name
+                )));
+            }
+            if remote.kubeconfig_secret.is_some() == remote.api_server_url.is_some() {
+                return Err(Error::InvalidConfig(format!(
+                    "Remote cluster {} needs apiServerUrl together with tokenSecret, clientCertificateSecret or eks, but not with kubeconfigSecret",
+                    remote.name
+                )));
+            }
+            if remote.eks.is_some() && !remote.fallback_kubeconfig_secrets.is_empty() {
+                return Err(Error::InvalidConfig(format!(
+                    "Remote cluster {} can not use fallbackKubeconfigSecrets together with eks",
+                    remote.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Depending on `onDuplicateClusterName` duplicate cluster names are either an error or are made unique
+    fn rename_duplicate_clusters(&mut self) -> Result<()> {
+        let on_duplicate = self
+            .global
+            .as_ref()
+            .map(|g| g.on_duplicate_cluster_name)
+            .unwrap_or_default();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        if self.local.as_ref().is_some_and(|local| local.enabled) {
+            *counts.entry("local".to_owned()).or_default() += 1;
+        }
+        for remote in self
+            .remote
+            .iter()
+            .flat_map(|remote| remote.values().flatten())
+        {
+            *counts.entry(remote.name.clone()).or_default() += 1;
+        }
+        let duplicates: Vec<&String> = counts
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(name, _)| name)
+            .collect();
+        if duplicates.is_empty() {
+            return Ok(());
+        }
+        if on_duplicate == DuplicateClusterNames::Error {
+            let mut duplicates = duplicates;
+            duplicates.sort();
+            return Err(Error::InvalidConfig(format!(
+                "Duplicate cluster names: {}",
+                duplicates
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        for (group_name, clusters) in self.remote.iter_mut().flatten() {
+            let mut used: HashMap<String, usize> = HashMap::new();
+            for remote in clusters.iter_mut() {
+                if counts[&remote.name] < 2 {
+                    continue;
+                }
+                let prefixed = format!("{}/{}", group_name.0, remote.name);
+                let count = used.entry(prefixed.clone()).or_default();
+                *count += 1;
+                let name = if *count > 1 {
+                    format!("{prefixed} #{count}")
+                } else {
+                    prefixed
+                };
+                tracing::warn!(
+                    "Cluster name {} is used several times, renaming cluster to {name}",
+                    remote.name
+                );
+                remote.name = name;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the remote clusters of the `groups` section to `remote`
+    fn merge_groups(&mut self) {
+        for group in self.groups.iter().flatten() {
+            if group.clusters.is_empty() {
+                continue;
+            }
+            self.remote
+                .get_or_insert_default()
+                .entry(GroupName(group.name.clone()))
+                .or_default()
+                .extend(group.clusters.iter().cloned());
+        }
+    }
+
+    /// Name of the group the local cluster is shown in
+    pub fn local_group(&self) -> &str {
+        self.groups
+            .iter()
+            .flatten()
+            .find(|group| group.local)
+            .map_or("local", |group| &group.name)
+    }
+
+    /// Group of the `groups` section with the given name
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups
+            .iter()
+            .flatten()
+            .find(|group| group.name == name)
+    }
+
+    /// Resource the summary of the collection and the conditions are written to
+    pub fn status_resource(&self) -> Option<&StatusResource> {
+        self.global
+            .as_ref()
+            .and_then(|g| g.status_resource.as_ref())
+            .or(self.source_resource.as_ref())
+    }
+
+    /// Collect from the local cluster in all namespaces, used if no clusters are configured
+    fn with_local_default(self) -> Config {
+        Config {
+            local: Some(LocalCluster {
+                enabled: true,
+                ..Default::default()
+            }),
+            ..self
+        }
+    }
+}
+
+/// `LandingPage` resource to read the config from instead of the file, given as `CONFIG_RESOURCE=<namespace>/<name>`
+pub fn config_resource() -> Result<Option<StatusResource>> {
+    let Ok(value) = std::env::var("CONFIG_RESOURCE") else {
+        return Ok(None);
+    };
+    match value.split_once('/') {
+        Some((namespace, name)) if !namespace.is_empty() && !name.is_empty() => {
+            Ok(Some(StatusResource {
+                name: name.to_owned(),
+                namespace: namespace.to_owned(),
+            }))
+        }
+        _ => Err(Error::InvalidConfig(format!(
+            "CONFIG_RESOURCE must have the format <namespace>/<name>, got {value}"
+        ))),
+    }
+}
+
+/// Path of the config file, `CONFIG_FILE` or `config.yaml`. It can also be a comma-separated list of files and directories.
+pub fn config_path() -> String {
+    std::env::var("CONFIG_FILE").unwrap_or_else(|_| "config.yaml".to_owned())
+}
+
+/// Format of the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// The format given by `CONFIG_FORMAT`, otherwise detected from the extension of the file.
+    /// Files with other extensions are read as YAML.
+    pub fn detect(path: &str) -> Result<ConfigFormat> {
+        if let Ok(format) = std::env::var("CONFIG_FORMAT") {
+            return match format.to_lowercase().as_str() {
+                "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+                "json" => Ok(ConfigFormat::Json),
+                "toml" => Ok(ConfigFormat::Toml),
+                _ => Err(Error::InvalidConfig(format!(
+                    "Unknown CONFIG_FORMAT {format}, supported are yaml, json and toml"
+                ))),
+            };
+        }
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        Ok(match extension.as_deref() {
+            Some("json") => ConfigFormat::Json,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Yaml,
+        })
+    }
+}
+
+/// Extensions of the files that are read from a config directory
+const CONFIG_EXTENSIONS: [&str; 4] = ["yaml", "yml", "json", "toml"];
+
+/// Content of the config files in the order they are merged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFiles(Vec<(String, String)>);
+
+impl ConfigFiles {
+    /// Reads the files of `CONFIG_FILE`, directories are read with all their config files sorted by name.
+    /// Returns `None` if `CONFIG_FILE` is a single file that does not exist.
+    pub fn read() -> Result<Option<ConfigFiles>> {
+        let config_path = config_path();
+        let entries: Vec<&str> = config_path
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        let mut paths = Vec::new();
+        for entry in entries.iter() {
+            let path = std::path::Path::new(entry);
+            if path.is_dir() {
+                paths.append(&mut config_files_in(path)?);
+            } else {
+                paths.push(entry.to_string());
+            }
+        }
+        let mut files = Vec::new();
+        for path in paths {
+            match std::fs::read_to_string(&path) {
+                Ok(data) => files.push((path, data)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound && entries.len() == 1 => {
+                    return Ok(None);
+                }
+                Err(err) => {
+                    return Err(Error::InvalidConfig(format!(
+                        "Could not read config file {path}: {err}"
+                    )));
+                }
+            }
+        }
+        Ok(Some(ConfigFiles(files)))
+    }
+
+    /// Parses the files and merges them, keys of later files take precedence. Objects are merged
+    /// recursively, all other values (including lists) are replaced.
+    pub fn parse(&self) -> Result<Config> {
+        if let [(path, data)] = self.0.as_slice() {
+            return parse_config(data, ConfigFormat::detect(path)?);
+        }
+        let mut merged = serde_json::Value::Object(serde_json::Map::new());
+        for (path, data) in self.0.iter() {
+            let value =
+                parse_value(data, ConfigFormat::detect(path)?).map_err(|err| match err {
+                    Error::InvalidConfig(message) => {
+                        Error::InvalidConfig(format!("{path}: {message}"))
+                    }
+                    err => err,
+                })?;
+            merge_values(&mut merged, value);
+        }
+        config_from_value(merged)
+    }
+}
+
+/// Config files directly in the directory sorted by name, hidden files (like the `..data` link of a mounted ConfigMap) are skipped
+fn config_files_in(directory: &std::path::Path) -> Result<Vec<String>> {
+    let read_error = |err: std::io::Error| {
+        Error::InvalidConfig(format!(
+            "Could not read config directory {}: {err}",
+            directory.display()
+        ))
+    };
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(directory).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_none_or(|name| name.starts_with('.'));
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        if !hidden
+            && path.is_file()
+            && extension.is_some_and(|extension| CONFIG_EXTENSIONS.contains(&extension.as_str()))
+        {
+            paths.push(path.to_string_lossy().into_owned());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn parse_value(data: &str, format: ConfigFormat) -> Result<serde_json::Value> {
+    let value = match format {
+        ConfigFormat::Yaml => {
+            serde_yaml::from_str(data).map_err(|err| Error::InvalidConfig(err.to_string()))?
+        }
+        ConfigFormat::Json => {
+            serde_json::from_str(data).map_err(|err| Error::InvalidConfig(err.to_string()))?
+        }
+        ConfigFormat::Toml => toml::parse(data)?,
+    };
+    // An empty YAML file is null
+    Ok(match value {
+        serde_json::Value::Null => serde_json::Value::Object(serde_json::Map::new()),
+        value => value,
+    })
+}
+
+fn merge_values(target: &mut serde_json::Value, value: serde_json::Value) {
+    match (target, value) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(values)) => {
+            for (key, value) in values {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, value) => *target = value,
+    }
+}
+
+/// Reads the config files, the tool can not start without a valid config and exits if it is invalid
 pub fn read_config() -> Config {
-    let data = std::fs::read_to_string(
-        std::env::var("CONFIG_FILE").unwrap_or_else(|_| "config.yaml".to_owned()),
-    )
-    .unwrap();
-    serde_yaml::from_str(&data).unwrap()
+    let path = config_path();
+    match ConfigFiles::read().and_then(|files| files.map(|files| files.parse()).transpose()) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            tracing::info!(
+                "Config file {path} does not exist, running in default in-cluster mode collecting from all namespaces of the local cluster"
+            );
+            Config::default().with_local_default()
+        }
+        Err(err) => {
+            tracing::error!("Invalid config file {path}: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parses and validates the content of a config file
+pub fn parse_config(data: &str, format: ConfigFormat) -> Result<Config> {
+    let config: Config = match format {
+        ConfigFormat::Yaml => {
+            serde_yaml::from_str(data).map_err(|err| Error::InvalidConfig(err.to_string()))?
+        }
+        ConfigFormat::Json => {
+            serde_json::from_str(data).map_err(|err| Error::InvalidConfig(err.to_string()))?
+        }
+        // The parsed document has no locations, the path tells where a wrong value is
+        ConfigFormat::Toml => serde_path_to_error::deserialize(toml::parse(data)?)
+            .map_err(|err| Error::InvalidConfig(err.to_string()))?,
+    };
+    prepare_config(config)
+}
+
+/// Parses and validates a config given as JSON value, like the spec of a `LandingPage` resource or merged config files
+pub fn config_from_value(value: serde_json::Value) -> Result<Config> {
+    let config: Config =
+        serde_json::from_value(value).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+    prepare_config(config)
+}
+
+fn prepare_config(mut config: Config) -> Result<Config> {
+    config.merge_groups();
+    config.validate()?;
+    let discovers = config.discovery.as_ref().is_some_and(|d| d.enabled)
+        || config.cluster_api.as_ref().is_some_and(|c| c.enabled);
+    if config.local.is_none() && config.remote.is_none() && !discovers {
+        tracing::info!(
+            "No clusters configured, running in default in-cluster mode collecting from all namespaces of the local cluster"
+        );
+        return Ok(config.with_local_default());
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_config_without_clusters() {
+        let result = parse_config("global:\n  includeHostsRegex: \"((\"\n", ConfigFormat::Yaml);
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn toml_errors_contain_the_field_path() {
+        let result = parse_config(
+            "[global]\nrefreshIntervalSeconds = \"often\"\n",
+            ConfigFormat::Toml,
+        );
+        let Err(Error::InvalidConfig(message)) = result else {
+            panic!("expected an invalid config, got {result:?}");
+        };
+        assert!(
+            message.starts_with("global.refreshIntervalSeconds: "),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_cache_control() {
+        let result = parse_config(
+            "global:\n  htmlCacheControl: \"max-age=60\\nX-Injected: 1\"\n",
+            ConfigFormat::Yaml,
+        );
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn falls_back_to_local_cluster_without_clusters() {
+        let config = parse_config(
+            "global:\n  refreshIntervalSeconds: 60\n",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        assert!(config.local.is_some_and(|local| local.enabled));
+
+        let config = parse_config(
+            "discovery:\n  enabled: true\n  namespace: landingpage\n",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        assert!(config.local.is_none());
+    }
+
+    const DUPLICATE_CLUSTERS: &str = "remote:
+  prod:
+    - name: main
+      kubeconfigSecret: { name: prod-a, namespace: default }
+    - name: main
+      kubeconfigSecret: { name: prod-b, namespace: default }
+  dev:
+    - name: main
+      kubeconfigSecret: { name: dev, namespace: default }
+";
+
+    #[test]
+    fn renames_duplicate_clusters() {
+        let config = parse_config(DUPLICATE_CLUSTERS, ConfigFormat::Yaml).unwrap();
+        let remote = config.remote.unwrap();
+        let names = |group: &str| {
+            remote[&GroupName(group.to_owned())]
+                .iter()
+                .map(|remote| remote.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("prod"), ["prod/main", "prod/main #2"]);
+        assert_eq!(names("dev"), ["dev/main"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_clusters_if_configured() {
+        let config = format!("global:\n  onDuplicateClusterName: error\n{DUPLICATE_CLUSTERS}");
+        let result = parse_config(&config, ConfigFormat::Yaml);
+        let Err(Error::InvalidConfig(message)) = result else {
+            panic!("expected an invalid config, got {result:?}");
+        };
+        assert_eq!(message, "Duplicate cluster names: main");
+    }
+
+    #[test]
+    fn merges_config_files() {
+        let files = ConfigFiles(vec![
+            (
+                "config.d/01-base.yaml".to_owned(),
+                "global:\n  refreshIntervalSeconds: 60\n  categoryOrder: [Apps, Tools]\n"
+                    .to_owned(),
+            ),
+            (
+                "config.d/02-override.json".to_owned(),
+                r#"{"global": {"categoryOrder": ["Tools"], "showBackend": true}}"#.to_owned(),
+            ),
+        ]);
+        let config = files.parse().unwrap();
+        let global = config.global.unwrap();
+        assert_eq!(global.refresh_interval_seconds, Some(60));
+        assert_eq!(global.category_order, ["Tools"]);
+        assert!(global.show_backend);
+    }
+
+    #[test]
+    fn names_the_file_with_a_syntax_error() {
+        let files = ConfigFiles(vec![
+            ("a.yaml".to_owned(), "global: {}\n".to_owned()),
+            ("b.json".to_owned(), "{".to_owned()),
+        ]);
+        let Err(Error::InvalidConfig(message)) = files.parse() else {
+            panic!("expected an invalid config");
+        };
+        assert!(message.starts_with("b.json: "), "{message}");
+    }
+
+    #[test]
+    fn collects_from_local_cluster_without_config_file() {
+        // SAFETY: no other test reads or writes `CONFIG_FILE`
+        unsafe { std::env::set_var("CONFIG_FILE", "/nonexistent/config.yaml") };
+        assert_eq!(ConfigFiles::read().unwrap(), None);
+        let config = read_config();
+        assert!(config.local.is_some_and(|local| local.enabled));
+        assert!(config.remote.is_none());
+    }
 }
@@ -0,0 +1,140 @@
+//! Remote clusters that are registered by objects in the cluster instead of the config,
+//! either labeled kubeconfig secrets or the `Cluster` objects of Cluster API
+
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams},
+};
+
+use crate::{
+    config::{ClusterApi, Discovery, KubeconfigSecret, RemoteCluster},
+    errors::Result,
+};
+
+/// Secrets with this label set to `true` are registered as remote clusters
+pub const KUBECONFIG_LABEL: &str = "landingpage.info/kubeconfig";
+const NAME_ANNOTATION: &str = "landingpage.info/name";
+const DESCRIPTION_ANNOTATION: &str = "landingpage.info/description";
+const GROUP_ANNOTATION: &str = "landingpage.info/group";
+
+pub const DEFAULT_GROUP: &str = "discovered";
+
+/// Label selector of the secrets that are registered as remote clusters
+pub fn secret_selector() -> String {
+    format!("{KUBECONFIG_LABEL}=true")
+}
+
+/// Lists the labeled secrets and turns each into a remote cluster together with the name of its group.
+/// The cluster is named after the secret unless the secret has a name annotation.
+pub async fn discover_secrets(
+    discovery: &Discovery,
+    client: Client,
+) -> Result<Vec<(String, RemoteCluster)>> {
+    let api = Api::<Secret>::namespaced(client, &discovery.namespace);
+    let secrets = api
+        .list_metadata(&ListParams::default().labels(&secret_selector()))
+        .await?;
+    let default_group = discovery.default_group.as_deref().unwrap_or(DEFAULT_GROUP);
+    let mut clusters: Vec<(String, RemoteCluster)> = secrets
+        .items
+        .iter()
+        .map(|secret| {
+            let annotations = secret.annotations();
+            let group = annotations
+                .get(GROUP_ANNOTATION)
+                .cloned()
+                .unwrap_or_else(|| default_group.to_owned());
+            let remote = RemoteCluster {
+                name: annotations
+                    .get(NAME_ANNOTATION)
+                    .cloned()
+                    .unwrap_or_else(|| secret.name_any()),
+                description: annotations.get(DESCRIPTION_ANNOTATION).cloned(),
+                kubeconfig_secret: Some(KubeconfigSecret {
+                    name: secret.name_any(),
+                    namespace: discovery.namespace.clone(),
+                    key: None,
+                }),
+                ..RemoteCluster::default()
+            };
+            (group, remote)
+        })
+        .collect();
+    clusters.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    Ok(clusters)
+}
+
+pub const DEFAULT_CLUSTER_API_GROUP: &str = "cluster-api";
+
+fn cluster_api_resource() -> ApiResource {
+    ApiResource::from_gvk(&GroupVersionKind::gvk(
+        "cluster.x-k8s.io",
+        "v1beta1",
+        "Cluster",
+    ))
+}
+
+/// `Cluster` objects of Cluster API in the configured namespace (or all)
+pub fn cluster_objects(cluster_api: &ClusterApi, client: Client) -> Api<DynamicObject> {
+    let resource = cluster_api_resource();
+    match cluster_api.namespace.as_deref() {
+        Some(namespace) => Api::namespaced_with(client, namespace, &resource),
+        None => Api::all_with(client, &resource),
+    }
+}
+
+/// Lists the `Cluster` objects and turns every ready one into a remote cluster together with the name of its group.
+/// Cluster API stores the kubeconfig of a cluster in the secret `<name>-kubeconfig` next to it.
+pub async fn discover_cluster_api(
+    cluster_api: &ClusterApi,
+    client: Client,
+) -> Result<Vec<(String, RemoteCluster)>> {
+    let mut params = ListParams::default();
+    if let Some(selector) = cluster_api.label_selector.as_deref() {
+        params = params.labels(selector);
+    }
+    let clusters = cluster_objects(cluster_api, client).list(&params).await?;
+    let default_group = cluster_api
+        .default_group
+        .as_deref()
+        .unwrap_or(DEFAULT_CLUSTER_API_GROUP);
+    let mut remotes: Vec<(String, RemoteCluster)> = clusters
+        .items
+        .iter()
+        .filter(|cluster| is_ready(cluster))
+        .map(|cluster| {
+            let group = cluster_api
+                .group_label
+                .as_ref()
+                .and_then(|label| cluster.labels().get(label))
+                .cloned()
+                .unwrap_or_else(|| default_group.to_owned());
+            let remote = RemoteCluster {
+                name: cluster.name_any(),
+                description: cluster.annotations().get(DESCRIPTION_ANNOTATION).cloned(),
+                kubeconfig_secret: Some(KubeconfigSecret {
+                    name: format!("{}-kubeconfig", cluster.name_any()),
+                    namespace: cluster.namespace().unwrap_or_default(),
+                    key: None,
+                }),
+                ..RemoteCluster::default()
+            };
+            (group, remote)
+        })
+        .collect();
+    remotes.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    Ok(remotes)
+}
+
+/// A cluster is ready once its `Ready` condition is true, clusters that are still provisioned
+/// or deleted are skipped
+fn is_ready(cluster: &DynamicObject) -> bool {
+    cluster.data["status"]["conditions"]
+        .as_array()
+        .is_some_and(|conditions| {
+            conditions
+                .iter()
+                .any(|condition| condition["type"] == "Ready" && condition["status"] == "True")
+        })
+}
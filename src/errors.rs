@@ -8,6 +8,14 @@ pub enum Error {
     Kube(#[from] kube::Error),
     #[error("MissingKubeconfig: {0}")]
     MissingKubeconfig(String),
+    #[error("InvalidKubeconfig: {0}")]
+    InvalidKubeconfig(String),
+    #[error("InvalidUrl: {0}")]
+    InvalidUrl(String),
+    #[error("InvalidConfig: {0}")]
+    InvalidConfig(String),
+    #[error("Timeout: {0}")]
+    Timeout(String),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
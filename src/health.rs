@@ -0,0 +1,330 @@
+use std::{ops::RangeInclusive, str::FromStr, time::Duration};
+
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collector::{IngressCollection, IngressInfo},
+    config::HealthCheck,
+};
+
+/// Number of health checks that are run at the same time
+const CONCURRENT_CHECKS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+    #[default]
+    Unknown,
+}
+
+impl HealthStatus {
+    /// Position when sorting by health, problems come first
+    fn sort_rank(self) -> u8 {
+        match self {
+            HealthStatus::Down => 0,
+            HealthStatus::Unknown => 1,
+            HealthStatus::Up => 2,
+        }
+    }
+}
+
+/// HTTP method used to probe an URL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum HealthMethod {
+    #[default]
+    Get,
+    Head,
+}
+
+impl FromStr for HealthMethod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_uppercase().as_str() {
+            "GET" => Ok(HealthMethod::Get),
+            "HEAD" => Ok(HealthMethod::Head),
+            _ => Err(format!("Unsupported health check method {value}")),
+        }
+    }
+}
+
+impl TryFrom<String> for HealthMethod {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Status codes that count as up, parsed from a list like `200,204,301-399`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct StatusRanges(Vec<RangeInclusive<u16>>);
+
+impl StatusRanges {
+    pub fn contains(&self, status: u16) -> bool {
+        self.0.iter().any(|range| range.contains(&status))
+    }
+}
+
+impl FromStr for StatusRanges {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_status = |status: &str| {
+            status
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|status| (100..=599).contains(status))
+                .ok_or_else(|| format!("Invalid status code {status} in {value}"))
+        };
+        let mut ranges = Vec::new();
+        for part in value.split(',').filter(|part| !part.trim().is_empty()) {
+            let range = match part.split_once('-') {
+                Some((start, end)) => parse_status(start)?..=parse_status(end)?,
+                None => {
+                    let status = parse_status(part)?;
+                    status..=status
+                }
+            };
+            if range.is_empty() {
+                return Err(format!("Invalid status range {part} in {value}"));
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err("No status codes given".to_owned());
+        }
+        Ok(StatusRanges(ranges))
+    }
+}
+
+impl TryFrom<String> for StatusRanges {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Probes the URLs of the given ingresses and records their health.
+/// If the primary URL of an ingress is down but its secondary URL is up, the secondary URL is used instead.
+pub async fn check_ingresses(
+    config: &HealthCheck,
+    ingresses: impl Iterator<Item = &mut IngressInfo>,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::error!("Could not create client for health checks: {err}");
+            return;
+        }
+    };
+
+    stream::iter(ingresses)
+        .for_each_concurrent(CONCURRENT_CHECKS, |ingress| {
+            check_ingress(&client, config, ingress)
+        })
+        .await;
+}
+
+/// Orders the ingresses of every cluster by their health (down, unknown, up).
+/// The sort is stable so ingresses with the same health keep their order.
+pub fn sort_by_health(collection: &mut IngressCollection) {
+    for cluster in collection
+        .iter_mut()
+        .flat_map(|group| group.clusters.iter_mut())
+    {
+        cluster
+            .ingresses
+            .sort_by_key(|ingress| ingress.health.sort_rank());
+    }
+}
+
+async fn check_ingress(client: &reqwest::Client, config: &HealthCheck, ingress: &mut IngressInfo) {
+    // Settings from annotations take precedence over the global defaults
+    let method = ingress.health_method.unwrap_or(config.method);
+    let expect = ingress.health_expect.as_ref().or(config.expect.as_ref());
+    ingress.health = probe(client, method, expect, &ingress.url).await;
+    if ingress.health == HealthStatus::Down
+        && let Some(secondary_url) = ingress.secondary_url.as_ref()
+        && probe(client, method, expect, secondary_url).await == HealthStatus::Up
+    {
+        tracing::debug!(
+            "Primary url {} is down, using secondary url {secondary_url}",
+            ingress.url
+        );
+        ingress.url = secondary_url.clone();
+        ingress.health = HealthStatus::Up;
+        ingress.failed_over = true;
+    }
+}
+
+/// Without expected status codes every response except a server error counts as up
+async fn probe(
+    client: &reqwest::Client,
+    method: HealthMethod,
+    expect: Option<&StatusRanges>,
+    url: &str,
+) -> HealthStatus {
+    let request = match method {
+        HealthMethod::Get => client.get(url),
+        HealthMethod::Head => client.head(url),
+    };
+    match request.send().await {
+        Ok(response) => {
+            let status = response.status();
+            let up = match expect {
+                Some(expect) => expect.contains(status.as_u16()),
+                None => !status.is_server_error(),
+            };
+            if up {
+                HealthStatus::Up
+            } else {
+                tracing::debug!("Health check of {url} returned unexpected status {status}");
+                HealthStatus::Down
+            }
+        }
+        Err(err) => {
+            tracing::debug!("Health check of {url} failed: {err}");
+            HealthStatus::Down
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::StatusCode, routing::get};
+
+    use super::*;
+    use crate::collector::{ClusterInfo, GroupInfo};
+
+    /// Serves `/up` with 200 and `/down` with 503, returns the base URL
+    async fn serve() -> String {
+        let app = Router::new()
+            .route("/up", get(|| async { StatusCode::OK }))
+            .route("/down", get(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn health_check() -> HealthCheck {
+        HealthCheck {
+            enabled: true,
+            timeout_seconds: 5,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_secondary_url() {
+        let base = serve().await;
+        let mut ingress = IngressInfo {
+            url: format!("{base}/down"),
+            secondary_url: Some(format!("{base}/up")),
+            ..Default::default()
+        };
+        check_ingresses(&health_check(), std::iter::once(&mut ingress)).await;
+        assert_eq!(ingress.health, HealthStatus::Up);
+        assert_eq!(ingress.url, format!("{base}/up"));
+        assert!(ingress.failed_over);
+
+        let mut ingress = IngressInfo {
+            url: format!("{base}/down"),
+            secondary_url: Some(format!("{base}/down")),
+            ..Default::default()
+        };
+        check_ingresses(&health_check(), std::iter::once(&mut ingress)).await;
+        assert_eq!(ingress.health, HealthStatus::Down);
+        assert!(!ingress.failed_over);
+    }
+
+    #[test]
+    fn parses_status_ranges() {
+        let ranges: StatusRanges = "200, 204,301-399".parse().unwrap();
+        assert!(ranges.contains(200));
+        assert!(ranges.contains(204));
+        assert!(ranges.contains(301));
+        assert!(ranges.contains(399));
+        assert!(!ranges.contains(201));
+        assert!(!ranges.contains(404));
+    }
+
+    #[test]
+    fn rejects_invalid_status_ranges() {
+        for value in ["", "abc", "200-", "99", "600", "399-301"] {
+            assert!(value.parse::<StatusRanges>().is_err(), "{value}");
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_expected_status_codes() {
+        let base = serve().await;
+        let mut ingress = IngressInfo {
+            url: format!("{base}/down"),
+            health_expect: Some("503".parse().unwrap()),
+            ..Default::default()
+        };
+        check_ingresses(&health_check(), std::iter::once(&mut ingress)).await;
+        assert_eq!(ingress.health, HealthStatus::Up);
+
+        let mut ingress = IngressInfo {
+            url: format!("{base}/up"),
+            health_method: Some(HealthMethod::Head),
+            health_expect: Some("204".parse().unwrap()),
+            ..Default::default()
+        };
+        check_ingresses(&health_check(), std::iter::once(&mut ingress)).await;
+        assert_eq!(ingress.health, HealthStatus::Down);
+    }
+
+    #[test]
+    fn sorts_ingresses_by_health() {
+        let ingress = |name: &str, health| IngressInfo {
+            name: name.to_owned(),
+            health,
+            ..Default::default()
+        };
+        let mut collection = vec![GroupInfo {
+            name: "local".to_owned(),
+            description: String::new(),
+            clusters: vec![ClusterInfo {
+                name: "local".to_owned(),
+                description: String::new(),
+                ingresses: vec![
+                    ingress("a", HealthStatus::Up),
+                    ingress("b", HealthStatus::Unknown),
+                    ingress("c", HealthStatus::Down),
+                    ingress("d", HealthStatus::Up),
+                    ingress("e", HealthStatus::Down),
+                ],
+                truncated: false,
+                hidden_count: 0,
+                error: None,
+                gateways: Vec::new(),
+                links: Vec::new(),
+                last_updated: Default::default(),
+                stale: false,
+            }],
+            links: Vec::new(),
+        }];
+        sort_by_health(&mut collection);
+        let names: Vec<_> = collection[0].clusters[0]
+            .ingresses
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["c", "e", "b", "a", "d"]);
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// Formatting rules for numbers and dates of a locale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub tag: String,
+    group_separator: &'static str,
+    decimal_separator: &'static str,
+    date_format: &'static str,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::try_from("en".to_owned()).expect("en is a supported locale")
+    }
+}
+
+impl<'de> Deserialize<'de> for Locale {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Locale::try_from(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = String;
+
+    /// Parses a locale tag like `de` or `en-GB`
+    fn try_from(tag: String) -> Result<Self, Self::Error> {
+        let normalized = tag.replace('_', "-").to_lowercase();
+        let (language, region) = match normalized.split_once('-') {
+            Some((language, region)) => (language, Some(region)),
+            None => (normalized.as_str(), None),
+        };
+        let (group_separator, decimal_separator, date_format) = match (language, region) {
+            ("en", Some("gb" | "ie" | "au" | "nz")) => (",", ".", "%d/%m/%Y %H:%M"),
+            ("en", None | Some("us" | "ca")) => (",", ".", "%m/%d/%Y %I:%M %p"),
+            ("de", Some("ch")) => ("’", ".", "%d.%m.%Y %H:%M"),
+            ("de", None | Some("de" | "at")) => (".", ",", "%d.%m.%Y %H:%M"),
+            // French uses a narrow no-break space to group digits
+            ("fr", None | Some("fr" | "be" | "ca" | "ch")) => ("\u{202f}", ",", "%d/%m/%Y %H:%M"),
+            ("es", None | Some("es")) => (".", ",", "%d/%m/%Y %H:%M"),
+            ("it", None | Some("it")) => (".", ",", "%d/%m/%Y %H:%M"),
+            ("nl", None | Some("nl" | "be")) => (".", ",", "%d-%m-%Y %H:%M"),
+            ("pt", None | Some("pt" | "br")) => (".", ",", "%d/%m/%Y %H:%M"),
+            ("ja", None | Some("jp")) => (",", ".", "%Y/%m/%d %H:%M"),
+            _ => return Err(format!("Unsupported locale {tag}")),
+        };
+        Ok(Locale {
+            tag,
+            group_separator,
+            decimal_separator,
+            date_format,
+        })
+    }
+}
+
+impl Locale {
+    pub fn format_number(&self, number: f64) -> String {
+        let formatted = if number.fract() == 0.0 {
+            format!("{number:.0}")
+        } else {
+            format!("{number:.2}")
+        };
+        let (sign, formatted) = match formatted.strip_prefix('-') {
+            Some(formatted) => ("-", formatted),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted, None),
+        };
+        let mut result = sign.to_owned();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                result.push_str(self.group_separator);
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            result.push_str(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    pub fn format_datetime(&self, datetime: &DateTime<Utc>) -> String {
+        datetime.format(self.date_format).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(tag: &str) -> Locale {
+        Locale::try_from(tag.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(locale("en").format_number(1234567.0), "1,234,567");
+        assert_eq!(locale("de").format_number(1234567.0), "1.234.567");
+        assert_eq!(locale("en").format_number(-1234.5), "-1,234.50");
+        assert_eq!(locale("de").format_number(-1234.5), "-1.234,50");
+        assert_eq!(locale("de").format_number(123.0), "123");
+    }
+
+    #[test]
+    fn formats_dates() {
+        let datetime = DateTime::parse_from_rfc3339("2024-05-01T15:30:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            locale("en-US").format_datetime(&datetime),
+            "05/01/2024 03:30 PM"
+        );
+        assert_eq!(locale("de").format_datetime(&datetime), "01.05.2024 15:30");
+    }
+
+    #[test]
+    fn rejects_unsupported_locales() {
+        assert!(Locale::try_from("xx".to_owned()).is_err());
+    }
+}
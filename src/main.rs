@@ -1,7 +1,26 @@
 pub mod api;
+pub mod cli;
 pub mod collector;
 pub mod config;
+pub mod discovery;
 pub mod errors;
+pub mod health;
+pub mod locale;
+pub mod metrics;
+pub mod openapi;
+pub mod reload;
+pub mod resource;
+pub mod retry;
+pub mod routes;
+pub mod sanitize;
+pub mod schema;
+pub mod status;
+pub mod telemetry;
+pub mod token;
+pub mod toml;
+pub mod urls;
+pub mod watch;
+pub mod websocket;
 
 // Avoid musl's default allocator due to lackluster performance
 // https://nickb.dev/blog/default-musl-allocator-considered-harmful-to-performance
@@ -11,26 +30,39 @@ static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 #[tokio::main]
 async fn main() {
-    init_logging();
-    let config = config::read_config();
-    let info = collector::start_collector(config).await.unwrap();
-    api::api(info).await;
+    let code = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => {
+            init_logging(command.logs_to_stderr());
+            cli::run(command).await
+        }
+        Err(err) => cli::usage_error(&err),
+    };
+    std::process::exit(code);
 }
 
-fn init_logging() {
-    use tracing_subscriber::{EnvFilter, prelude::*};
+fn init_logging(to_stderr: bool) {
+    use tracing_subscriber::{EnvFilter, fmt::writer::BoxMakeWriter, prelude::*};
     let filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .unwrap();
 
-    let subscriber = tracing_subscriber::registry().with(filter);
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(telemetry::layer());
 
+    let writer = if to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     let log_mode = std::env::var("LOGGING_MODE").unwrap_or_else(|_| "plain".to_string());
     if log_mode.to_lowercase() == "json" {
         subscriber
-            .with(tracing_subscriber::fmt::layer().json())
+            .with(tracing_subscriber::fmt::layer().json().with_writer(writer))
             .init();
     } else {
-        subscriber.with(tracing_subscriber::fmt::layer()).init();
+        subscriber
+            .with(tracing_subscriber::fmt::layer().with_writer(writer))
+            .init();
     }
 }
@@ -0,0 +1,56 @@
+//! Counters exposed in the Prometheus text format on `/metrics`
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    const fn new(name: &'static str, help: &'static str) -> Self {
+        Counter {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub static PAGE_CACHE_HITS: Counter = Counter::new(
+    "landingpage_page_cache_hits_total",
+    "Requests answered from the rendered page cache",
+);
+pub static PAGE_CACHE_MISSES: Counter = Counter::new(
+    "landingpage_page_cache_misses_total",
+    "Requests that had to render the page although the cache is enabled",
+);
+pub static TEMPLATE_RENDERS: Counter = Counter::new(
+    "landingpage_template_renders_total",
+    "Number of times the template was rendered",
+);
+
+const COUNTERS: &[&Counter] = &[&PAGE_CACHE_HITS, &PAGE_CACHE_MISSES, &TEMPLATE_RENDERS];
+
+/// Renders all counters in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut output = String::new();
+    for counter in COUNTERS {
+        output.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n",
+            name = counter.name,
+            help = counter.help,
+            value = counter.get(),
+        ));
+    }
+    output
+}
@@ -0,0 +1,318 @@
+//! OpenAPI description of the JSON API, served as `/openapi.json`. The schemas mirror the
+//! serialized form of the types in `collector`, keep them in sync when changing those.
+
+use serde_json::{Value, json};
+
+/// Page that renders `/openapi.json` with Swagger UI loaded from a CDN
+pub const SWAGGER_UI_PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>Landingpage API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        window.ui = SwaggerUIBundle({ url: "./openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>
+"##;
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn array_of(name: &str) -> Value {
+    json!({ "type": "array", "items": schema_ref(name) })
+}
+
+fn nullable(type_: &str) -> Value {
+    json!({ "type": [type_, "null"] })
+}
+
+fn string_map() -> Value {
+    json!({ "type": "object", "additionalProperties": { "type": "string" } })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+fn pretty_parameter() -> Value {
+    json!({
+        "name": "pretty",
+        "in": "query",
+        "description": "Indent the JSON",
+        "schema": { "type": "boolean", "default": false },
+    })
+}
+
+fn query_parameter(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn not_collected_response() -> Value {
+    json!({
+        "description": "The clusters have not been collected yet",
+        "content": json_content(schema_ref("Error")),
+    })
+}
+
+fn paths() -> Value {
+    json!({
+        "/api/v1/clusters": {
+            "get": {
+                "summary": "The complete collection, the groups with their clusters and ingresses",
+                "operationId": "getClusters",
+                "parameters": [pretty_parameter()],
+                "responses": {
+                    "200": {
+                        "description": "The collected groups",
+                        "content": json_content(array_of("Group")),
+                    },
+                    "503": not_collected_response(),
+                },
+            },
+        },
+        "/api/v1/ingresses": {
+            "get": {
+                "summary": "All ingresses as flat list, filtered by the parameters",
+                "operationId": "getIngresses",
+                "parameters": [
+                    query_parameter("group", "Name of the group"),
+                    query_parameter("cluster", "Name of the cluster"),
+                    query_parameter("q", "Text searched case-insensitively in name, description and URL"),
+                    query_parameter("label", "Comma-separated list of `key=value` or `key` the labels must match"),
+                    pretty_parameter(),
+                ],
+                "responses": {
+                    "200": {
+                        "description": "The matching ingresses",
+                        "content": json_content(array_of("FlatIngress")),
+                    },
+                    "503": not_collected_response(),
+                },
+            },
+        },
+        "/api/v1/events": {
+            "get": {
+                "summary": "Server-Sent Events: a `snapshot` event with all ingresses, then a `diff` event after every collection",
+                "operationId": "getEvents",
+                "responses": {
+                    "200": {
+                        "description": "Stream of events, the data of `snapshot` is a list of `FlatIngress`, the data of `diff` a `CollectionDiff`",
+                        "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                    },
+                },
+            },
+        },
+        "/api/events": {
+            "get": {
+                "summary": "Server-Sent Events: a `collection` event with all groups after every collection",
+                "operationId": "getCollectionEvents",
+                "responses": {
+                    "200": {
+                        "description": "Stream of events, the data is a list of `Group`",
+                        "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                    },
+                },
+            },
+        },
+        "/api/refresh": {
+            "post": {
+                "summary": "Collects all clusters immediately",
+                "description": "Only available if `REFRESH_TOKEN` or OIDC is configured",
+                "operationId": "refresh",
+                "security": [{}, { "refreshToken": [] }],
+                "responses": {
+                    "204": { "description": "The collection was updated" },
+                    "401": { "description": "The token is missing or wrong" },
+                    "503": { "description": "The collector is not running" },
+                },
+            },
+        },
+        "/health": {
+            "get": {
+                "summary": "Liveness of the service",
+                "operationId": "health",
+                "responses": {
+                    "200": {
+                        "description": "The service is running",
+                        "content": { "text/plain": { "schema": { "type": "string" } } },
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn schemas() -> Value {
+    json!({
+        "Group": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "clusters": array_of("Cluster"),
+                "links": array_of("Link"),
+            },
+            "required": ["name", "description", "clusters", "links"],
+        },
+        "Cluster": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "ingresses": array_of("Ingress"),
+                "truncated": { "type": "boolean", "description": "Ingresses were left out because of `maxIngressesPerCluster`" },
+                "hidden_count": { "type": "integer", "minimum": 0 },
+                "error": { "type": ["string", "null"], "description": "Set if the cluster could not or only partially be collected" },
+                "gateways": array_of("Gateway"),
+                "links": array_of("Link"),
+                "last_updated": { "type": "string", "format": "date-time" },
+                "stale": { "type": "boolean", "description": "The latest collection failed, the data is from an earlier one" },
+            },
+            "required": [
+                "name", "description", "ingresses", "truncated", "hidden_count", "error",
+                "gateways", "links", "last_updated", "stale",
+            ],
+        },
+        "Ingress": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "description": "Display name" },
+                "ingress_name": { "type": "string", "description": "Name of the Kubernetes object" },
+                "description": { "type": "string" },
+                "description_html": nullable("string"),
+                "url": { "type": "string" },
+                "secondary_url": nullable("string"),
+                "docs_url": nullable("string"),
+                "expires": { "type": ["string", "null"], "format": "date-time" },
+                "annotations": string_map(),
+                "labels": string_map(),
+                "backend": { "oneOf": [schema_ref("Backend"), { "type": "null" }] },
+                "health": { "type": "string", "enum": ["up", "down", "unknown"] },
+                "failed_over": { "type": "boolean", "description": "`url` is the secondary URL because the primary URL is down" },
+                "color": nullable("string"),
+                "category": nullable("string"),
+                "protected": { "type": "boolean" },
+                "protection": nullable("string"),
+                "sources": { "type": "array", "items": { "type": "string" } },
+                "stale": { "type": "boolean", "description": "Missing from the latest collection but within the grace period" },
+            },
+            "required": [
+                "name", "ingress_name", "description", "description_html", "url", "secondary_url",
+                "docs_url", "expires", "annotations", "labels", "backend", "health", "failed_over",
+                "color", "category", "protected", "protection", "sources", "stale",
+            ],
+        },
+        "FlatIngress": {
+            "allOf": [
+                schema_ref("Ingress"),
+                {
+                    "type": "object",
+                    "properties": {
+                        "group": { "type": "string" },
+                        "cluster": { "type": "string" },
+                    },
+                    "required": ["group", "cluster"],
+                },
+            ],
+        },
+        "CollectionDiff": {
+            "type": "object",
+            "properties": {
+                "added": array_of("FlatIngress"),
+                "removed": array_of("FlatIngress"),
+                "changed": array_of("FlatIngress"),
+            },
+            "required": ["added", "removed", "changed"],
+        },
+        "Backend": {
+            "oneOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": "service" },
+                        "name": { "type": "string" },
+                        "port": nullable("string"),
+                    },
+                    "required": ["type", "name", "port"],
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": { "const": "resource" },
+                        "kind": { "type": "string" },
+                        "name": { "type": "string" },
+                    },
+                    "required": ["type", "kind", "name"],
+                },
+            ],
+        },
+        "Link": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "description": { "type": "string" },
+                "url": { "type": "string" },
+                "icon": nullable("string"),
+            },
+            "required": ["name", "description", "url", "icon"],
+        },
+        "Gateway": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "namespace": { "type": "string" },
+                "class_name": nullable("string"),
+                "listeners": array_of("Listener"),
+            },
+            "required": ["name", "namespace", "class_name", "listeners"],
+        },
+        "Listener": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "hostname": nullable("string"),
+                "port": { "type": "integer", "minimum": 0 },
+                "protocol": { "type": "string" },
+                "tls": { "type": "boolean" },
+                "attached_routes": { "type": ["integer", "null"], "minimum": 0 },
+            },
+            "required": ["name", "hostname", "port", "protocol", "tls", "attached_routes"],
+        },
+        "Error": {
+            "type": "object",
+            "properties": { "error": { "type": "string" } },
+            "required": ["error"],
+        },
+    })
+}
+
+/// The OpenAPI 3.1 document of the API
+pub fn openapi_spec() -> Value {
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "K8s Landingpage",
+            "description": "Ingresses collected from the configured Kubernetes clusters",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths(),
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "refreshToken": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
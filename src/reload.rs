@@ -0,0 +1,54 @@
+//! Applies changes of the config files without a restart, e.g. when the ConfigMap they are mounted from was updated
+
+use std::time::Duration;
+
+use crate::{
+    collector::Collector,
+    config::{self, Config, ConfigFiles},
+};
+
+/// Starts polling the config files if `reloadConfigSeconds` is set. The files are compared by content,
+/// since ConfigMap volumes replace them through a symlink and the modification time is not reliable.
+/// Directories are listed again every time, so added and removed files are noticed as well.
+/// A changed config restarts the collector, an invalid one is logged and ignored.
+pub fn watch_config(config: &Config, collector: Collector) {
+    let Some(seconds) = config.global.as_ref().and_then(|g| g.reload_config_seconds) else {
+        return;
+    };
+    let path = config::config_path();
+    tokio::spawn(async move {
+        let mut current = ConfigFiles::read().ok().flatten();
+        let mut interval = tokio::time::interval(Duration::from_secs(seconds.max(1)));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let files = match ConfigFiles::read() {
+                Ok(Some(files)) => files,
+                Ok(None) => {
+                    tracing::warn!("Config file {path} does not exist anymore, not reloading");
+                    continue;
+                }
+                Err(err) => {
+                    tracing::warn!("Could not read config file {path} for reloading: {err}");
+                    continue;
+                }
+            };
+            if current.as_ref() == Some(&files) {
+                continue;
+            }
+            let result = files.parse();
+            current = Some(files);
+            match result {
+                Ok(config) => {
+                    tracing::info!("Config file {path} changed, restarting the collection");
+                    collector.restart(config);
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "Config file {path} changed but is invalid, keeping the previous config: {err}"
+                    )
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,116 @@
+//! Reads the config from the spec of a `LandingPage` custom resource instead of a file and applies
+//! every change of the spec without a restart. Whether the spec is valid is reported as a condition
+//! in the status of the resource.
+
+use std::time::Duration;
+
+use futures::{StreamExt, TryStreamExt};
+use kube::{
+    Client,
+    api::{DynamicObject, WatchEvent, WatchParams},
+};
+use serde_json::json;
+
+use crate::{
+    collector::Collector,
+    config::{self, Config, StatusResource},
+    errors::Result,
+    status::{self, CONFIG_VALID, Condition},
+};
+
+/// Wait time before a failed or closed watch is started again
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// Reads and validates the config from the spec of the resource, returns it together with the generation of the resource
+pub async fn read_config(resource: &StatusResource) -> Result<(Config, Option<i64>)> {
+    let api = status::landingpages(Client::try_default().await?, &resource.namespace);
+    let object = api.get(&resource.name).await?;
+    let config = apply_spec(resource, &object).await?;
+    Ok((config, object.metadata.generation))
+}
+
+/// Watches the resource and restarts the collector whenever its spec changed. An invalid spec
+/// is reported in the status and the previous config is kept.
+pub fn watch_config(resource: &StatusResource, generation: Option<i64>, collector: Collector) {
+    let resource = resource.clone();
+    tokio::spawn(async move {
+        // The generation read at startup is already applied
+        let mut applied = generation;
+        loop {
+            match watch_resource(&resource, &collector, &mut applied).await {
+                Ok(()) => tracing::debug!("Watch of the config resource ended, restarting"),
+                Err(err) => {
+                    tracing::warn!("Watch of the config resource failed, restarting: {err}")
+                }
+            }
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+async fn watch_resource(
+    resource: &StatusResource,
+    collector: &Collector,
+    applied: &mut Option<i64>,
+) -> Result<()> {
+    let api = status::landingpages(Client::try_default().await?, &resource.namespace);
+    let params = WatchParams::default().fields(&format!("metadata.name={}", resource.name));
+    // Watching from version 0 starts with the current state, which is skipped if already applied
+    let mut events = api.watch(&params, "0").await?.boxed();
+    while let Some(event) = events.try_next().await? {
+        match event {
+            WatchEvent::Added(object) | WatchEvent::Modified(object) => {
+                // The generation only changes with the spec, not with updates of the status
+                if object.metadata.generation == *applied {
+                    continue;
+                }
+                *applied = object.metadata.generation;
+                match apply_spec(resource, &object).await {
+                    Ok(config) => {
+                        tracing::info!(
+                            "Config resource {}/{} changed, restarting the collection",
+                            resource.namespace,
+                            resource.name
+                        );
+                        collector.restart(config);
+                    }
+                    Err(err) => tracing::error!(
+                        "Config resource {}/{} changed but is invalid, keeping the previous config: {err}",
+                        resource.namespace,
+                        resource.name
+                    ),
+                }
+            }
+            WatchEvent::Deleted(_) => tracing::warn!(
+                "Config resource {}/{} was deleted, keeping the previous config",
+                resource.namespace,
+                resource.name
+            ),
+            WatchEvent::Bookmark(_) => {}
+            WatchEvent::Error(err) => return Err(kube::Error::Api(err).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the spec and sets the condition about its validity
+async fn apply_spec(resource: &StatusResource, object: &DynamicObject) -> Result<Config> {
+    let spec = object
+        .data
+        .get("spec")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    match config::config_from_value(spec) {
+        Ok(mut config) => {
+            config.source_resource = Some(resource.clone());
+            status::update_condition(resource, Condition::new(CONFIG_VALID, true, "Applied", ""))
+                .await;
+            Ok(config)
+        }
+        Err(err) => {
+            let condition = Condition::new(CONFIG_VALID, false, "InvalidSpec", &err.to_string());
+            status::update_condition(resource, condition).await;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Retrying operations against remote clusters with exponential backoff and jitter
+
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{
+    config::{Config, Retry},
+    errors::{Error, Result},
+};
+
+/// Whether an error is likely to go away by itself, like an unreachable API server
+pub fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Kube(err) => is_transient_kube(err),
+        Error::Timeout(_) => true,
+        _ => false,
+    }
+}
+
+/// Server errors, throttling and connection problems are transient, other API errors (like 403) are not
+pub fn is_transient_kube(err: &kube::Error) -> bool {
+    match err {
+        kube::Error::Api(response) => response.code == 429 || response.code >= 500,
+        _ => true,
+    }
+}
+
+pub fn policy(config: &Config) -> Retry {
+    config
+        .global
+        .as_ref()
+        .map(|g| g.retry.clone())
+        .unwrap_or_default()
+}
+
+/// Runs the operation until it succeeds, fails with an error that is not transient or
+/// the attempts are used up. Failed attempts before the last one are only logged at debug level.
+pub async fn retry<T, E, F, Fut>(
+    policy: &Retry,
+    what: &str,
+    transient: impl Fn(&E) -> bool,
+    mut operation: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= policy.attempts || !transient(&err) => return Err(err),
+            Err(err) => err,
+        };
+        let delay = delay(policy, attempt);
+        tracing::debug!(
+            "Attempt {attempt} of {what} failed, retrying in {}ms: {err}",
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Exponential delay capped at the maximum, randomized between half and the full delay
+fn delay(policy: &Retry, attempt: u32) -> Duration {
+    let exponential = policy
+        .initial_delay_millis
+        .saturating_mul(2u64.saturating_pow(attempt - 1))
+        .min(policy.max_delay_millis);
+    let jittered = rand::thread_rng().gen_range(exponential / 2..=exponential);
+    Duration::from_millis(jittered)
+}
@@ -0,0 +1,1014 @@
+//! Entries from routing resources other than ingresses. Custom resources are read with the
+//! dynamic API so that the CRDs only need to be installed in clusters that use them.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+};
+
+use jsonpath_rust::JsonPath;
+use k8s_openapi::api::core::v1::{Node, Service};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams},
+};
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    collector::{BackendInfo, GatewayInfo, IngressSpec, ListenerInfo, ObjectSpecs},
+    config::{Config, CustomKind, ResourceKind},
+    errors::{Error, Result},
+};
+
+const GATEWAY_API_GROUP: &str = "gateway.networking.k8s.io";
+const TRAEFIK_GROUP: &str = "traefik.io";
+
+fn api_resource(group: &str, version: &str, kind: &str) -> ApiResource {
+    ApiResource::from_gvk(&GroupVersionKind::gvk(group, version, kind))
+}
+
+async fn list_objects(
+    client: Client,
+    namespace: Option<&str>,
+    resource: &ApiResource,
+    params: &ListParams,
+) -> Result<Vec<DynamicObject>> {
+    let api = if let Some(namespace) = namespace {
+        Api::<DynamicObject>::namespaced_with(client, namespace, resource)
+    } else {
+        Api::<DynamicObject>::all_with(client, resource)
+    };
+    Ok(api.list(params).await?.items)
+}
+
+/// Strings of an array field, missing or invalid fields are treated as empty
+fn strings<'a>(value: &'a Value, pointer: &str) -> impl Iterator<Item = &'a str> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+fn items<'a>(value: &'a Value, pointer: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Wildcard hostnames can not be linked to
+fn linkable_host(host: &str) -> bool {
+    !host.is_empty() && !host.starts_with('*')
+}
+
+/// Lists the objects of a kind other than `Ingress` and derives their entries
+pub(crate) async fn collect(
+    config: &Config,
+    kind: &ResourceKind,
+    client: Client,
+    namespace: Option<&str>,
+    params: &ListParams,
+    assume_tls: Option<bool>,
+) -> Result<Vec<ObjectSpecs>> {
+    match kind {
+        ResourceKind::Ingress | ResourceKind::Gateway => {
+            unreachable!("{kind:?} is not collected as entries")
+        }
+        ResourceKind::HttpRoute => {
+            http_routes(client, namespace, params, assume_tls.unwrap_or(true)).await
+        }
+        ResourceKind::Route => {
+            let resource = api_resource("route.openshift.io", "v1", "Route");
+            list_specs(client, namespace, &resource, params, |route| {
+                openshift_route_spec(route, assume_tls)
+                    .into_iter()
+                    .collect()
+            })
+            .await
+        }
+        ResourceKind::VirtualService => {
+            let resource = api_resource("networking.istio.io", "v1beta1", "VirtualService");
+            list_specs(client, namespace, &resource, params, |service| {
+                virtual_service_specs(service, assume_tls.unwrap_or(true))
+            })
+            .await
+        }
+        ResourceKind::IngressRoute => {
+            let resource = api_resource(TRAEFIK_GROUP, "v1alpha1", "IngressRoute");
+            list_specs(client, namespace, &resource, params, |route| {
+                traefik_route_specs(route, assume_tls, false)
+            })
+            .await
+        }
+        ResourceKind::IngressRouteTcp => {
+            let resource = api_resource(TRAEFIK_GROUP, "v1alpha1", "IngressRouteTCP");
+            list_specs(client, namespace, &resource, params, |route| {
+                traefik_route_specs(route, assume_tls, true)
+            })
+            .await
+        }
+        ResourceKind::HttpProxy => {
+            let resource = api_resource("projectcontour.io", "v1", "HTTPProxy");
+            list_specs(client, namespace, &resource, params, |proxy| {
+                http_proxy_specs(proxy, assume_tls)
+            })
+            .await
+        }
+        ResourceKind::KnativeService => {
+            let resource = api_resource("serving.knative.dev", "v1", "Service");
+            list_specs(client, namespace, &resource, params, |service| {
+                knative_service_spec(service, assume_tls)
+                    .into_iter()
+                    .collect()
+            })
+            .await
+        }
+        ResourceKind::Service => services(client, namespace, params, assume_tls).await,
+        ResourceKind::Mapping => {
+            let resource = api_resource("getambassador.io", "v3alpha1", "Mapping");
+            list_specs(client, namespace, &resource, params, |mapping| {
+                mapping_spec(mapping, assume_tls.unwrap_or(true))
+                    .into_iter()
+                    .collect()
+            })
+            .await
+        }
+        ResourceKind::Custom(name) => {
+            let Some(custom_kind) = config
+                .global
+                .as_ref()
+                .and_then(|g| g.custom_kinds.get(name))
+            else {
+                return Err(Error::InvalidConfig(format!("Unknown kind {name}")));
+            };
+            let (group, version) = custom_kind
+                .api_version
+                .split_once('/')
+                .unwrap_or(("", &custom_kind.api_version));
+            let resource = api_resource(group, version, &custom_kind.kind);
+            list_specs(client, namespace, &resource, params, |object| {
+                custom_specs(custom_kind, object, assume_tls)
+            })
+            .await
+        }
+    }
+}
+
+/// Lists the objects and converts each of them to its entries
+async fn list_specs(
+    client: Client,
+    namespace: Option<&str>,
+    resource: &ApiResource,
+    params: &ListParams,
+    convert: impl Fn(&DynamicObject) -> Vec<IngressSpec>,
+) -> Result<Vec<ObjectSpecs>> {
+    let objects = list_objects(client, namespace, resource, params).await?;
+    Ok(objects
+        .into_iter()
+        .map(|object| {
+            let specs = convert(&object);
+            (object.metadata, specs)
+        })
+        .collect())
+}
+
+/// A path of a route together with the backend it routes to
+struct RoutePath<'a> {
+    path: &'a str,
+    regex: bool,
+    backend: Option<BackendInfo>,
+}
+
+/// Adds the path unless it is already known, matches that only differ in headers or query parameters result in the same link
+fn add_path<'a>(
+    paths: &mut Vec<RoutePath<'a>>,
+    path: &'a str,
+    regex: bool,
+    backend: &Option<BackendInfo>,
+) {
+    if !paths.iter().any(|existing| existing.path == path) {
+        paths.push(RoutePath {
+            path,
+            regex,
+            backend: backend.clone(),
+        });
+    }
+}
+
+/// One entry for every combination of host and path
+fn host_path_specs<'a>(
+    metadata: &ObjectMeta,
+    hosts: impl Iterator<Item = &'a str>,
+    paths: &[RoutePath],
+    tls_used: bool,
+) -> Vec<IngressSpec> {
+    hosts
+        .flat_map(|host| {
+            paths.iter().map(move |path| IngressSpec {
+                path: Some(path.path.to_owned()),
+                path_regex: path.regex,
+                backend: path.backend.clone(),
+                ..IngressSpec::for_object(metadata, host.to_owned(), tls_used)
+            })
+        })
+        .collect()
+}
+
+/// Lists `HTTPRoute` objects. Routes without hostnames get the hostnames of the listeners of their gateways.
+async fn http_routes(
+    client: Client,
+    namespace: Option<&str>,
+    params: &ListParams,
+    tls_used: bool,
+) -> Result<Vec<ObjectSpecs>> {
+    let routes = list_objects(
+        client.clone(),
+        namespace,
+        &api_resource(GATEWAY_API_GROUP, "v1", "HTTPRoute"),
+        params,
+    )
+    .await?;
+    let parents: BTreeSet<(String, String)> = routes
+        .iter()
+        .filter(|route| strings(&route.data, "/spec/hostnames").next().is_none())
+        .flat_map(|route| {
+            let namespace = route.namespace().unwrap_or_default();
+            items(&route.data, "/spec/parentRefs")
+                .filter(|parent| {
+                    parent
+                        .get("kind")
+                        .and_then(Value::as_str)
+                        .unwrap_or("Gateway")
+                        == "Gateway"
+                })
+                .filter_map(move |parent| {
+                    let name = parent.get("name")?.as_str()?.to_owned();
+                    let namespace = parent
+                        .get("namespace")
+                        .and_then(Value::as_str)
+                        .unwrap_or(&namespace)
+                        .to_owned();
+                    Some((namespace, name))
+                })
+        })
+        .collect();
+    let resource = api_resource(GATEWAY_API_GROUP, "v1", "Gateway");
+    let mut gateways = HashMap::new();
+    for (namespace, name) in parents {
+        let api = Api::<DynamicObject>::namespaced_with(client.clone(), &namespace, &resource);
+        match api.get_opt(&name).await {
+            Ok(Some(gateway)) => {
+                gateways.insert((namespace, name), gateway);
+            }
+            Ok(None) => tracing::debug!("Gateway {namespace}/{name} does not exist"),
+            // Gateways in other namespaces might not be readable, their routes are skipped
+            Err(err) => tracing::warn!("Could not read gateway {namespace}/{name}: {err}"),
+        }
+    }
+    Ok(routes
+        .iter()
+        .map(|route| {
+            let specs = http_route_specs(route, &gateways, tls_used);
+            (route.metadata.clone(), specs)
+        })
+        .collect())
+}
+
+fn http_route_specs(
+    route: &DynamicObject,
+    gateways: &HashMap<(String, String), DynamicObject>,
+    tls_used: bool,
+) -> Vec<IngressSpec> {
+    let mut hosts: BTreeSet<&str> = strings(&route.data, "/spec/hostnames").collect();
+    if hosts.is_empty() {
+        let namespace = route.namespace().unwrap_or_default();
+        for parent in items(&route.data, "/spec/parentRefs") {
+            let Some(name) = parent.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let parent_namespace = parent
+                .get("namespace")
+                .and_then(Value::as_str)
+                .unwrap_or(&namespace);
+            let Some(gateway) = gateways.get(&(parent_namespace.to_owned(), name.to_owned()))
+            else {
+                continue;
+            };
+            let section = parent.get("sectionName").and_then(Value::as_str);
+            hosts.extend(
+                items(&gateway.data, "/spec/listeners")
+                    .filter(|listener| {
+                        section.is_none() || listener.get("name").and_then(Value::as_str) == section
+                    })
+                    .filter_map(|listener| listener.get("hostname")?.as_str()),
+            );
+        }
+    }
+    let mut paths = Vec::new();
+    for rule in items(&route.data, "/spec/rules") {
+        let backend = items(rule, "/backendRefs").next().and_then(backend_ref);
+        let mut matches: Vec<(&str, bool)> = items(rule, "/matches")
+            .map(|path_match| {
+                let path_type = path_match
+                    .pointer("/path/type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("PathPrefix");
+                let path = path_match
+                    .pointer("/path/value")
+                    .and_then(Value::as_str)
+                    .unwrap_or("/");
+                (path, path_type == "RegularExpression")
+            })
+            .collect();
+        // A rule without matches matches all requests
+        if matches.is_empty() {
+            matches.push(("/", false));
+        }
+        for (path, regex) in matches {
+            add_path(&mut paths, path, regex, &backend);
+        }
+    }
+    let hosts = hosts.into_iter().filter(|host| linkable_host(host));
+    host_path_specs(&route.metadata, hosts, &paths, tls_used)
+}
+
+/// Lists the gateways with their listeners
+pub(crate) async fn gateways(client: Client, namespace: Option<&str>) -> Result<Vec<GatewayInfo>> {
+    let resource = api_resource(GATEWAY_API_GROUP, "v1", "Gateway");
+    let mut gateways: Vec<GatewayInfo> =
+        list_objects(client, namespace, &resource, &ListParams::default())
+            .await?
+            .iter()
+            .map(gateway_info)
+            .collect();
+    gateways.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+    Ok(gateways)
+}
+
+fn gateway_info(gateway: &DynamicObject) -> GatewayInfo {
+    let listeners = items(&gateway.data, "/spec/listeners")
+        .filter_map(|listener| {
+            let name = listener.get("name")?.as_str()?.to_owned();
+            let protocol = listener
+                .get("protocol")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            let attached_routes = items(&gateway.data, "/status/listeners")
+                .find(|status| status.get("name").and_then(Value::as_str) == Some(&name))
+                .and_then(|status| status.get("attachedRoutes")?.as_u64());
+            Some(ListenerInfo {
+                hostname: listener
+                    .get("hostname")
+                    .and_then(Value::as_str)
+                    .map(str::to_owned),
+                port: listener
+                    .get("port")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default(),
+                tls: matches!(protocol.as_str(), "HTTPS" | "TLS"),
+                protocol,
+                attached_routes,
+                name,
+            })
+        })
+        .collect();
+    GatewayInfo {
+        name: gateway.name_any(),
+        namespace: gateway.namespace().unwrap_or_default(),
+        class_name: gateway
+            .data
+            .pointer("/spec/gatewayClassName")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        listeners,
+    }
+}
+
+/// Unless the cluster overrides it, routes with a TLS termination are linked with https
+fn openshift_route_spec(route: &DynamicObject, assume_tls: Option<bool>) -> Option<IngressSpec> {
+    let host = route.data.pointer("/spec/host")?.as_str()?;
+    if !linkable_host(host) {
+        return None;
+    }
+    let tls_used = assume_tls.unwrap_or_else(|| {
+        route
+            .data
+            .pointer("/spec/tls/termination")
+            .and_then(Value::as_str)
+            .is_some()
+    });
+    let backend = route.data.pointer("/spec/to").and_then(|to| {
+        let name = to.get("name")?.as_str()?.to_owned();
+        let port = route
+            .data
+            .pointer("/spec/port/targetPort")
+            .and_then(|port| match port {
+                Value::String(name) => Some(name.clone()),
+                Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            });
+        match to.get("kind").and_then(Value::as_str) {
+            None | Some("Service") => Some(BackendInfo::Service { name, port }),
+            Some(kind) => Some(BackendInfo::Resource {
+                kind: kind.to_owned(),
+                name,
+            }),
+        }
+    });
+    Some(IngressSpec {
+        path: route
+            .data
+            .pointer("/spec/path")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        backend,
+        ..IngressSpec::for_object(&route.metadata, host.to_owned(), tls_used)
+    })
+}
+
+/// Only virtual services bound to a gateway are shown, hosts only reachable within the mesh are skipped
+fn virtual_service_specs(service: &DynamicObject, tls_used: bool) -> Vec<IngressSpec> {
+    // Without gateways a virtual service only applies to sidecars within the mesh
+    if strings(&service.data, "/spec/gateways").all(|gateway| gateway == "mesh") {
+        return Vec::new();
+    }
+    let mut paths = Vec::new();
+    for route in items(&service.data, "/spec/http") {
+        let backend = items(route, "/route")
+            .next()
+            .and_then(|destination| destination.get("destination"))
+            .and_then(|destination| {
+                // The host is a service name, possibly fully qualified like `reviews.prod.svc.cluster.local`
+                let host = destination.get("host")?.as_str()?;
+                Some(BackendInfo::Service {
+                    name: host.split('.').next()?.to_owned(),
+                    port: destination
+                        .pointer("/port/number")
+                        .and_then(Value::as_u64)
+                        .map(|port| port.to_string()),
+                })
+            });
+        let mut matches: Vec<(&str, bool)> = items(route, "/match")
+            .map(|route_match| {
+                let uri = route_match.get("uri");
+                let string = |field| uri.and_then(|uri| uri.get(field)?.as_str());
+                if let Some(path) = string("prefix").or_else(|| string("exact")) {
+                    (path, false)
+                } else if let Some(regex) = string("regex") {
+                    (regex, true)
+                } else {
+                    // Matches without uri apply to all paths
+                    ("/", false)
+                }
+            })
+            .collect();
+        // Routes without matches apply to all paths
+        if matches.is_empty() {
+            matches.push(("/", false));
+        }
+        for (path, regex) in matches {
+            add_path(&mut paths, path, regex, &backend);
+        }
+    }
+    // Short names like `reviews` only resolve within the mesh
+    let hosts = strings(&service.data, "/spec/hosts")
+        .filter(|host| linkable_host(host) && host.contains('.') && !host.ends_with(".local"));
+    host_path_specs(&service.metadata, hosts, &paths, tls_used)
+}
+
+/// Reads the hosts and paths from the `Host`/`HostSNI` and `Path`/`PathPrefix` matchers of the rules.
+/// Unless the cluster overrides it, routes with TLS configured are linked with https.
+fn traefik_route_specs(
+    route: &DynamicObject,
+    assume_tls: Option<bool>,
+    tcp: bool,
+) -> Vec<IngressSpec> {
+    let tls_used = assume_tls.unwrap_or_else(|| route.data.pointer("/spec/tls").is_some());
+    let mut result = Vec::new();
+    for rule in items(&route.data, "/spec/routes") {
+        let Some(rule_match) = rule.get("match").and_then(Value::as_str) else {
+            continue;
+        };
+        let backend = items(rule, "/services").next().and_then(|service| {
+            let name = service.get("name")?.as_str()?.to_owned();
+            let port = service.get("port").and_then(|port| match port {
+                Value::String(name) => Some(name.clone()),
+                Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            });
+            match service.get("kind").and_then(Value::as_str) {
+                None | Some("Service") => Some(BackendInfo::Service { name, port }),
+                Some(kind) => Some(BackendInfo::Resource {
+                    kind: kind.to_owned(),
+                    name,
+                }),
+            }
+        });
+        let hosts = matcher_arguments(rule_match, if tcp { "HostSNI" } else { "Host" });
+        let mut paths = Vec::new();
+        if !tcp {
+            for path in matcher_arguments(rule_match, "Path")
+                .into_iter()
+                .chain(matcher_arguments(rule_match, "PathPrefix"))
+            {
+                add_path(&mut paths, path, false, &backend);
+            }
+            for path in matcher_arguments(rule_match, "PathRegexp") {
+                add_path(&mut paths, path, true, &backend);
+            }
+        }
+        if paths.is_empty() {
+            add_path(&mut paths, "/", false, &backend);
+        }
+        let hosts = hosts.into_iter().filter(|host| linkable_host(host));
+        for spec in host_path_specs(&route.metadata, hosts, &paths, tls_used) {
+            if !result.iter().any(|existing: &IngressSpec| {
+                existing.host == spec.host && existing.path == spec.path
+            }) {
+                result.push(spec);
+            }
+        }
+    }
+    result
+}
+
+/// Arguments of all occurrences of a matcher in a Traefik rule, e.g. the hosts of ``Host(`a.example.com`, `b.example.com`)``
+fn matcher_arguments<'a>(rule: &'a str, matcher: &str) -> Vec<&'a str> {
+    let mut result = Vec::new();
+    let pattern = format!("{matcher}(");
+    let mut rest = rule;
+    while let Some(start) = rest.find(&pattern) {
+        // Only whole matcher names, `Path(` must not match within `ClientPath(`
+        let whole = rest[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_ascii_alphanumeric());
+        rest = &rest[start + pattern.len()..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        if whole {
+            result.extend(
+                rest[..end]
+                    .split(',')
+                    .map(|argument| argument.trim().trim_matches(['`', '"']))
+                    .filter(|argument| !argument.is_empty()),
+            );
+        }
+        rest = &rest[end + 1..];
+    }
+    result
+}
+
+/// Only root proxies (with a virtual host) are shown, with the paths of their routes and includes.
+/// Unless the cluster overrides it, proxies with TLS configured are linked with https.
+fn http_proxy_specs(proxy: &DynamicObject, assume_tls: Option<bool>) -> Vec<IngressSpec> {
+    let Some(host) = proxy
+        .data
+        .pointer("/spec/virtualhost/fqdn")
+        .and_then(Value::as_str)
+        .filter(|host| linkable_host(host))
+    else {
+        return Vec::new();
+    };
+    let tls_used =
+        assume_tls.unwrap_or_else(|| proxy.data.pointer("/spec/virtualhost/tls").is_some());
+    let mut paths = Vec::new();
+    let routes = items(&proxy.data, "/spec/routes").map(|route| {
+        let backend = items(route, "/services").next().and_then(|service| {
+            Some(BackendInfo::Service {
+                name: service.get("name")?.as_str()?.to_owned(),
+                port: service
+                    .get("port")
+                    .and_then(Value::as_u64)
+                    .map(|port| port.to_string()),
+            })
+        });
+        (route, backend)
+    });
+    // Included proxies are shown as paths of the root proxy, their backends are not known here
+    let includes = items(&proxy.data, "/spec/includes").map(|include| (include, None));
+    for (entry, backend) in routes.chain(includes) {
+        let prefixes: Vec<&str> = items(entry, "/conditions")
+            .filter_map(|condition| {
+                condition
+                    .get("prefix")
+                    .or_else(|| condition.get("exact"))?
+                    .as_str()
+            })
+            .collect();
+        if prefixes.is_empty() {
+            add_path(&mut paths, "/", false, &backend);
+        }
+        for prefix in prefixes {
+            add_path(&mut paths, prefix, false, &backend);
+        }
+    }
+    if paths.is_empty() {
+        add_path(&mut paths, "/", false, &None);
+    }
+    host_path_specs(&proxy.metadata, std::iter::once(host), &paths, tls_used)
+}
+
+/// The URL of a Knative service is taken from its status, services that are only reachable within the cluster are skipped
+fn knative_service_spec(service: &DynamicObject, assume_tls: Option<bool>) -> Option<IngressSpec> {
+    let url = service.data.pointer("/status/url")?.as_str()?;
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(err) => {
+            tracing::warn!(
+                "Ignoring invalid URL {url} of knative service {}/{}: {err}",
+                service.namespace().unwrap_or_default(),
+                service.name_any()
+            );
+            return None;
+        }
+    };
+    let host = url.host_str()?;
+    if !linkable_host(host) || host.ends_with(".local") {
+        return None;
+    }
+    let tls_used = assume_tls.unwrap_or(url.scheme() == "https");
+    Some(IngressSpec {
+        path: Some(url.path().to_owned()),
+        ..IngressSpec::for_object(&service.metadata, host.to_owned(), tls_used)
+    })
+}
+
+/// Lists services that are reachable from outside the cluster. Node ports are linked with the external addresses of the nodes.
+async fn services(
+    client: Client,
+    namespace: Option<&str>,
+    params: &ListParams,
+    assume_tls: Option<bool>,
+) -> Result<Vec<ObjectSpecs>> {
+    let api = if let Some(namespace) = namespace {
+        Api::<Service>::namespaced(client.clone(), namespace)
+    } else {
+        Api::<Service>::all(client.clone())
+    };
+    let services = api.list(params).await?.items;
+    let node_port_services = services.iter().any(|service| {
+        service.spec.as_ref().and_then(|spec| spec.type_.as_deref()) == Some("NodePort")
+    });
+    let node_addresses = if node_port_services {
+        node_addresses(client).await?
+    } else {
+        Vec::new()
+    };
+    Ok(services
+        .into_iter()
+        .map(|service| {
+            let specs = service_specs(&service, &node_addresses, assume_tls);
+            (service.metadata, specs)
+        })
+        .collect())
+}
+
+/// External addresses of all nodes
+async fn node_addresses(client: Client) -> Result<Vec<String>> {
+    let nodes = Api::<Node>::all(client)
+        .list(&ListParams::default())
+        .await?;
+    let mut addresses: Vec<String> = nodes
+        .items
+        .into_iter()
+        .flat_map(|node| node.status.and_then(|status| status.addresses))
+        .flatten()
+        .filter(|address| matches!(address.type_.as_str(), "ExternalIP" | "ExternalDNS"))
+        .map(|address| address.address)
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    Ok(addresses)
+}
+
+/// One entry per external address and TCP port. Unless the cluster overrides it, only port 443 and 8443 are linked with https.
+fn service_specs(
+    service: &Service,
+    node_addresses: &[String],
+    assume_tls: Option<bool>,
+) -> Vec<IngressSpec> {
+    let Some(spec) = service.spec.as_ref() else {
+        return Vec::new();
+    };
+    let node_port = spec.type_.as_deref() == Some("NodePort");
+    let mut addresses: Vec<&str> = spec
+        .external_ips
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    if spec.type_.as_deref() == Some("LoadBalancer") {
+        addresses.extend(
+            service
+                .status
+                .iter()
+                .filter_map(|status| status.load_balancer.as_ref())
+                .flat_map(|load_balancer| load_balancer.ingress.iter().flatten())
+                .filter_map(|ingress| ingress.hostname.as_deref().or(ingress.ip.as_deref())),
+        );
+    }
+    let mut result = Vec::new();
+    for port in spec.ports.iter().flatten() {
+        if port
+            .protocol
+            .as_deref()
+            .is_some_and(|protocol| protocol != "TCP")
+        {
+            continue;
+        }
+        let backend = Some(BackendInfo::Service {
+            name: service.name_any(),
+            port: Some(port.name.clone().unwrap_or_else(|| port.port.to_string())),
+        });
+        let mut targets: Vec<(&str, i32)> = addresses
+            .iter()
+            .map(|address| (*address, port.port))
+            .collect();
+        if node_port && let Some(node_port) = port.node_port {
+            targets.extend(
+                node_addresses
+                    .iter()
+                    .map(|address| (address.as_str(), node_port)),
+            );
+        }
+        for (address, port_number) in targets {
+            let tls_used = assume_tls.unwrap_or(matches!(port_number, 443 | 8443));
+            let default_port = if tls_used { 443 } else { 80 };
+            // IPv6 addresses have to be enclosed in brackets within URLs
+            let address = if address.contains(':') {
+                format!("[{address}]")
+            } else {
+                address.to_owned()
+            };
+            let host = if port_number == default_port {
+                address
+            } else {
+                format!("{address}:{port_number}")
+            };
+            result.push(IngressSpec {
+                backend: backend.clone(),
+                ..IngressSpec::for_object(&service.metadata, host, tls_used)
+            });
+        }
+    }
+    result
+}
+
+/// The URL of a mapping is its hostname with its prefix, mappings for all hosts are skipped
+fn mapping_spec(mapping: &DynamicObject, tls_used: bool) -> Option<IngressSpec> {
+    let spec = mapping.data.get("spec")?;
+    // `host` is the older name of the field
+    let host = spec
+        .get("hostname")
+        .or_else(|| spec.get("host"))?
+        .as_str()
+        .filter(|host| linkable_host(host))?;
+    // The service is given as `name`, `name.namespace` or `name:port`, possibly with a scheme
+    let backend = spec.get("service").and_then(Value::as_str).map(|service| {
+        let service = service
+            .split_once("://")
+            .map_or(service, |(_, service)| service);
+        let (service, port) = match service.rsplit_once(':') {
+            Some((service, port)) => (service, Some(port.to_owned())),
+            None => (service, None),
+        };
+        BackendInfo::Service {
+            name: service.split('.').next().unwrap_or(service).to_owned(),
+            port,
+        }
+    });
+    Some(IngressSpec {
+        path: spec
+            .get("prefix")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        path_regex: spec
+            .get("prefix_regex")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        backend,
+        ..IngressSpec::for_object(&mapping.metadata, host.to_owned(), tls_used)
+    })
+}
+
+/// Values of a JSONPath expression, expressions are validated with the config
+fn json_path_values(expression: &str, object: &Value) -> Vec<String> {
+    let Ok(path) = JsonPath::<Value>::from_str(expression) else {
+        return Vec::new();
+    };
+    path.find_slice_ptr(object)
+        .into_iter()
+        .filter_map(|value| match &*value {
+            Value::String(value) => Some(value.clone()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        })
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Entries of an object of a custom kind, either one per URL or one per host
+fn custom_specs(
+    custom_kind: &CustomKind,
+    object: &DynamicObject,
+    assume_tls: Option<bool>,
+) -> Vec<IngressSpec> {
+    let Ok(value) = serde_json::to_value(object) else {
+        return Vec::new();
+    };
+    let first = |expression: &Option<String>| {
+        expression
+            .as_deref()
+            .and_then(|expression| json_path_values(expression, &value).into_iter().next())
+    };
+    let base = |host: String, tls_used: bool| IngressSpec {
+        display_name: first(&custom_kind.name),
+        description: first(&custom_kind.description),
+        ..IngressSpec::for_object(&object.metadata, host, tls_used)
+    };
+    if let Some(url) = custom_kind.url.as_deref() {
+        return json_path_values(url, &value)
+            .into_iter()
+            .filter_map(|url| {
+                let url = Url::parse(&url)
+                    .inspect_err(|err| {
+                        tracing::warn!(
+                            "Ignoring invalid URL {url} of {} {}/{}: {err}",
+                            custom_kind.kind,
+                            object.namespace().unwrap_or_default(),
+                            object.name_any()
+                        )
+                    })
+                    .ok()?;
+                let host = match url.port() {
+                    Some(port) => format!("{}:{port}", url.host_str()?),
+                    None => url.host_str()?.to_owned(),
+                };
+                let tls_used = assume_tls.unwrap_or(url.scheme() == "https");
+                Some(IngressSpec {
+                    path: Some(url.path().to_owned()),
+                    ..base(host, tls_used)
+                })
+            })
+            .collect();
+    }
+    let path = first(&custom_kind.path);
+    custom_kind
+        .host
+        .as_deref()
+        .map(|host| json_path_values(host, &value))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|host| linkable_host(host))
+        .map(|host| IngressSpec {
+            path: path.clone(),
+            ..base(host, assume_tls.unwrap_or(true))
+        })
+        .collect()
+}
+
+/// Backend of a Gateway API `backendRef`, which refers to a service unless another kind is given
+fn backend_ref(backend: &Value) -> Option<BackendInfo> {
+    let name = backend.get("name")?.as_str()?.to_owned();
+    match backend.get("kind").and_then(Value::as_str) {
+        None | Some("Service") => Some(BackendInfo::Service {
+            name,
+            port: backend
+                .get("port")
+                .and_then(Value::as_u64)
+                .map(|port| port.to_string()),
+        }),
+        Some(kind) => Some(BackendInfo::Resource {
+            kind: kind.to_owned(),
+            name,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_virtual_services_bound_to_gateways() {
+        let service: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "networking.istio.io/v1",
+            "kind": "VirtualService",
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": {
+                "gateways": ["istio-system/public"],
+                "hosts": ["reviews.example.com", "reviews", "reviews.default.svc.cluster.local"],
+                "http": [
+                    {
+                        "match": [{ "uri": { "prefix": "/api" } }, { "uri": { "regex": "/v[0-9]+" } }],
+                        "route": [{ "destination": { "host": "reviews.default.svc.cluster.local", "port": { "number": 9080 } } }],
+                    },
+                    { "route": [{ "destination": { "host": "frontend" } }] },
+                ],
+            },
+        }))
+        .unwrap();
+        let specs = virtual_service_specs(&service, true);
+        let paths: Vec<_> = specs
+            .iter()
+            .map(|spec| (spec.host.as_str(), spec.path.as_deref(), spec.path_regex))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("reviews.example.com", Some("/api"), false),
+                ("reviews.example.com", Some("/v[0-9]+"), true),
+                ("reviews.example.com", Some("/"), false),
+            ]
+        );
+        let Some(BackendInfo::Service { name, port }) = specs[0].backend.as_ref() else {
+            panic!("expected a service backend");
+        };
+        assert_eq!(name, "reviews");
+        assert_eq!(port.as_deref(), Some("9080"));
+    }
+
+    #[test]
+    fn skips_mesh_only_virtual_services() {
+        let service: DynamicObject = serde_json::from_value(serde_json::json!({
+            "apiVersion": "networking.istio.io/v1",
+            "kind": "VirtualService",
+            "metadata": { "name": "reviews", "namespace": "default" },
+            "spec": {
+                "hosts": ["reviews.example.com"],
+                "http": [{ "route": [{ "destination": { "host": "reviews" } }] }],
+            },
+        }))
+        .unwrap();
+        assert!(virtual_service_specs(&service, true).is_empty());
+    }
+
+    #[test]
+    fn reads_traefik_matcher_arguments() {
+        let rule = "Host(`a.example.com`, `b.example.com`) && (PathPrefix(`/api`) || Path(\"/health\")) && ClientIP(`10.0.0.0/8`)";
+        assert_eq!(
+            matcher_arguments(rule, "Host"),
+            ["a.example.com", "b.example.com"]
+        );
+        assert_eq!(matcher_arguments(rule, "PathPrefix"), ["/api"]);
+        assert_eq!(matcher_arguments(rule, "Path"), ["/health"]);
+        assert_eq!(
+            matcher_arguments("HostSNI(`*`)", "Host"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            matcher_arguments("Host(`a.example.com`", "Host"),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn links_external_service_ports() {
+        let service: Service = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app", "namespace": "default" },
+            "spec": {
+                "type": "LoadBalancer",
+                "ports": [
+                    { "name": "https", "port": 443, "protocol": "TCP" },
+                    { "port": 8080, "protocol": "TCP", "nodePort": 30080 },
+                    { "name": "dns", "port": 53, "protocol": "UDP" },
+                ],
+            },
+            "status": { "loadBalancer": { "ingress": [{ "ip": "2001:db8::1" }] } },
+        }))
+        .unwrap();
+        let hosts: Vec<_> = service_specs(&service, &["203.0.113.1".to_owned()], None)
+            .into_iter()
+            .map(|spec| (spec.host, spec.tls_used))
+            .collect();
+        assert_eq!(
+            hosts,
+            [
+                ("[2001:db8::1]".to_owned(), true),
+                ("[2001:db8::1]:8080".to_owned(), false),
+            ]
+        );
+
+        let node_port: Service = serde_json::from_value(serde_json::json!({
+            "metadata": { "name": "app", "namespace": "default" },
+            "spec": {
+                "type": "NodePort",
+                "ports": [{ "port": 8080, "protocol": "TCP", "nodePort": 30080 }],
+            },
+        }))
+        .unwrap();
+        let hosts: Vec<_> = service_specs(&node_port, &["203.0.113.1".to_owned()], Some(true))
+            .into_iter()
+            .map(|spec| (spec.host, spec.tls_used))
+            .collect();
+        assert_eq!(hosts, [("203.0.113.1:30080".to_owned(), true)]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Minimal HTML sanitizer for descriptions that may contain simple formatting.
+//! Everything that is not on the allowlist is removed, text is escaped.
+
+/// Tags that are kept, all attributes except `href` on links are removed
+const ALLOWED_TAGS: &[&str] = &["a", "b", "br", "code", "em", "i", "strong", "u"];
+/// Tags without closing tag
+const VOID_TAGS: &[&str] = &["br"];
+/// Tags that are removed together with their content
+const REMOVED_WITH_CONTENT: &[&str] = &["script", "style", "iframe", "object", "embed", "template"];
+/// Schemes allowed for the `href` of links
+const ALLOWED_LINK_SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attributes: &'a str,
+}
+
+/// Returns HTML that only contains allowed tags, which is safe to render without escaping
+pub fn sanitize_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut open_tags: Vec<String> = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find('<') {
+        escape_text(&rest[..start], &mut output);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, rest)| rest);
+            continue;
+        }
+        // Like browsers treat a `<` that does not start a tag as text
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            output.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            // Unterminated tag, treat as text
+            break;
+        };
+        let Some(tag) = parse_tag(&rest[1..end]) else {
+            // Doctypes and malformed tags are dropped
+            rest = &rest[end + 1..];
+            continue;
+        };
+        rest = &rest[end + 1..];
+        if !tag.closing && REMOVED_WITH_CONTENT.contains(&tag.name.as_str()) {
+            rest = skip_content(rest, &tag.name);
+            continue;
+        }
+        if !ALLOWED_TAGS.contains(&tag.name.as_str()) {
+            continue;
+        }
+        if tag.closing {
+            // Only close tags that are open, closing all tags opened after it
+            if let Some(index) = open_tags.iter().rposition(|open| *open == tag.name) {
+                for open in open_tags.drain(index..).rev() {
+                    output.push_str(&format!("</{open}>"));
+                }
+            }
+        } else if VOID_TAGS.contains(&tag.name.as_str()) {
+            output.push_str(&format!("<{}>", tag.name));
+        } else if tag.name == "a" {
+            match link_target(tag.attributes) {
+                Some(href) => {
+                    output.push_str("<a href=\"");
+                    escape_text(&href, &mut output);
+                    output.push_str("\" target=\"_blank\" rel=\"noopener noreferrer\">");
+                }
+                None => output.push_str("<a>"),
+            }
+            open_tags.push(tag.name);
+        } else {
+            output.push_str(&format!("<{}>", tag.name));
+            open_tags.push(tag.name);
+        }
+    }
+    escape_text(rest, &mut output);
+    for open in open_tags.into_iter().rev() {
+        output.push_str(&format!("</{open}>"));
+    }
+    output
+}
+
+fn parse_tag(content: &str) -> Option<Tag<'_>> {
+    let (closing, content) = match content.strip_prefix('/') {
+        Some(content) => (true, content),
+        None => (false, content),
+    };
+    let content = content.trim_end_matches('/');
+    let name_end = content
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(content.len());
+    if name_end == 0 {
+        return None;
+    }
+    Some(Tag {
+        name: content[..name_end].to_ascii_lowercase(),
+        closing,
+        attributes: &content[name_end..],
+    })
+}
+
+/// Skips everything up to and including the closing tag with the given name
+fn skip_content<'a>(input: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{name}");
+    match input.to_ascii_lowercase().find(&closing) {
+        Some(start) => match input[start..].find('>') {
+            Some(end) => &input[start + end + 1..],
+            None => "",
+        },
+        None => "",
+    }
+}
+
+/// Extracts the `href` attribute of a link if it uses an allowed scheme
+fn link_target(attributes: &str) -> Option<String> {
+    let lowercase = attributes.to_ascii_lowercase();
+    let start = lowercase.find("href")?;
+    let value = attributes[start + 4..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let href = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split_whitespace().next()?,
+    };
+    let href = decode_entities(href.trim());
+    let lowercase = href.to_ascii_lowercase();
+    ALLOWED_LINK_SCHEMES
+        .iter()
+        .any(|scheme| lowercase.starts_with(scheme))
+        .then_some(href)
+}
+
+/// Decodes the entities that could be used to hide a scheme
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&colon;", ":")
+        .replace("&#58;", ":")
+        .replace("&#x3a;", ":")
+        .replace("&#x3A;", ":")
+        .replace("&amp;", "&")
+}
+
+fn escape_text(text: &str, output: &mut String) {
+    for (index, c) in text.char_indices() {
+        match c {
+            '&' if is_entity(&text[index..]) => output.push('&'),
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            c => output.push(c),
+        }
+    }
+}
+
+/// Checks if the text starts with a character reference like `&amp;` or `&#169;`
+fn is_entity(text: &str) -> bool {
+    let Some(end) = text.find(';') else {
+        return false;
+    };
+    let name = &text[1..end];
+    !name.is_empty()
+        && name.len() <= 32
+        && match name.strip_prefix('#') {
+            Some(number) => match number.strip_prefix(['x', 'X']) {
+                Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+            },
+            None => name.chars().all(|c| c.is_ascii_alphanumeric()),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_allowed_tags_and_removes_scripts() {
+        assert_eq!(
+            sanitize_html("<b>Bold</b> text<script>alert(1)</script>"),
+            "<b>Bold</b> text"
+        );
+        assert_eq!(
+            sanitize_html("<B onclick=\"alert(1)\">Bold</B><div>block</div>"),
+            "<b>Bold</b>block"
+        );
+    }
+
+    #[test]
+    fn only_keeps_safe_links() {
+        assert_eq!(
+            sanitize_html("<a href=\"https://example.com\" onclick=\"x\">Docs</a>"),
+            "<a href=\"https://example.com\" target=\"_blank\" rel=\"noopener noreferrer\">Docs</a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"javascript&colon;alert(1)\">Docs</a>"),
+            "<a>Docs</a>"
+        );
+    }
+
+    #[test]
+    fn escapes_text_and_closes_open_tags() {
+        assert_eq!(sanitize_html("a < b & c"), "a &lt; b &amp; c");
+        assert_eq!(sanitize_html("&copy; <i>open"), "&copy; <i>open</i>");
+    }
+}
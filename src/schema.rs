@@ -0,0 +1,495 @@
+//! JSON Schema of the config, e.g. for completion and validation of config files in editors.
+//! The schema is derived from the `Deserialize` implementations of the config structs by tracing
+//! them with a deserializer that records every type it is asked for, so it always matches the structs.
+//! Struct fields without default are required, structs that deny unknown fields do not allow
+//! additional properties.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Display,
+};
+
+use serde::{
+    Deserialize,
+    de::{
+        self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+        Visitor,
+    },
+};
+use serde_json::{Value, json};
+
+use crate::config::Config;
+
+/// JSON Schema (draft 2020-12) of the config file
+pub fn config_schema() -> Value {
+    let registry = RefCell::new(Registry::default());
+    // Every pass skips the fields that failed in the previous ones, until all fields were traced
+    loop {
+        let poisoned = registry.borrow().poisoned.len();
+        trace(&registry);
+        if registry.borrow().poisoned.len() == poisoned {
+            break;
+        }
+    }
+    // Required fields and unknown fields are probed once per field and struct
+    let structs: Vec<(&'static str, &'static [&'static str])> = registry
+        .borrow()
+        .fields
+        .iter()
+        .map(|(name, fields)| (*name, *fields))
+        .collect();
+    for (name, fields) in structs {
+        registry.borrow_mut().probe = Some(Probe::Unknown(name));
+        trace(&registry);
+        for field in fields {
+            registry.borrow_mut().probe = Some(Probe::Omit(name, field));
+            trace(&registry);
+        }
+    }
+    registry.into_inner().schema()
+}
+
+fn trace(registry: &RefCell<Registry>) {
+    let schema = RefCell::new(Value::Null);
+    // Errors only mean that some fields could not be traced in this pass
+    let _ = Config::deserialize(Tracer {
+        registry,
+        schema: &schema,
+    });
+}
+
+/// A modification of a tracing pass to find out how a struct handles missing and unknown fields
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    /// Leave out the field of the struct, fails if it is required
+    Omit(&'static str, &'static str),
+    /// Add an unknown field to the struct, fails if unknown fields are denied
+    Unknown(&'static str),
+}
+
+#[derive(Default)]
+struct Registry {
+    /// Fields of every struct by name of the struct
+    fields: BTreeMap<&'static str, &'static [&'static str]>,
+    properties: BTreeMap<&'static str, serde_json::Map<String, Value>>,
+    required: BTreeMap<&'static str, BTreeSet<&'static str>>,
+    deny_unknown_fields: HashSet<&'static str>,
+    enums: BTreeMap<&'static str, &'static [&'static str]>,
+    /// Fields whose value can not be deserialized from the placeholder values, they are skipped in later passes
+    poisoned: HashSet<(&'static str, &'static str)>,
+    probe: Option<Probe>,
+}
+
+impl Registry {
+    fn schema(self) -> Value {
+        let mut definitions = serde_json::Map::new();
+        for (name, properties) in self.properties {
+            let mut definition = json!({ "type": "object", "properties": properties });
+            if let Some(required) = self.required.get(name) {
+                definition["required"] = json!(required);
+            }
+            if self.deny_unknown_fields.contains(name) {
+                definition["additionalProperties"] = json!(false);
+            }
+            definitions.insert(name.to_owned(), definition);
+        }
+        for (name, variants) in self.enums {
+            definitions.insert(
+                name.to_owned(),
+                json!({ "type": "string", "enum": variants }),
+            );
+        }
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "K8s-Landingpage config",
+            "$ref": "#/$defs/Config",
+            "$defs": definitions,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum TraceError {
+    Custom(String),
+    MissingField(&'static str),
+    UnknownField,
+    /// A field further down failed and was already marked as poisoned
+    Poisoned,
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceError::Custom(message) => write!(f, "{message}"),
+            TraceError::MissingField(field) => write!(f, "missing field {field}"),
+            TraceError::UnknownField => write!(f, "unknown field"),
+            TraceError::Poisoned => write!(f, "a field could not be traced"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T: Display>(message: T) -> Self {
+        TraceError::Custom(message.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        TraceError::MissingField(field)
+    }
+
+    fn unknown_field(_: &str, _: &'static [&'static str]) -> Self {
+        TraceError::UnknownField
+    }
+}
+
+/// Records the schema of the type it deserializes in `schema` and passes placeholder values to the visitor
+struct Tracer<'r> {
+    registry: &'r RefCell<Registry>,
+    schema: &'r RefCell<Value>,
+}
+
+impl<'r> Tracer<'r> {
+    fn record(&self, schema: Value) {
+        *self.schema.borrow_mut() = schema;
+    }
+
+    /// Deserializes a nested value, returns its schema together with the result
+    fn nested<'de, T: DeserializeSeed<'de>>(
+        &self,
+        seed: T,
+    ) -> (Value, Result<T::Value, TraceError>) {
+        let schema = RefCell::new(Value::Null);
+        let result = seed.deserialize(Tracer {
+            registry: self.registry,
+            schema: &schema,
+        });
+        (schema.into_inner(), result)
+    }
+}
+
+macro_rules! trace_primitive {
+    ($method:ident, $visit:ident, $value:expr, $schema:tt) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+            self.record(json!($schema));
+            visitor.$visit($value)
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Tracer<'_> {
+    type Error = TraceError;
+
+    trace_primitive!(deserialize_bool, visit_bool, false, { "type": "boolean" });
+    trace_primitive!(deserialize_i8, visit_i8, 0, { "type": "integer" });
+    trace_primitive!(deserialize_i16, visit_i16, 0, { "type": "integer" });
+    trace_primitive!(deserialize_i32, visit_i32, 0, { "type": "integer" });
+    trace_primitive!(deserialize_i64, visit_i64, 0, { "type": "integer" });
+    trace_primitive!(deserialize_u8, visit_u8, 0, { "type": "integer", "minimum": 0 });
+    trace_primitive!(deserialize_u16, visit_u16, 0, { "type": "integer", "minimum": 0 });
+    trace_primitive!(deserialize_u32, visit_u32, 0, { "type": "integer", "minimum": 0 });
+    trace_primitive!(deserialize_u64, visit_u64, 0, { "type": "integer", "minimum": 0 });
+    trace_primitive!(deserialize_f32, visit_f32, 0.0, { "type": "number" });
+    trace_primitive!(deserialize_f64, visit_f64, 0.0, { "type": "number" });
+    trace_primitive!(deserialize_char, visit_char, ' ', { "type": "string" });
+    trace_primitive!(deserialize_str, visit_str, "", { "type": "string" });
+    trace_primitive!(deserialize_string, visit_str, "", { "type": "string" });
+    trace_primitive!(deserialize_bytes, visit_bytes, &[], { "type": "string" });
+    trace_primitive!(deserialize_byte_buf, visit_bytes, &[], { "type": "string" });
+    trace_primitive!(deserialize_identifier, visit_str, "", { "type": "string" });
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "null" }));
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        visitor.visit_unit()
+    }
+
+    /// Used by types that decide on the input, like enums with untagged variants, which accept any value here
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({}));
+        visitor.visit_str("")
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        let registry = self.registry;
+        let schema = RefCell::new(Value::Null);
+        let result = visitor.visit_some(Tracer {
+            registry,
+            schema: &schema,
+        });
+        // Empty values like `description:` in YAML are null
+        self.record(json!({ "anyOf": [schema.into_inner(), { "type": "null" }] }));
+        result
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        let mut access = Single {
+            tracer: &self,
+            value: None,
+            done: false,
+        };
+        let result = visitor.visit_seq(&mut access);
+        let items = access.value.unwrap_or_else(|| json!({}));
+        self.record(json!({ "type": "array", "items": items }));
+        result
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        let mut access = Single {
+            tracer: &self,
+            value: None,
+            done: false,
+        };
+        let result = visitor.visit_map(&mut access);
+        let values = access.value.unwrap_or_else(|| json!({}));
+        self.record(json!({ "type": "object", "additionalProperties": values }));
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.record(json!({ "$ref": format!("#/$defs/{name}") }));
+        let (remaining, unknown) = {
+            let mut registry = self.registry.borrow_mut();
+            registry.fields.insert(name, fields);
+            registry.properties.entry(name).or_default();
+            let remaining: Vec<&'static str> = fields
+                .iter()
+                .copied()
+                .filter(|field| {
+                    !registry.poisoned.contains(&(name, field))
+                        && registry.probe != Some(Probe::Omit(name, field))
+                })
+                .collect();
+            (remaining, registry.probe == Some(Probe::Unknown(name)))
+        };
+        let result = visitor.visit_map(Fields {
+            tracer: &self,
+            name,
+            remaining: remaining.into_iter(),
+            unknown,
+            current: None,
+        });
+        match &result {
+            Err(TraceError::MissingField(field)) => {
+                let mut registry = self.registry.borrow_mut();
+                registry.required.entry(name).or_default().insert(field);
+            }
+            Err(TraceError::UnknownField) if unknown => {
+                self.registry.borrow_mut().deny_unknown_fields.insert(name);
+            }
+            _ => {}
+        }
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        self.registry.borrow_mut().enums.insert(name, variants);
+        self.record(json!({ "$ref": format!("#/$defs/{name}") }));
+        visitor.visit_enum(UnitVariant(variants[0]))
+    }
+}
+
+/// Passes the fields of a struct that are not skipped, each with a placeholder value
+struct Fields<'t, 'r> {
+    tracer: &'t Tracer<'r>,
+    name: &'static str,
+    remaining: std::vec::IntoIter<&'static str>,
+    /// Pass an unknown field first
+    unknown: bool,
+    current: Option<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_, '_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        if self.unknown {
+            self.unknown = false;
+            self.current = None;
+            return seed.deserialize("$unknown".into_deserializer()).map(Some);
+        }
+        let Some(field) = self.remaining.next() else {
+            return Ok(None);
+        };
+        self.current = Some(field);
+        seed.deserialize(field.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let (schema, result) = self.tracer.nested(seed);
+        let Some(field) = self.current else {
+            // The value of the unknown field
+            return result;
+        };
+        let mut registry = self.tracer.registry.borrow_mut();
+        if !schema.is_null() {
+            registry
+                .properties
+                .entry(self.name)
+                .or_default()
+                .insert(field.to_owned(), schema);
+        }
+        match result {
+            Ok(value) => Ok(value),
+            Err(TraceError::Poisoned) => Err(TraceError::Poisoned),
+            Err(_) => {
+                // Only skipped in the passes that find the fields, not while probing other structs
+                if registry.probe.is_none() {
+                    registry.poisoned.insert((self.name, field));
+                }
+                Err(TraceError::Poisoned)
+            }
+        }
+    }
+}
+
+/// Passes a single element of a sequence or a single entry of a map and records their schema
+struct Single<'t, 'r> {
+    tracer: &'t Tracer<'r>,
+    value: Option<Value>,
+    done: bool,
+}
+
+impl<'de> SeqAccess<'de> for Single<'_, '_> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TraceError> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        let (schema, result) = self.tracer.nested(seed);
+        self.value = Some(schema);
+        result.map(Some)
+    }
+}
+
+impl<'de> MapAccess<'de> for Single<'_, '_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        // Keys are always strings in the config formats
+        let (_, result) = self.tracer.nested(seed);
+        result.map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let (schema, result) = self.tracer.nested(seed);
+        self.value = Some(schema);
+        result
+    }
+}
+
+/// Selects the given unit variant of an enum
+struct UnitVariant(&'static str);
+
+impl<'de> EnumAccess<'de> for UnitVariant {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), TraceError> {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for UnitVariant {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), TraceError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        _seed: T,
+    ) -> Result<T::Value, TraceError> {
+        Err(de::Error::custom("only unit variants are supported"))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        Err(de::Error::custom("only unit variants are supported"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        Err(de::Error::custom("only unit variants are supported"))
+    }
+}
@@ -0,0 +1,276 @@
+//! Publishes a summary of the collected ingresses and conditions about the health of the collection
+//! in the status of a `LandingPage` custom resource
+
+use chrono::Utc;
+use kube::{
+    Api, Client,
+    api::{ApiResource, DynamicObject, GroupVersionKind, Patch, PatchParams},
+};
+use serde_json::json;
+
+use crate::{
+    collector::IngressCollection,
+    config::StatusResource,
+    errors::{Error, Result},
+};
+
+const GROUP: &str = "landingpage.info";
+const VERSION: &str = "v1alpha1";
+const KIND: &str = "LandingPage";
+
+/// Condition that is true if the spec of the resource the config is read from is valid and applied
+pub const CONFIG_VALID: &str = "ConfigValid";
+/// Condition that is true if every cluster was collected without an error
+pub const COLLECTED: &str = "Collected";
+
+/// `LandingPage` resources in the given namespace
+pub fn landingpages(client: Client, namespace: &str) -> Api<DynamicObject> {
+    let api_resource = ApiResource::from_gvk(&GroupVersionKind::gvk(GROUP, VERSION, KIND));
+    Api::namespaced_with(client, namespace, &api_resource)
+}
+
+/// Writes the summary of the collection into the status of the configured resource.
+/// Failures are only logged as the page itself does not depend on the status.
+pub async fn publish(resource: &StatusResource, collection: &IngressCollection) {
+    let result = async {
+        let client = Client::try_default().await?;
+        patch_status(client.clone(), resource, collection).await?;
+        let failed: Vec<&str> = collection
+            .iter()
+            .flat_map(|group| &group.clusters)
+            .filter(|cluster| cluster.error.is_some())
+            .map(|cluster| cluster.name.as_str())
+            .collect();
+        let condition = if failed.is_empty() {
+            Condition::new(COLLECTED, true, "AllClustersCollected", "")
+        } else {
+            Condition::new(
+                COLLECTED,
+                false,
+                "ClusterErrors",
+                &format!("Could not collect clusters: {}", failed.join(", ")),
+            )
+        };
+        set_condition(client, resource, condition).await
+    };
+    log_failure(resource, result.await);
+}
+
+/// Sets a condition in the status of the resource, failures are only logged
+pub async fn update_condition(resource: &StatusResource, condition: Condition) {
+    let result = async {
+        let client = Client::try_default().await?;
+        set_condition(client, resource, condition).await
+    };
+    log_failure(resource, result.await);
+}
+
+fn log_failure(resource: &StatusResource, result: Result<()>) {
+    match result {
+        Ok(()) => {}
+        Err(Error::Kube(kube::Error::Api(response))) if response.code == 404 => {
+            tracing::warn!(
+                "Could not update status of {KIND} {}/{}: resource or CRD does not exist",
+                resource.namespace,
+                resource.name
+            );
+        }
+        Err(err) => tracing::warn!(
+            "Could not update status of {KIND} {}/{}: {err}",
+            resource.namespace,
+            resource.name
+        ),
+    }
+}
+
+/// A condition in the format of the Kubernetes API conventions
+pub struct Condition {
+    type_: &'static str,
+    status: bool,
+    reason: &'static str,
+    message: String,
+}
+
+impl Condition {
+    pub fn new(type_: &'static str, status: bool, reason: &'static str, message: &str) -> Self {
+        Condition {
+            type_,
+            status,
+            reason,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// Replaces the condition of the same type in the status, the transition time is only
+/// changed if the status of the condition changed
+async fn set_condition(
+    client: Client,
+    resource: &StatusResource,
+    condition: Condition,
+) -> Result<()> {
+    let api = landingpages(client, &resource.namespace);
+    let object = api.get(&resource.name).await?;
+    let status = if condition.status { "True" } else { "False" };
+    let mut conditions: Vec<serde_json::Value> = object.data["status"]["conditions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let existing = conditions
+        .iter()
+        .position(|existing| existing["type"] == condition.type_);
+    let transition_time = existing
+        .map(|index| &conditions[index])
+        .filter(|existing| existing["status"] == status)
+        .and_then(|existing| existing["lastTransitionTime"].as_str())
+        .map(str::to_owned)
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+    let value = json!({
+        "type": condition.type_,
+        "status": status,
+        "reason": condition.reason,
+        "message": condition.message,
+        "lastTransitionTime": transition_time,
+        "observedGeneration": object.metadata.generation,
+    });
+    match existing {
+        Some(index) => conditions[index] = value,
+        None => conditions.push(value),
+    }
+    let patch = json!({ "status": { "conditions": conditions } });
+    api.patch_status(
+        &resource.name,
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn patch_status(
+    client: Client,
+    resource: &StatusResource,
+    collection: &IngressCollection,
+) -> Result<()> {
+    let api = landingpages(client, &resource.namespace);
+    let patch = json!({ "status": summary(collection) });
+    api.patch_status(
+        &resource.name,
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await?;
+    Ok(())
+}
+
+fn summary(collection: &IngressCollection) -> serde_json::Value {
+    let groups: Vec<_> = collection
+        .iter()
+        .map(|group| {
+            let clusters: Vec<_> = group
+                .clusters
+                .iter()
+                .map(|cluster| {
+                    let ingresses: Vec<_> = cluster
+                        .ingresses
+                        .iter()
+                        .map(|ingress| json!({ "name": ingress.name, "url": ingress.url }))
+                        .collect();
+                    json!({
+                        "name": cluster.name,
+                        "ingressCount": ingresses.len() + cluster.hidden_count,
+                        "error": cluster.error,
+                        "ingresses": ingresses,
+                    })
+                })
+                .collect();
+            json!({ "name": group.name, "clusters": clusters })
+        })
+        .collect();
+    json!({
+        "lastCollected": Utc::now().to_rfc3339(),
+        "groups": groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::collector::{ClusterInfo, GroupInfo, IngressInfo};
+
+    #[tokio::test]
+    async fn patches_summary_into_status() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let service = tower::service_fn({
+            let requests = requests.clone();
+            move |request: axum::http::Request<kube::client::Body>| {
+                let requests = requests.clone();
+                async move {
+                    let method = request.method().clone();
+                    let path = request.uri().path().to_owned();
+                    let body = axum::body::to_bytes(
+                        axum::body::Body::new(request.into_body()),
+                        usize::MAX,
+                    )
+                    .await
+                    .unwrap();
+                    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                    requests.lock().unwrap().push((method, path, body));
+                    let response = json!({
+                        "apiVersion": "landingpage.info/v1alpha1",
+                        "kind": "LandingPage",
+                        "metadata": { "name": "landingpage", "namespace": "default" },
+                    });
+                    let response =
+                        axum::http::Response::new(axum::body::Body::from(response.to_string()));
+                    Ok::<_, std::convert::Infallible>(response)
+                }
+            }
+        });
+        let collection = vec![GroupInfo {
+            name: "prod".to_owned(),
+            description: String::new(),
+            clusters: vec![ClusterInfo {
+                name: "main".to_owned(),
+                description: String::new(),
+                ingresses: vec![IngressInfo {
+                    name: "Grafana".to_owned(),
+                    url: "https://grafana.example.com/".to_owned(),
+                    ..Default::default()
+                }],
+                truncated: true,
+                hidden_count: 2,
+                error: None,
+                gateways: Vec::new(),
+                links: Vec::new(),
+                last_updated: Utc::now(),
+                stale: false,
+            }],
+            links: Vec::new(),
+        }];
+        let resource = StatusResource {
+            name: "landingpage".to_owned(),
+            namespace: "default".to_owned(),
+        };
+        patch_status(Client::new(service, "default"), &resource, &collection)
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let (method, path, body) = &requests[0];
+        assert_eq!(method, axum::http::Method::PATCH);
+        assert_eq!(
+            path,
+            "/apis/landingpage.info/v1alpha1/namespaces/default/landingpages/landingpage/status"
+        );
+        let cluster = &body["status"]["groups"][0]["clusters"][0];
+        assert_eq!(cluster["name"], "main");
+        assert_eq!(cluster["ingressCount"], 3);
+        assert_eq!(
+            cluster["ingresses"],
+            json!([{ "name": "Grafana", "url": "https://grafana.example.com/" }])
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Export of tracing spans to an OpenTelemetry collector via OTLP/HTTP using the JSON encoding.
+//! Only active if `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set.
+
+use std::{
+    fmt::Debug,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_QUEUED_SPANS: usize = 4096;
+// OTLP span kind and status codes
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_CODE_ERROR: u8 = 2;
+
+struct SpanData {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    name: &'static str,
+    start: SystemTime,
+    attributes: Vec<Value>,
+    events: Vec<Value>,
+    error: bool,
+}
+
+pub struct OtlpLayer {
+    sender: mpsc::Sender<Value>,
+}
+
+/// Creates the export layer and spawns the background exporter task if an endpoint is configured.
+/// Must be called from within a tokio runtime.
+pub fn layer() -> Option<OtlpLayer> {
+    let endpoint = if let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+        endpoint
+    } else {
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+        format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+    };
+    let service_name =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "landingpage".to_owned());
+    // Format is a comma-separated list of key=value pairs
+    let headers: Vec<(String, String)> = std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|header| header.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect();
+
+    let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+    tokio::spawn(export_task(endpoint, service_name, headers, receiver));
+    Some(OtlpLayer { sender })
+}
+
+async fn export_task(
+    endpoint: String,
+    service_name: String,
+    headers: Vec<(String, String)>,
+    mut receiver: mpsc::Receiver<Value>,
+) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut spans = Vec::new();
+        while let Ok(span) = receiver.try_recv() {
+            spans.push(span);
+        }
+        if spans.is_empty() {
+            if receiver.is_closed() {
+                return;
+            }
+            continue;
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", json!({"stringValue": service_name}))],
+                },
+                "scopeSpans": [{
+                    "scope": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")},
+                    "spans": spans,
+                }],
+            }],
+        });
+        let mut request = client.post(&endpoint).json(&body);
+        for (key, value) in headers.iter() {
+            request = request.header(key, value);
+        }
+        match request.send().await {
+            Ok(response) if !response.status().is_success() => {
+                tracing::warn!("Exporting traces failed with status {}", response.status());
+            }
+            Ok(_) => {}
+            Err(err) => tracing::warn!("Exporting traces failed: {err}"),
+        }
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| (data.trace_id, data.span_id))
+        });
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, parent_span_id)) => (trace_id, Some(parent_span_id)),
+            None => (rand::random(), None),
+        };
+        let mut data = SpanData {
+            trace_id,
+            span_id: rand::random(),
+            parent_span_id,
+            name: attrs.metadata().name(),
+            start: SystemTime::now(),
+            attributes: Vec::new(),
+            events: Vec::new(),
+            error: false,
+        };
+        attrs.record(&mut AttributeVisitor(&mut data.attributes));
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(data) = span.extensions_mut().get_mut::<SpanData>()
+        {
+            values.record(&mut AttributeVisitor(&mut data.attributes));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else {
+            return;
+        };
+        let mut attributes = vec![attribute(
+            "level",
+            json!({"stringValue": event.metadata().level().as_str()}),
+        )];
+        event.record(&mut AttributeVisitor(&mut attributes));
+        // Use the log message as the name of the event
+        let name = attributes
+            .iter()
+            .position(|attr| attr["key"] == "message")
+            .map(|index| attributes.remove(index)["value"]["stringValue"].clone())
+            .unwrap_or_else(|| json!(event.metadata().name()));
+        data.events.push(json!({
+            "timeUnixNano": unix_nanos(SystemTime::now()),
+            "name": name,
+            "attributes": attributes,
+        }));
+        if *event.metadata().level() == Level::ERROR {
+            data.error = true;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        let mut otlp_span = json!({
+            "traceId": format!("{:032x}", data.trace_id),
+            "spanId": format!("{:016x}", data.span_id),
+            "name": data.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": unix_nanos(data.start),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": data.attributes,
+            "events": data.events,
+        });
+        if let Some(parent_span_id) = data.parent_span_id {
+            otlp_span["parentSpanId"] = json!(format!("{parent_span_id:016x}"));
+        }
+        if data.error {
+            otlp_span["status"] = json!({"code": STATUS_CODE_ERROR});
+        }
+        // Drop the span if the exporter can not keep up instead of blocking
+        let _ = self.sender.try_send(otlp_span);
+    }
+}
+
+struct AttributeVisitor<'a>(&'a mut Vec<Value>);
+
+impl Visit for AttributeVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .push(attribute(field.name(), json!({"stringValue": value})));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0
+            .push(attribute(field.name(), json!({"boolValue": value})));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        // OTLP JSON encodes 64 bit integers as strings
+        self.0.push(attribute(
+            field.name(),
+            json!({"intValue": value.to_string()}),
+        ));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.push(attribute(
+            field.name(),
+            json!({"intValue": value.to_string()}),
+        ));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0
+            .push(attribute(field.name(), json!({"doubleValue": value})));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({"key": key, "value": value})
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
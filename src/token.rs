@@ -0,0 +1,124 @@
+//! Bearer tokens for remote clusters that are created by the tool itself instead of being read from a secret,
+//! e.g. for cloud-managed clusters whose kubeconfigs rely on exec plugins that are not available in the container
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::EksCluster,
+    errors::{Error, Result},
+};
+
+/// Creates a token to authenticate against the API server of a remote cluster.
+/// A new token is requested for every new client, so providers do not need to cache them.
+pub trait TokenProvider: Send + Sync {
+    fn token(&self) -> BoxFuture<'_, Result<String>>;
+}
+
+/// The presigned URL is only accepted by EKS within this many seconds
+const EKS_URL_EXPIRES_SECONDS: u32 = 60;
+
+/// Creates tokens for EKS like `aws eks get-token`: a presigned `GetCallerIdentity` request to STS,
+/// signed with the AWS credentials from the environment (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`
+/// and optionally `AWS_SESSION_TOKEN`)
+pub struct EksTokenProvider {
+    cluster_name: String,
+    region: String,
+}
+
+impl EksTokenProvider {
+    pub fn new(eks: &EksCluster) -> Self {
+        EksTokenProvider {
+            cluster_name: eks.cluster_name.clone(),
+            region: eks.region.clone(),
+        }
+    }
+
+    fn sign(&self) -> Result<String> {
+        let access_key = aws_env("AWS_ACCESS_KEY_ID")?;
+        let secret_key = aws_env("AWS_SECRET_ACCESS_KEY")?;
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let host = format!("sts.{}.amazonaws.com", self.region);
+        let scope = format!("{date}/{}/sts/aws4_request", self.region);
+
+        // Parameters have to be sorted by name for the canonical request
+        let mut parameters = vec![
+            ("Action", "GetCallerIdentity".to_owned()),
+            ("Version", "2011-06-15".to_owned()),
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_owned()),
+            ("X-Amz-Credential", format!("{access_key}/{scope}")),
+            ("X-Amz-Date", timestamp.clone()),
+            ("X-Amz-Expires", EKS_URL_EXPIRES_SECONDS.to_string()),
+        ];
+        if let Some(session_token) = session_token {
+            parameters.push(("X-Amz-Security-Token", session_token));
+        }
+        parameters.push(("X-Amz-SignedHeaders", "host;x-k8s-aws-id".to_owned()));
+        let query = parameters
+            .iter()
+            .map(|(name, value)| format!("{name}={}", uri_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let canonical_request = format!(
+            "GET\n/\n{query}\nhost:{host}\nx-k8s-aws-id:{}\n\nhost;x-k8s-aws-id\n{}",
+            self.cluster_name,
+            hex::encode(Sha256::digest(b""))
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = [self.region.as_str(), "sts", "aws4_request"].iter().fold(
+            hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes()),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let url = format!("https://{host}/?{query}&X-Amz-Signature={signature}");
+        Ok(format!(
+            "k8s-aws-v1.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(url.as_bytes())
+        ))
+    }
+}
+
+impl TokenProvider for EksTokenProvider {
+    fn token(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move { self.sign() })
+    }
+}
+
+fn aws_env(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|_| {
+        Error::InvalidKubeconfig(format!(
+            "Environment variable {name} is needed for EKS clusters but not set"
+        ))
+    })
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes everything except the unreserved characters, as required by AWS signatures
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,513 @@
+//! Parser for config files in the TOML format. The document is turned into a JSON value that is
+//! deserialized like the other formats. Dates and times are kept as strings.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Number, Value};
+
+use crate::errors::{Error, Result};
+
+/// Parses a TOML document into a JSON object
+pub fn parse(data: &str) -> Result<Value> {
+    let mut parser = Parser {
+        chars: data.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .document()
+        .map_err(|err| Error::InvalidConfig(format!("TOML error at line {}: {err}", parser.line)))
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn document(&mut self) -> ParseResult<Value> {
+        let mut root = Map::new();
+        // Path of the table the following key/value pairs belong to
+        let mut current: Vec<String> = Vec::new();
+        // Tables with a header, a table must not be defined twice
+        let mut defined: HashSet<Vec<String>> = HashSet::new();
+        loop {
+            self.skip_blank_lines();
+            match self.peek() {
+                None => break,
+                Some('[') if self.peek_at(1) == Some('[') => {
+                    self.pos += 2;
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    self.expect(']')?;
+                    let (last, parents) = path.split_last().expect("keys are never empty");
+                    let parent = table_mut(&mut root, parents)?;
+                    match parent
+                        .entry(last.clone())
+                        .or_insert_with(|| Value::Array(Vec::new()))
+                    {
+                        Value::Array(tables) => tables.push(Value::Object(Map::new())),
+                        _ => {
+                            return Err(format!("{} is not an array of tables", path.join(".")));
+                        }
+                    }
+                    // The tables of the new element can be defined again
+                    defined.retain(|table| !table.starts_with(&path));
+                    current = path;
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    if !defined.insert(path.clone()) {
+                        return Err(format!("table [{}] is defined twice", path.join(".")));
+                    }
+                    table_mut(&mut root, &path)?;
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key()?;
+                    let full_path = [current.as_slice(), &path].concat();
+                    let value = self
+                        .expect('=')
+                        .and_then(|()| self.value())
+                        .map_err(|err| format!("{err} (key {})", full_path.join(".")))?;
+                    insert(&mut root, &full_path, value)?;
+                }
+            }
+            self.end_of_line()?;
+        }
+        Ok(Value::Object(root))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(offset, c)| self.peek_at(offset) == Some(c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and newlines, e.g. between lines or values of an array
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => {
+                    self.next();
+                }
+                Some('\r') if self.peek_at(1) == Some('\n') => {
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> ParseResult<()> {
+        self.skip_whitespace();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.pos += 1;
+        }
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(c) => Err(format!("unexpected {c:?}, expected the end of the line")),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> ParseResult<()> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("unexpected {c:?}, expected {expected:?}")),
+            None => Err(format!("unexpected end, expected {expected:?}")),
+        }
+    }
+
+    /// A dotted key like `global.healthCheck."timeout"`
+    fn key(&mut self) -> ParseResult<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let part = match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    self.literal_string()?
+                }
+                _ => {
+                    let start = self.pos;
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err("expected a key".to_owned());
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            path.push(part);
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> ParseResult<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') if self.starts_with("\"\"\"") => {
+                self.pos += 3;
+                self.multiline_string(true).map(Value::String)
+            }
+            Some('\'') if self.starts_with("'''") => {
+                self.pos += 3;
+                self.multiline_string(false).map(Value::String)
+            }
+            Some('"') => {
+                self.pos += 1;
+                self.basic_string().map(Value::String)
+            }
+            Some('\'') => {
+                self.pos += 1;
+                self.literal_string().map(Value::String)
+            }
+            Some('[') => {
+                self.pos += 1;
+                self.array()
+            }
+            Some('{') => {
+                self.pos += 1;
+                self.inline_table()
+            }
+            Some(_) => self.scalar(),
+            None => Err("expected a value".to_owned()),
+        }
+    }
+
+    fn basic_string(&mut self) -> ParseResult<String> {
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(result),
+                Some('\\') => result.push(self.escape()?),
+                Some('\n') | None => return Err("unterminated string".to_owned()),
+                Some(c) => result.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> ParseResult<String> {
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(result),
+                Some('\n') | None => return Err("unterminated string".to_owned()),
+                Some(c) => result.push(c),
+            }
+        }
+    }
+
+    /// A string in triple quotes, escapes are only processed in basic strings
+    fn multiline_string(&mut self, basic: bool) -> ParseResult<String> {
+        let delimiter = if basic { "\"\"\"" } else { "'''" };
+        // A newline directly after the opening delimiter is not part of the string
+        if self.starts_with("\r\n") {
+            self.pos += 1;
+        }
+        if self.peek() == Some('\n') {
+            self.next();
+        }
+        let mut result = String::new();
+        loop {
+            if self.starts_with(delimiter) {
+                self.pos += 3;
+                return Ok(result);
+            }
+            match self.next() {
+                Some('\\') if basic => {
+                    // A backslash at the end of a line removes the line break and the following whitespace
+                    if matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                            self.next();
+                        }
+                    } else {
+                        result.push(self.escape()?);
+                    }
+                }
+                Some(c) => result.push(c),
+                None => return Err("unterminated string".to_owned()),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> ParseResult<char> {
+        let c = match self.next() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => self.unicode(4)?,
+            Some('U') => self.unicode(8)?,
+            Some(c) => return Err(format!("invalid escape sequence \\{c}")),
+            None => return Err("unterminated string".to_owned()),
+        };
+        Ok(c)
+    }
+
+    fn unicode(&mut self, digits: usize) -> ParseResult<char> {
+        let hex: String = self.chars[self.pos..].iter().take(digits).collect();
+        self.pos += hex.len();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid unicode escape {hex}"))
+    }
+
+    fn array(&mut self) -> ParseResult<Value> {
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank_lines();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(values)),
+                Some(c) => return Err(format!("unexpected {c:?} in array")),
+                None => return Err("unterminated array".to_owned()),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> ParseResult<Value> {
+        let mut table = Map::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(table));
+        }
+        loop {
+            let path = self.key()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            insert(&mut table, &path, value)?;
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Object(table)),
+                Some(c) => return Err(format!("unexpected {c:?} in inline table")),
+                None => return Err("unterminated inline table".to_owned()),
+            }
+        }
+    }
+
+    /// Booleans, numbers and dates, which are kept as strings
+    fn scalar(&mut self) -> ParseResult<Value> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !matches!(c, ',' | ']' | '}' | '#' | '\n' | '\r' | '\t'))
+        {
+            // Dates and times can be separated by a space
+            if self.peek() == Some(' ') && !self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+                break;
+            }
+            self.pos += 1;
+        }
+        let token: String = self.chars[start..self.pos].iter().collect();
+        let token = token.trim_end();
+        match token {
+            "" => return Err("expected a value".to_owned()),
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+        let digits = token.replace('_', "");
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(unsigned) => (-1, unsigned),
+            None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+        };
+        let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+            .into_iter()
+            .find_map(|(prefix, radix)| unsigned.strip_prefix(prefix).map(|rest| (rest, radix)));
+        if let Some((rest, radix)) = radix
+            && let Ok(number) = i64::from_str_radix(rest, radix)
+        {
+            return Ok(Value::Number((sign * number).into()));
+        }
+        if let Ok(number) = digits.parse::<i64>() {
+            return Ok(Value::Number(number.into()));
+        }
+        if let Some(number) = digits.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Ok(Value::Number(number));
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit()) && token.contains(['-', ':']) {
+            return Ok(Value::String(token.to_owned()));
+        }
+        Err(format!("invalid value {token}"))
+    }
+}
+
+/// Returns the table at the path, missing tables are created. For arrays of tables the last table is used.
+fn table_mut<'a>(
+    root: &'a mut Map<String, Value>,
+    path: &[String],
+) -> ParseResult<&'a mut Map<String, Value>> {
+    let mut table = root;
+    for (index, key) in path.iter().enumerate() {
+        let not_a_table = || format!("{} is not a table", path[..=index].join("."));
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let value = match value {
+            Value::Array(tables) => tables.last_mut().ok_or_else(not_a_table)?,
+            value => value,
+        };
+        table = value.as_object_mut().ok_or_else(not_a_table)?;
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Map<String, Value>, path: &[String], value: Value) -> ParseResult<()> {
+    let (last, parents) = path.split_last().expect("keys are never empty");
+    let table = table_mut(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("duplicate key {}", path.join(".")));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn error(data: &str) -> String {
+        match parse(data) {
+            Err(Error::InvalidConfig(message)) => message,
+            result => panic!("expected an error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_tables_and_values() {
+        let data = r#"
+# Comment
+[global]
+refreshIntervalSeconds = 30
+watch = true
+locale = "de-DE" # Comment
+healthCheck.timeoutSeconds = 5
+
+[[staticLinks]]
+name = 'Docs'
+group = "tools"
+
+[[staticLinks]]
+name = """
+Wiki"""
+group = "tools"
+tags = ["a", "b",]
+meta = { order = 1_000, ratio = 0.5 }
+"#;
+        assert_eq!(
+            parse(data).unwrap(),
+            json!({
+                "global": {
+                    "refreshIntervalSeconds": 30,
+                    "watch": true,
+                    "locale": "de-DE",
+                    "healthCheck": { "timeoutSeconds": 5 },
+                },
+                "staticLinks": [
+                    { "name": "Docs", "group": "tools" },
+                    {
+                        "name": "Wiki",
+                        "group": "tools",
+                        "tags": ["a", "b"],
+                        "meta": { "order": 1000, "ratio": 0.5 },
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_redefined_tables() {
+        let message = error("[global]\nwatch = true\n\n[global]\nlocale = \"de\"\n");
+        assert_eq!(
+            message,
+            "TOML error at line 4: table [global] is defined twice"
+        );
+    }
+
+    #[test]
+    fn allows_tables_in_every_element_of_an_array() {
+        let data = "[[remote.prod]]\nname = \"a\"\n[remote.prod.eks]\nregion = \"x\"\n\n[[remote.prod]]\nname = \"b\"\n[remote.prod.eks]\nregion = \"y\"\n";
+        let value = parse(data).unwrap();
+        assert_eq!(value["remote"]["prod"][1]["eks"]["region"], "y");
+    }
+
+    #[test]
+    fn errors_contain_the_key_path() {
+        assert_eq!(
+            error("[global]\nwatch = true\nwatch = false\n"),
+            "TOML error at line 3: duplicate key global.watch"
+        );
+        assert_eq!(
+            error("[global.healthCheck]\ntimeoutSeconds = 5s\n"),
+            "TOML error at line 2: invalid value 5s (key global.healthCheck.timeoutSeconds)"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use url::{ParseError, Url};
+
+use crate::{
+    config::Config,
+    errors::{Error, Result},
+};
+
+/// Schemes that are allowed for URLs rendered as links on the page
+const ALLOWED_SCHEMES: &[&str] = &["http", "https"];
+
+/// Parts that are removed from URLs during normalization
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UrlOptions {
+    pub strip_query: bool,
+    pub strip_fragment: bool,
+}
+
+impl UrlOptions {
+    pub fn from_config(config: &Config) -> Self {
+        config
+            .global
+            .as_ref()
+            .map(|g| UrlOptions {
+                strip_query: g.strip_url_query,
+                strip_fragment: g.strip_url_fragment,
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Validates and normalizes a URL provided by an annotation.
+/// Relative URLs are resolved against the host of the ingress, URLs with schemes other than
+/// http(s) (e.g. `javascript:`) are rejected.
+pub fn normalize_url(
+    value: &str,
+    host: &str,
+    tls_used: bool,
+    options: UrlOptions,
+) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(Error::InvalidUrl("URL is empty".to_owned()));
+    }
+    let mut url = match Url::parse(value) {
+        Ok(url) => url,
+        Err(ParseError::RelativeUrlWithoutBase) => {
+            let scheme = if tls_used { "https" } else { "http" };
+            let base = Url::parse(&format!("{scheme}://{host}/"))
+                .map_err(|err| Error::InvalidUrl(format!("{value}: {err}")))?;
+            base.join(value)
+                .map_err(|err| Error::InvalidUrl(format!("{value}: {err}")))?
+        }
+        Err(err) => return Err(Error::InvalidUrl(format!("{value}: {err}"))),
+    };
+    if !ALLOWED_SCHEMES.contains(&url.scheme()) {
+        return Err(Error::InvalidUrl(format!(
+            "{value}: scheme {} is not allowed",
+            url.scheme()
+        )));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(Error::InvalidUrl(format!("{value}: URL has no host")));
+    }
+    if options.strip_query {
+        url.set_query(None);
+    }
+    if options.strip_fragment {
+        url.set_fragment(None);
+    }
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(value: &str) -> Result<String> {
+        normalize_url(value, "app.example.com", true, UrlOptions::default())
+    }
+
+    #[test]
+    fn keeps_valid_urls() {
+        assert_eq!(
+            normalize(" https://docs.example.com/app?page=1 ").unwrap(),
+            "https://docs.example.com/app?page=1"
+        );
+        assert_eq!(
+            normalize("http://docs.example.com").unwrap(),
+            "http://docs.example.com/"
+        );
+    }
+
+    #[test]
+    fn resolves_relative_urls_against_the_host() {
+        assert_eq!(normalize("/docs").unwrap(), "https://app.example.com/docs");
+        assert_eq!(
+            normalize_url("docs", "app.example.com", false, UrlOptions::default()).unwrap(),
+            "http://app.example.com/docs"
+        );
+    }
+
+    #[test]
+    fn rejects_dangerous_urls() {
+        for value in [
+            "javascript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "file:///etc/passwd",
+            "",
+        ] {
+            assert!(
+                matches!(normalize(value), Err(Error::InvalidUrl(_))),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn strips_query_and_fragment() {
+        let url = "https://app.example.com/search?q=1#results";
+        let strip = |strip_query, strip_fragment| {
+            let options = UrlOptions {
+                strip_query,
+                strip_fragment,
+            };
+            normalize_url(url, "app.example.com", true, options).unwrap()
+        };
+        assert_eq!(strip(false, false), url);
+        assert_eq!(strip(true, false), "https://app.example.com/search#results");
+        assert_eq!(strip(false, true), "https://app.example.com/search?q=1");
+        assert_eq!(strip(true, true), "https://app.example.com/search");
+    }
+}
@@ -0,0 +1,428 @@
+//! Watches ingresses of a cluster so it is refreshed shortly after a change
+//! instead of only with the next periodic refresh. The watches run in the `JoinSet`
+//! of the task collecting the cluster and stop together with it.
+//! The watched ingresses are kept in an `IngressStore`, so a refresh does not list them again.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::{core::v1::Secret, networking::v1::Ingress};
+use kube::{
+    Api, Client, Resource,
+    api::{ListParams, WatchEvent, WatchParams},
+};
+use serde::de::DeserializeOwned;
+use tokio::{sync::Notify, task::JoinSet};
+
+use crate::{
+    collector::{list_params, local_client, remote_client},
+    config::{
+        ClusterApi, ClusterConfig, Config, Discovery, LocalCluster, RemoteCluster, ResourceKind,
+        Retry,
+    },
+    discovery,
+    errors::Result,
+    retry,
+};
+
+/// Wait time before a failed or closed watch is started again
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+type Ingresses = BTreeMap<(String, String), Ingress>;
+
+/// Ingresses of a watched cluster by namespace and name, `None` while the watch is not running
+#[derive(Clone, Default)]
+pub struct IngressStore(Arc<RwLock<Option<Ingresses>>>);
+
+impl IngressStore {
+    /// The ingresses in the namespace (or all), `None` if they have to be listed since the store is not in sync
+    pub fn list(&self, namespace: Option<&str>) -> Option<Vec<Ingress>> {
+        let ingresses = self.0.read().unwrap();
+        let ingresses = ingresses
+            .as_ref()?
+            .iter()
+            .filter(|((ingress_namespace, _), _)| namespace.is_none_or(|n| n == ingress_namespace))
+            .map(|(_, ingress)| ingress.clone())
+            .collect();
+        Some(ingresses)
+    }
+
+    fn replace(&self, ingresses: Option<Ingresses>) {
+        *self.0.write().unwrap() = ingresses;
+    }
+
+    fn insert(&self, ingress: Ingress) {
+        if let Some(ingresses) = self.0.write().unwrap().as_mut() {
+            ingresses.insert(store_key(&ingress), ingress);
+        }
+    }
+
+    fn remove(&self, ingress: &Ingress) {
+        if let Some(ingresses) = self.0.write().unwrap().as_mut() {
+            ingresses.remove(&store_key(ingress));
+        }
+    }
+}
+
+fn store_key(ingress: &Ingress) -> (String, String) {
+    (
+        ingress.metadata.namespace.clone().unwrap_or_default(),
+        ingress.metadata.name.clone().unwrap_or_default(),
+    )
+}
+
+/// Starts watching the local cluster, `changed` is notified on every change
+pub fn watch_local(
+    config: &Config,
+    local: &LocalCluster,
+    ingresses: &IngressStore,
+    changed: Arc<Notify>,
+    watches: &mut JoinSet<()>,
+) {
+    if !is_watchable("local", local) {
+        return;
+    }
+    if local.discover_namespaces || local.auto_detect_permissions {
+        tracing::info!(
+            "Not watching the local cluster, the namespaces are discovered on every refresh"
+        );
+        return;
+    }
+    let params = list_params(config, local);
+    let local = local.clone();
+    let ingresses = ingresses.clone();
+    watches.spawn(async move {
+        loop {
+            let result = match local_client(&local).await {
+                Ok(client) => {
+                    watch_cluster(client, &params, &local.namespaces, &ingresses, &changed).await
+                }
+                Err(err) => Err(err),
+            };
+            log_restart("local", result);
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+/// Starts watching a remote cluster, `changed` is notified on every change.
+/// The watch reconnects with a new client whenever `rotated` is notified.
+pub fn watch_remote(
+    config: &Config,
+    remote: &RemoteCluster,
+    ingresses: &IngressStore,
+    changed: Arc<Notify>,
+    rotated: Arc<Notify>,
+    watches: &mut JoinSet<()>,
+) {
+    if !is_watchable(&remote.name, remote) {
+        return;
+    }
+    let params = list_params(config, remote);
+    let retry = retry::policy(config);
+    let remote = remote.clone();
+    let ingresses = ingresses.clone();
+    watches.spawn(async move {
+        loop {
+            let result = match connect(&remote, &retry).await {
+                Ok(client) => tokio::select! {
+                    result = watch_cluster(client, &params, &remote.namespaces, &ingresses, &changed) => result,
+                    _ = rotated.notified() => {
+                        tracing::info!("Secrets of cluster {} changed, reconnecting the watch", remote.name);
+                        ingresses.replace(None);
+                        continue;
+                    }
+                },
+                Err(err) => Err(err),
+            };
+            log_restart(&remote.name, result);
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+/// Starts watching the secrets the client of a remote cluster is built from (kubeconfig, token, certificates
+/// and fallbacks). On a change both `changed` and `rotated` are notified, so the cluster is collected with a
+/// new client right away and a running watch reconnects instead of keeping the old client.
+pub fn watch_secrets(
+    remote: &RemoteCluster,
+    changed: Arc<Notify>,
+    rotated: Arc<Notify>,
+    watches: &mut JoinSet<()>,
+) {
+    let secrets: Vec<(String, String)> = remote
+        .kubeconfig_secret
+        .iter()
+        .chain(&remote.fallback_kubeconfig_secrets)
+        .map(|secret| (secret.namespace.clone(), secret.name.clone()))
+        .chain(
+            [
+                &remote.token_secret,
+                &remote.client_certificate_secret,
+                &remote.ca_secret,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|secret| (secret.namespace.clone(), secret.name.clone())),
+        )
+        .collect();
+    if secrets.is_empty() {
+        return;
+    }
+    let name = remote.name.clone();
+    watches.spawn(async move {
+        let on_change = || {
+            changed.notify_one();
+            rotated.notify_one();
+        };
+        loop {
+            let result = match Client::try_default().await {
+                Ok(client) => {
+                    let watches = secrets.iter().map(|(namespace, secret)| {
+                        let api = Api::<Secret>::namespaced(client.clone(), namespace);
+                        let params =
+                            WatchParams::default().fields(&format!("metadata.name={secret}"));
+                        Box::pin(async move { watch_changes(api, &params, &on_change).await })
+                    });
+                    futures::future::select_all(watches).await.0
+                }
+                Err(err) => Err(err.into()),
+            };
+            log_restart(&format!("secrets of {name}"), result);
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+/// Starts watching the labeled kubeconfig secrets, `changed` is notified whenever one is added, changed or removed
+pub fn watch_discovery(discovery: &Discovery, changed: Arc<Notify>, watches: &mut JoinSet<()>) {
+    let params = WatchParams::default().labels(&discovery::secret_selector());
+    let namespace = discovery.namespace.clone();
+    watches.spawn(async move {
+        loop {
+            let result = match Client::try_default().await {
+                Ok(client) => {
+                    let api = Api::<Secret>::namespaced(client, &namespace);
+                    watch_changes(api, &params, &|| changed.notify_one()).await
+                }
+                Err(err) => Err(err.into()),
+            };
+            log_restart("discovered", result);
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+/// Starts watching the `Cluster` objects of Cluster API, `changed` is notified whenever one is added, changed or removed
+pub fn watch_cluster_api(
+    cluster_api: &ClusterApi,
+    changed: Arc<Notify>,
+    watches: &mut JoinSet<()>,
+) {
+    let mut params = WatchParams::default();
+    if let Some(selector) = cluster_api.label_selector.as_deref() {
+        params = params.labels(selector);
+    }
+    let cluster_api = cluster_api.clone();
+    watches.spawn(async move {
+        loop {
+            let result = match Client::try_default().await {
+                Ok(client) => {
+                    let api = discovery::cluster_objects(&cluster_api, client);
+                    watch_changes(api, &params, &|| changed.notify_one()).await
+                }
+                Err(err) => Err(err.into()),
+            };
+            log_restart("cluster-api", result);
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    });
+}
+
+/// Only typed `networking.k8s.io/v1` ingresses are watched, other kinds are picked up by the periodic refresh
+fn is_watchable(name: &str, cluster: &impl ClusterConfig) -> bool {
+    if !cluster.kinds().contains(&ResourceKind::Ingress) {
+        return false;
+    }
+    if cluster.ingress_api_version().is_some() {
+        tracing::debug!("Not watching cluster {name}, it uses a custom ingress API version");
+        return false;
+    }
+    true
+}
+
+fn watch_params(params: &ListParams) -> WatchParams {
+    WatchParams {
+        label_selector: params.label_selector.clone(),
+        field_selector: params.field_selector.clone(),
+        ..WatchParams::default()
+    }
+}
+
+async fn connect(remote: &RemoteCluster, retry: &Retry) -> Result<Client> {
+    let client = Client::try_default().await?;
+    // No request timeout, a watch without changes would run into it
+    remote_client(remote, client, retry, None).await
+}
+
+fn log_restart(name: &str, result: Result<()>) {
+    match result {
+        Ok(()) => tracing::debug!("Watch of cluster {name} ended, restarting"),
+        Err(err) => tracing::warn!("Watch of cluster {name} failed, restarting: {err}"),
+    }
+}
+
+/// Lists the ingresses of the given namespaces (or all) into the store and applies the changes of
+/// concurrent watches until one of them ends. The store is out of sync afterwards.
+async fn watch_cluster(
+    client: Client,
+    params: &ListParams,
+    namespaces: &Option<Vec<String>>,
+    ingresses: &IngressStore,
+    changed: &Notify,
+) -> Result<()> {
+    let apis: Vec<Api<Ingress>> = match namespaces {
+        Some(namespaces) => namespaces
+            .iter()
+            .map(|namespace| Api::namespaced(client.clone(), namespace))
+            .collect(),
+        None => vec![Api::all(client)],
+    };
+    let mut listed = BTreeMap::new();
+    let mut versions = Vec::new();
+    for api in &apis {
+        let list = api.list(params).await?;
+        versions.push(list.metadata.resource_version.unwrap_or_default());
+        listed.extend(
+            list.items
+                .into_iter()
+                .map(|ingress| (store_key(&ingress), ingress)),
+        );
+    }
+    ingresses.replace(Some(listed));
+    // Changes between the last refresh and the start of the watch
+    changed.notify_one();
+    if apis.is_empty() {
+        return std::future::pending().await;
+    }
+    let params = watch_params(params);
+    let watches = apis
+        .into_iter()
+        .zip(versions)
+        .map(|(api, version)| Box::pin(watch_ingresses(api, &params, version, ingresses, changed)));
+    let result = futures::future::select_all(watches).await.0;
+    ingresses.replace(None);
+    result
+}
+
+/// Applies the changes to the store. A watch closed by the API server is resumed from the last seen
+/// version, only an error (e.g. the version is too old) requires listing the ingresses again.
+async fn watch_ingresses(
+    api: Api<Ingress>,
+    params: &WatchParams,
+    mut version: String,
+    ingresses: &IngressStore,
+    changed: &Notify,
+) -> Result<()> {
+    loop {
+        let mut events = api.watch(params, &version).await?.boxed();
+        while let Some(event) = events.try_next().await? {
+            let ingress_version = match event {
+                WatchEvent::Added(ingress) | WatchEvent::Modified(ingress) => {
+                    let ingress_version = ingress.metadata.resource_version.clone();
+                    ingresses.insert(ingress);
+                    ingress_version
+                }
+                WatchEvent::Deleted(ingress) => {
+                    ingresses.remove(&ingress);
+                    ingress.metadata.resource_version
+                }
+                WatchEvent::Bookmark(bookmark) => {
+                    version = bookmark.metadata.resource_version;
+                    continue;
+                }
+                WatchEvent::Error(err) => return Err(kube::Error::Api(err).into()),
+            };
+            version = ingress_version.unwrap_or(version);
+            changed.notify_one();
+        }
+    }
+}
+
+/// Watches from the current resource version, so only changes after the start are reported
+async fn watch_changes<K>(api: Api<K>, params: &WatchParams, on_change: &impl Fn()) -> Result<()>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let list = api.list_metadata(&ListParams::default().limit(1)).await?;
+    let version = list.metadata.resource_version.unwrap_or_default();
+    let mut events = api.watch(params, &version).await?.boxed();
+    while let Some(event) = events.try_next().await? {
+        match event {
+            WatchEvent::Added(_) | WatchEvent::Modified(_) | WatchEvent::Deleted(_) => {
+                on_change();
+            }
+            WatchEvent::Bookmark(_) => {}
+            WatchEvent::Error(err) => return Err(kube::Error::Api(err).into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use kube::api::ObjectMeta;
+
+    use super::*;
+
+    fn ingress(namespace: &str, name: &str) -> Ingress {
+        Ingress {
+            metadata: ObjectMeta {
+                namespace: Some(namespace.to_owned()),
+                name: Some(name.to_owned()),
+                ..ObjectMeta::default()
+            },
+            ..Ingress::default()
+        }
+    }
+
+    fn names(ingresses: Option<Vec<Ingress>>) -> Option<Vec<String>> {
+        ingresses.map(|ingresses| {
+            ingresses
+                .into_iter()
+                .map(|ingress| ingress.metadata.name.unwrap_or_default())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn store_applies_changes_once_in_sync() {
+        let store = IngressStore::default();
+        store.insert(ingress("default", "ignored"));
+        assert_eq!(store.list(None), None);
+
+        store.replace(Some(Ingresses::new()));
+        store.insert(ingress("default", "web"));
+        store.insert(ingress("tools", "grafana"));
+        store.insert(ingress("default", "api"));
+        assert_eq!(
+            names(store.list(None)),
+            Some(vec!["api".into(), "web".into(), "grafana".into()])
+        );
+        assert_eq!(
+            names(store.list(Some("tools"))),
+            Some(vec!["grafana".into()])
+        );
+
+        store.remove(&ingress("default", "web"));
+        assert_eq!(names(store.list(Some("default"))), Some(vec!["api".into()]));
+
+        store.replace(None);
+        assert_eq!(store.list(None), None);
+    }
+}
@@ -0,0 +1,272 @@
+//! Minimal server side of the WebSocket protocol (RFC 6455), enough to push text messages to
+//! browsers. Messages of the client are ignored, only pings and the closing handshake are handled.
+
+use std::io;
+
+use axum::{
+    body::Body,
+    http::{
+        HeaderMap, Request, StatusCode,
+        header::{
+            CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE,
+        },
+    },
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use ring::digest;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
+    sync::mpsc,
+};
+
+/// Appended to the key of the client to compute the accept header
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Larger frames of the client close the connection, the client is not expected to send data
+const MAX_INCOMING_PAYLOAD: u64 = 64 * 1024;
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+type Stream = TokioIo<Upgraded>;
+
+/// Control messages received from the client
+#[derive(Debug)]
+pub enum Incoming {
+    Ping(Vec<u8>),
+    /// The client closed the connection or it failed
+    Close,
+}
+
+/// An established connection
+pub struct WebSocket {
+    writer: WriteHalf<Stream>,
+    incoming: mpsc::Receiver<Incoming>,
+}
+
+impl WebSocket {
+    pub async fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.send_frame(OPCODE_TEXT, text.as_bytes()).await
+    }
+
+    pub async fn ping(&mut self) -> io::Result<()> {
+        self.send_frame(OPCODE_PING, &[]).await
+    }
+
+    pub async fn pong(&mut self, payload: &[u8]) -> io::Result<()> {
+        self.send_frame(OPCODE_PONG, payload).await
+    }
+
+    /// Sends the closing frame, errors are ignored as the connection is dropped anyway
+    pub async fn close(mut self) {
+        let _ = self.send_frame(OPCODE_CLOSE, &[]).await;
+        let _ = self.writer.shutdown().await;
+    }
+
+    /// Waits for the next control message of the client, can be used in `tokio::select!`
+    pub async fn receive(&mut self) -> Incoming {
+        self.incoming.recv().await.unwrap_or(Incoming::Close)
+    }
+
+    async fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        // Frames of the server are never fragmented or masked
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len @ 0..=125 => frame.push(len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        self.writer.write_all(&frame).await?;
+        self.writer.flush().await
+    }
+}
+
+/// Answers the handshake and calls the handler with the connection once the request was upgraded.
+/// Requests that are no valid WebSocket handshake are answered with `400 Bad Request`.
+pub fn upgrade<F, Fut>(mut req: Request<Body>, handler: F) -> Response
+where
+    F: FnOnce(WebSocket) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let Some(key) = handshake_key(req.headers()) else {
+        return (StatusCode::BAD_REQUEST, "Expected a WebSocket handshake").into_response();
+    };
+    let accept = accept_key(&key);
+    tokio::spawn(async move {
+        match hyper::upgrade::on(&mut req).await {
+            Ok(upgraded) => {
+                let (reader, writer) = tokio::io::split(TokioIo::new(upgraded));
+                let (sender, incoming) = mpsc::channel(8);
+                tokio::spawn(read_frames(reader, sender));
+                handler(WebSocket { writer, incoming }).await;
+            }
+            Err(err) => tracing::debug!("Upgrading the connection to a WebSocket failed: {err}"),
+        }
+    });
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(UPGRADE, "websocket")
+        .header(CONNECTION, "Upgrade")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Returns the key of the client if the headers request an upgrade to a WebSocket
+fn handshake_key(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let upgrade = header(UPGRADE)?;
+    let connection = header(CONNECTION)?;
+    if !upgrade.eq_ignore_ascii_case("websocket")
+        || !connection
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        || header(SEC_WEBSOCKET_VERSION) != Some("13")
+    {
+        return None;
+    }
+    header(SEC_WEBSOCKET_KEY).map(str::to_owned)
+}
+
+fn accept_key(key: &str) -> String {
+    // SHA-1 is required by the handshake, it is not used for anything security related
+    let digest = digest::digest(
+        &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{key}{HANDSHAKE_GUID}").as_bytes(),
+    );
+    BASE64_STANDARD.encode(digest)
+}
+
+/// Reads the frames of the client until the connection is closed, forwarding pings
+async fn read_frames(mut reader: ReadHalf<Stream>, sender: mpsc::Sender<Incoming>) {
+    loop {
+        let message = match read_frame(&mut reader).await {
+            Ok((OPCODE_PING, payload)) => Incoming::Ping(payload),
+            Ok((OPCODE_CLOSE, _)) => Incoming::Close,
+            // Data frames, continuations and pongs are not used
+            Ok(_) => continue,
+            Err(err) => {
+                tracing::debug!("Reading from WebSocket failed: {err}");
+                Incoming::Close
+            }
+        };
+        let close = matches!(message, Incoming::Close);
+        if sender.send(message).await.is_err() || close {
+            return;
+        }
+    }
+}
+
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header).await?;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7F {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        len => len as u64,
+    };
+    if !masked || len > MAX_INCOMING_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unmasked or too large frame",
+        ));
+    }
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await?;
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((opcode, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn handshake_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive, Upgrade"));
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        headers.insert(
+            SEC_WEBSOCKET_KEY,
+            HeaderValue::from_static("dGhlIHNhbXBsZSBub25jZQ=="),
+        );
+        headers
+    }
+
+    #[test]
+    fn computes_accept_key() {
+        // Example of RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn accepts_only_upgrade_requests() {
+        assert_eq!(
+            handshake_key(&handshake_headers()).as_deref(),
+            Some("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+
+        let mut no_upgrade = handshake_headers();
+        no_upgrade.remove(UPGRADE);
+        assert_eq!(handshake_key(&no_upgrade), None);
+
+        let mut keep_alive = handshake_headers();
+        keep_alive.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        assert_eq!(handshake_key(&keep_alive), None);
+
+        let mut old_version = handshake_headers();
+        old_version.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("8"));
+        assert_eq!(handshake_key(&old_version), None);
+    }
+
+    #[tokio::test]
+    async fn reads_masked_frames() {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x80 | OPCODE_PING, 0x80 | 2];
+        frame.extend_from_slice(&mask);
+        frame.extend([b'h' ^ mask[0], b'i' ^ mask[1]]);
+        let (opcode, payload) = read_frame(&mut frame.as_slice()).await.unwrap();
+        assert_eq!(opcode, OPCODE_PING);
+        assert_eq!(payload, b"hi");
+    }
+
+    #[tokio::test]
+    async fn rejects_unmasked_frames() {
+        let frame = [0x80 | OPCODE_TEXT, 2, b'h', b'i'];
+        let err = read_frame(&mut frame.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_frames() {
+        let mut frame = vec![0x80 | OPCODE_TEXT, 0x80 | 127];
+        frame.extend_from_slice(&(MAX_INCOMING_PAYLOAD + 1).to_be_bytes());
+        frame.extend_from_slice(&[0; 4]);
+        let err = read_frame(&mut frame.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}